@@ -0,0 +1,71 @@
+//! Allocation bitmaps for inodes and data blocks
+
+use alloc::sync::Arc;
+
+use crate::{block_cache::get_block_cache, BlockDevice, BLOCK_SZ};
+
+type BitmapBlock = [u64; BLOCK_SZ / 8];
+
+const BLOCK_BITS: usize = BLOCK_SZ * 8;
+
+/// `blocks` blocks starting at `start_block_id`, of which the first `len`
+/// bits are used
+pub struct Bitmap {
+    start_block_id: usize,
+    blocks: usize,
+    len: usize,
+}
+
+/// Return the block, the u64 within it and the bit within that holding `bit`.
+fn decomposition(mut bit: usize) -> (usize, usize, usize) {
+    let block_pos = bit / BLOCK_BITS;
+    bit %= BLOCK_BITS;
+    (block_pos, bit / 64, bit % 64)
+}
+
+impl Bitmap {
+    pub fn new(start_block_id: usize, blocks: usize, len: usize) -> Self {
+        assert!(len <= blocks * BLOCK_BITS);
+        Self {
+            start_block_id,
+            blocks,
+            len,
+        }
+    }
+
+    /// Set the first clear bit and return its index.
+    pub fn alloc(&self, block_device: &Arc<dyn BlockDevice>) -> Option<usize> {
+        for block_id in 0..self.blocks {
+            let pos = get_block_cache(block_id + self.start_block_id, block_device.clone())
+                .lock()
+                .modify(0, |bitmap_block: &mut BitmapBlock| {
+                    let (bits64_pos, bits64) = bitmap_block
+                        .iter()
+                        .enumerate()
+                        .find(|(_, bits64)| **bits64 != u64::MAX)?;
+                    let inner_pos = bits64.trailing_ones() as usize;
+                    let pos = block_id * BLOCK_BITS + bits64_pos * 64 + inner_pos;
+                    if pos >= self.len {
+                        return None;
+                    }
+                    bitmap_block[bits64_pos] |= 1u64 << inner_pos;
+                    Some(pos)
+                });
+            if pos.is_some() {
+                return pos;
+            }
+        }
+        None
+    }
+
+    /// Clear `bit`, which must be set.
+    pub fn dealloc(&self, block_device: &Arc<dyn BlockDevice>, bit: usize) {
+        let (block_pos, bits64_pos, inner_pos) = decomposition(bit);
+        get_block_cache(block_pos + self.start_block_id, block_device.clone())
+            .lock()
+            .modify(0, |bitmap_block: &mut BitmapBlock| {
+                assert!(bitmap_block[bits64_pos] & (1u64 << inner_pos) != 0);
+                bitmap_block[bits64_pos] &= !(1u64 << inner_pos);
+            });
+    }
+}
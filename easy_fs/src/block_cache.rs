@@ -0,0 +1,134 @@
+//! A small write-back cache of disk blocks
+
+use alloc::{collections::VecDeque, sync::Arc};
+use lazy_static::lazy_static;
+use spin::Mutex;
+
+use crate::{BlockDevice, BLOCK_SZ};
+
+/// One cached block. Changes are written back when the cache is evicted or
+/// synced.
+#[repr(C, align(8))]
+pub struct BlockCache {
+    /// aligned, so the on-disk structures can be read in place
+    cache: [u8; BLOCK_SZ],
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+    modified: bool,
+}
+
+impl BlockCache {
+    /// Load block `block_id` from `block_device`.
+    pub fn new(block_id: usize, block_device: Arc<dyn BlockDevice>) -> Self {
+        let mut cache = [0u8; BLOCK_SZ];
+        block_device.read_block(block_id, &mut cache);
+        Self {
+            cache,
+            block_id,
+            block_device,
+            modified: false,
+        }
+    }
+
+    fn addr_of_offset(&self, offset: usize) -> usize {
+        &self.cache[offset] as *const _ as usize
+    }
+
+    pub fn get_ref<T: Sized>(&self, offset: usize) -> &T {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        let addr = self.addr_of_offset(offset);
+        unsafe { &*(addr as *const T) }
+    }
+
+    pub fn get_mut<T: Sized>(&mut self, offset: usize) -> &mut T {
+        let type_size = core::mem::size_of::<T>();
+        assert!(offset + type_size <= BLOCK_SZ);
+        self.modified = true;
+        let addr = self.addr_of_offset(offset);
+        unsafe { &mut *(addr as *mut T) }
+    }
+
+    /// Run `f` on the `T` stored at `offset`.
+    pub fn read<T, V>(&self, offset: usize, f: impl FnOnce(&T) -> V) -> V {
+        f(self.get_ref(offset))
+    }
+
+    /// Run `f` on the `T` stored at `offset`, marking the block dirty.
+    pub fn modify<T, V>(&mut self, offset: usize, f: impl FnOnce(&mut T) -> V) -> V {
+        f(self.get_mut(offset))
+    }
+
+    pub fn sync(&mut self) {
+        if self.modified {
+            self.modified = false;
+            self.block_device.write_block(self.block_id, &self.cache);
+        }
+    }
+}
+
+impl Drop for BlockCache {
+    fn drop(&mut self) {
+        self.sync()
+    }
+}
+
+const BLOCK_CACHE_SIZE: usize = 16;
+
+pub struct BlockCacheManager {
+    queue: VecDeque<(usize, Arc<Mutex<BlockCache>>)>,
+}
+
+impl BlockCacheManager {
+    pub fn new() -> Self {
+        Self {
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Return the cache of `block_id`, loading it and evicting an unused
+    /// block if needed.
+    pub fn get_block_cache(
+        &mut self,
+        block_id: usize,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Arc<Mutex<BlockCache>> {
+        if let Some((_, cache)) = self.queue.iter().find(|(id, _)| *id == block_id) {
+            return cache.clone();
+        }
+        if self.queue.len() == BLOCK_CACHE_SIZE {
+            // evict the oldest block nobody is holding
+            let idx = self
+                .queue
+                .iter()
+                .position(|(_, cache)| Arc::strong_count(cache) == 1)
+                .expect("Run out of BlockCache!");
+            self.queue.remove(idx);
+        }
+        let block_cache = Arc::new(Mutex::new(BlockCache::new(block_id, block_device)));
+        self.queue.push_back((block_id, block_cache.clone()));
+        block_cache
+    }
+}
+
+lazy_static! {
+    pub static ref BLOCK_CACHE_MANAGER: Mutex<BlockCacheManager> =
+        Mutex::new(BlockCacheManager::new());
+}
+
+pub fn get_block_cache(
+    block_id: usize,
+    block_device: Arc<dyn BlockDevice>,
+) -> Arc<Mutex<BlockCache>> {
+    BLOCK_CACHE_MANAGER
+        .lock()
+        .get_block_cache(block_id, block_device)
+}
+
+/// Write every dirty cached block back to disk.
+pub fn block_cache_sync_all() {
+    let manager = BLOCK_CACHE_MANAGER.lock();
+    for (_, cache) in manager.queue.iter() {
+        cache.lock().sync();
+    }
+}
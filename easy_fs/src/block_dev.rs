@@ -0,0 +1,7 @@
+/// A device storing an array of [`BLOCK_SZ`](crate::BLOCK_SZ)-byte blocks
+pub trait BlockDevice: Send + Sync {
+    /// Read block `block_id` into `buf`, which holds exactly one block.
+    fn read_block(&self, block_id: usize, buf: &mut [u8]);
+    /// Write `buf`, which holds exactly one block, to block `block_id`.
+    fn write_block(&self, block_id: usize, buf: &[u8]);
+}
@@ -0,0 +1,174 @@
+//! Allocation of inodes and data blocks on the disk
+
+use alloc::sync::Arc;
+use spin::Mutex;
+
+use crate::{
+    bitmap::Bitmap,
+    block_cache::{block_cache_sync_all, get_block_cache},
+    layout::{DiskInode, DiskInodeType, SuperBlock},
+    vfs::Inode,
+    BlockDevice, BLOCK_SZ,
+};
+
+type DataBlock = [u8; BLOCK_SZ];
+
+pub struct EasyFileSystem {
+    pub block_device: Arc<dyn BlockDevice>,
+    pub inode_bitmap: Bitmap,
+    pub data_bitmap: Bitmap,
+    inode_area_start_block: u32,
+    data_area_start_block: u32,
+}
+
+impl EasyFileSystem {
+    /// Format `block_device` as a filesystem of `total_blocks` blocks with
+    /// room for `inode_bitmap_blocks * 4096` inodes, holding just an empty
+    /// root directory.
+    pub fn create(
+        block_device: Arc<dyn BlockDevice>,
+        total_blocks: u32,
+        inode_bitmap_blocks: u32,
+    ) -> Arc<Mutex<Self>> {
+        let inode_num = inode_bitmap_blocks as usize * BLOCK_SZ * 8;
+        let inode_area_blocks =
+            (inode_num * core::mem::size_of::<DiskInode>()).div_ceil(BLOCK_SZ) as u32;
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        let data_total_blocks = total_blocks - 1 - inode_total_blocks;
+        // each bitmap block covers itself and 4096 data blocks
+        let data_bitmap_blocks = data_total_blocks.div_ceil(4097);
+        let data_area_blocks = data_total_blocks - data_bitmap_blocks;
+        let mut efs = Self::with_layout(
+            block_device.clone(),
+            inode_bitmap_blocks,
+            inode_area_blocks,
+            data_bitmap_blocks,
+            data_area_blocks,
+        );
+        // clear all blocks
+        for i in 0..total_blocks {
+            get_block_cache(i as usize, block_device.clone())
+                .lock()
+                .modify(0, |data_block: &mut DataBlock| {
+                    data_block.fill(0);
+                });
+        }
+        get_block_cache(0, block_device.clone()).lock().modify(
+            0,
+            |super_block: &mut SuperBlock| {
+                super_block.initialize(
+                    total_blocks,
+                    inode_bitmap_blocks,
+                    inode_area_blocks,
+                    data_bitmap_blocks,
+                    data_area_blocks,
+                );
+            },
+        );
+        // the root directory is inode 0
+        assert_eq!(efs.alloc_inode(), Some(0));
+        let (root_inode_block_id, root_inode_offset) = efs.get_disk_inode_pos(0);
+        get_block_cache(root_inode_block_id as usize, block_device)
+            .lock()
+            .modify(root_inode_offset, |disk_inode: &mut DiskInode| {
+                disk_inode.initialize(DiskInodeType::Directory);
+            });
+        block_cache_sync_all();
+        Arc::new(Mutex::new(efs))
+    }
+
+    /// Open the filesystem on `block_device`, or return `None` if it was
+    /// never formatted.
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Option<Arc<Mutex<Self>>> {
+        get_block_cache(0, block_device.clone())
+            .lock()
+            .read(0, |super_block: &SuperBlock| {
+                if !super_block.is_valid() {
+                    return None;
+                }
+                let efs = Self::with_layout(
+                    block_device.clone(),
+                    super_block.inode_bitmap_blocks,
+                    super_block.inode_area_blocks,
+                    super_block.data_bitmap_blocks,
+                    super_block.data_area_blocks,
+                );
+                Some(Arc::new(Mutex::new(efs)))
+            })
+    }
+
+    fn with_layout(
+        block_device: Arc<dyn BlockDevice>,
+        inode_bitmap_blocks: u32,
+        inode_area_blocks: u32,
+        data_bitmap_blocks: u32,
+        data_area_blocks: u32,
+    ) -> Self {
+        let inode_num = inode_area_blocks as usize * BLOCK_SZ / core::mem::size_of::<DiskInode>();
+        let inode_total_blocks = inode_bitmap_blocks + inode_area_blocks;
+        Self {
+            block_device,
+            inode_bitmap: Bitmap::new(
+                1,
+                inode_bitmap_blocks as usize,
+                inode_num.min(inode_bitmap_blocks as usize * BLOCK_SZ * 8),
+            ),
+            data_bitmap: Bitmap::new(
+                (1 + inode_total_blocks) as usize,
+                data_bitmap_blocks as usize,
+                data_area_blocks as usize,
+            ),
+            inode_area_start_block: 1 + inode_bitmap_blocks,
+            data_area_start_block: 1 + inode_total_blocks + data_bitmap_blocks,
+        }
+    }
+
+    /// Return the root directory.
+    pub fn root_inode(efs: &Arc<Mutex<Self>>) -> Inode {
+        let block_device = efs.lock().block_device.clone();
+        let (block_id, block_offset) = efs.lock().get_disk_inode_pos(0);
+        Inode::new(0, block_id, block_offset, efs.clone(), block_device)
+    }
+
+    /// Return the block holding inode `inode_id`, and its offset there.
+    pub fn get_disk_inode_pos(&self, inode_id: u32) -> (u32, usize) {
+        let inode_size = core::mem::size_of::<DiskInode>();
+        let inodes_per_block = (BLOCK_SZ / inode_size) as u32;
+        let block_id = self.inode_area_start_block + inode_id / inodes_per_block;
+        (
+            block_id,
+            (inode_id % inodes_per_block) as usize * inode_size,
+        )
+    }
+
+    pub fn alloc_inode(&mut self) -> Option<u32> {
+        self.inode_bitmap
+            .alloc(&self.block_device)
+            .map(|id| id as u32)
+    }
+
+    pub fn dealloc_inode(&mut self, inode_id: u32) {
+        self.inode_bitmap
+            .dealloc(&self.block_device, inode_id as usize)
+    }
+
+    /// Allocate a data block and return its block id.
+    pub fn alloc_data(&mut self) -> Option<u32> {
+        self.data_bitmap
+            .alloc(&self.block_device)
+            .map(|id| id as u32 + self.data_area_start_block)
+    }
+
+    /// Zero the data block `block_id` and free it.
+    pub fn dealloc_data(&mut self, block_id: u32) {
+        get_block_cache(block_id as usize, self.block_device.clone())
+            .lock()
+            .modify(0, |data_block: &mut DataBlock| {
+                data_block.fill(0);
+            });
+        self.data_bitmap.dealloc(
+            &self.block_device,
+            (block_id - self.data_area_start_block) as usize,
+        )
+    }
+}
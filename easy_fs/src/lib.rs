@@ -0,0 +1,29 @@
+//! An inode-based filesystem on a block device
+//!
+//! The disk is laid out as
+//!
+//! ```text
+//! | super block | inode bitmap | inode area | data bitmap | data area |
+//! ```
+//!
+//! All disk accesses go through the block cache in [`block_cache`]. The crate
+//! is `no_std`, so the kernel and the host-side packer share it.
+
+#![no_std]
+
+extern crate alloc;
+
+mod bitmap;
+mod block_cache;
+mod block_dev;
+mod efs;
+mod layout;
+mod vfs;
+
+/// Size in bytes of a disk block
+pub const BLOCK_SZ: usize = 512;
+
+pub use block_cache::block_cache_sync_all;
+pub use block_dev::BlockDevice;
+pub use efs::EasyFileSystem;
+pub use vfs::Inode;
@@ -0,0 +1,378 @@
+//! Inodes as seen by the users of the filesystem
+
+use alloc::{string::String, sync::Arc, vec, vec::Vec};
+use spin::{Mutex, MutexGuard};
+
+use crate::{
+    block_cache::{block_cache_sync_all, get_block_cache},
+    efs::EasyFileSystem,
+    layout::{DirEntry, DiskInode, DiskInodeType, DIRENT_SZ},
+    BlockDevice,
+};
+
+/// A handle on an inode, which stays valid while the file exists
+pub struct Inode {
+    inode_id: u32,
+    block_id: usize,
+    block_offset: usize,
+    fs: Arc<Mutex<EasyFileSystem>>,
+    block_device: Arc<dyn BlockDevice>,
+}
+
+impl Inode {
+    pub fn new(
+        inode_id: u32,
+        block_id: u32,
+        block_offset: usize,
+        fs: Arc<Mutex<EasyFileSystem>>,
+        block_device: Arc<dyn BlockDevice>,
+    ) -> Self {
+        Self {
+            inode_id,
+            block_id: block_id as usize,
+            block_offset,
+            fs,
+            block_device,
+        }
+    }
+
+    fn read_disk_inode<V>(&self, f: impl FnOnce(&DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, self.block_device.clone())
+            .lock()
+            .read(self.block_offset, f)
+    }
+
+    fn modify_disk_inode<V>(&self, f: impl FnOnce(&mut DiskInode) -> V) -> V {
+        get_block_cache(self.block_id, self.block_device.clone())
+            .lock()
+            .modify(self.block_offset, f)
+    }
+
+    fn find_inode_id(&self, name: &str, disk_inode: &DiskInode) -> Option<u32> {
+        // assert it is a directory
+        assert!(disk_inode.is_dir());
+        let file_count = disk_inode.size as usize / DIRENT_SZ;
+        let mut dirent = DirEntry::empty();
+        for i in 0..file_count {
+            assert_eq!(
+                disk_inode.read_at(DIRENT_SZ * i, dirent.as_bytes_mut(), &self.block_device),
+                DIRENT_SZ,
+            );
+            if dirent.name() == name {
+                return Some(dirent.inode_number());
+            }
+        }
+        None
+    }
+
+    /// Run `f` on the disk inode `inode_id`, which must not be locked by the
+    /// caller already.
+    fn modify_disk_inode_of<V>(
+        &self,
+        fs: &EasyFileSystem,
+        inode_id: u32,
+        f: impl FnOnce(&mut DiskInode) -> V,
+    ) -> V {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        get_block_cache(block_id as usize, self.block_device.clone())
+            .lock()
+            .modify(block_offset, f)
+    }
+
+    fn inode_of(&self, fs: &EasyFileSystem, inode_id: u32) -> Arc<Inode> {
+        let (block_id, block_offset) = fs.get_disk_inode_pos(inode_id);
+        Arc::new(Self::new(
+            inode_id,
+            block_id,
+            block_offset,
+            self.fs.clone(),
+            self.block_device.clone(),
+        ))
+    }
+
+    /// Look `name` up in this directory.
+    pub fn find(&self, name: &str) -> Option<Arc<Inode>> {
+        let fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            if !disk_inode.is_dir() {
+                return None;
+            }
+            self.find_inode_id(name, disk_inode)
+                .map(|inode_id| self.inode_of(&fs, inode_id))
+        })
+    }
+
+    /// Grow `disk_inode` to `new_size` bytes, returning false if the disk is
+    /// full.
+    fn increase_size(
+        &self,
+        new_size: u32,
+        disk_inode: &mut DiskInode,
+        fs: &mut MutexGuard<EasyFileSystem>,
+    ) -> bool {
+        if new_size < disk_inode.size {
+            return true;
+        }
+        let blocks_needed = disk_inode.blocks_num_needed(new_size);
+        let mut v: Vec<u32> = Vec::new();
+        for _ in 0..blocks_needed {
+            match fs.alloc_data() {
+                Some(block_id) => v.push(block_id),
+                None => {
+                    for block_id in v {
+                        fs.dealloc_data(block_id);
+                    }
+                    return false;
+                }
+            }
+        }
+        disk_inode.increase_size(new_size, v, &self.block_device);
+        true
+    }
+
+    /// Append an entry naming `inode_id` to this directory, returning false
+    /// if `name` exists already, does not fit or the disk is full.
+    fn add_entry(&self, name: &str, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) -> bool {
+        let Some(dirent) = DirEntry::new(name, inode_id) else {
+            return false;
+        };
+        self.modify_disk_inode(|dir_inode| {
+            if self.find_inode_id(name, dir_inode).is_some() {
+                return false;
+            }
+            let file_count = dir_inode.size as usize / DIRENT_SZ;
+            let new_size = (file_count + 1) * DIRENT_SZ;
+            if !self.increase_size(new_size as u32, dir_inode, fs) {
+                return false;
+            }
+            dir_inode.write_at(
+                file_count * DIRENT_SZ,
+                dirent.as_bytes(),
+                &self.block_device,
+            );
+            true
+        })
+    }
+
+    /// Remove the entry `name` from this directory, returning the inode it
+    /// named.
+    fn remove_entry(&self, name: &str, fs: &mut MutexGuard<EasyFileSystem>) -> Option<u32> {
+        self.modify_disk_inode(|dir_inode| {
+            let inode_id = self.find_inode_id(name, dir_inode)?;
+            // rewrite the directory without the entry, which frees any block
+            // it no longer needs
+            let mut dirents = vec![0u8; dir_inode.size as usize];
+            dir_inode.read_at(0, &mut dirents, &self.block_device);
+            let dirents: Vec<u8> = dirents
+                .chunks(DIRENT_SZ)
+                .filter(|bytes| {
+                    let mut dirent = DirEntry::empty();
+                    dirent.as_bytes_mut().copy_from_slice(bytes);
+                    dirent.name() != name
+                })
+                .flatten()
+                .copied()
+                .collect();
+            for block_id in dir_inode.clear_size(&self.block_device) {
+                fs.dealloc_data(block_id);
+            }
+            assert!(self.increase_size(dirents.len() as u32, dir_inode, fs));
+            dir_inode.write_at(0, &dirents, &self.block_device);
+            Some(inode_id)
+        })
+    }
+
+    /// Drop a link to `inode_id`, freeing it once there are none left.
+    fn drop_link(&self, inode_id: u32, fs: &mut MutexGuard<EasyFileSystem>) {
+        let freed_blocks = self.modify_disk_inode_of(fs, inode_id, |disk_inode| {
+            disk_inode.nlink -= 1;
+            (disk_inode.nlink == 0).then(|| disk_inode.clear_size(&self.block_device))
+        });
+        if let Some(blocks) = freed_blocks {
+            for block_id in blocks {
+                fs.dealloc_data(block_id);
+            }
+            fs.dealloc_inode(inode_id);
+        }
+    }
+
+    fn create_inode(&self, name: &str, type_: DiskInodeType) -> Option<Arc<Inode>> {
+        let mut fs = self.fs.lock();
+        if self.read_disk_inode(|dir_inode| self.find_inode_id(name, dir_inode).is_some()) {
+            return None;
+        }
+        // reject names that do not fit before allocating anything
+        DirEntry::new(name, 0)?;
+        let new_inode_id = fs.alloc_inode()?;
+        self.modify_disk_inode_of(&fs, new_inode_id, |new_inode| {
+            new_inode.initialize(type_);
+        });
+        if !self.add_entry(name, new_inode_id, &mut fs) {
+            fs.dealloc_inode(new_inode_id);
+            return None;
+        }
+        block_cache_sync_all();
+        Some(self.inode_of(&fs, new_inode_id))
+    }
+
+    /// Create the empty file `name` in this directory, returning `None` if
+    /// it already exists or there is no room.
+    pub fn create(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::File)
+    }
+
+    /// Create the empty directory `name` in this directory, returning `None`
+    /// if it already exists or there is no room.
+    pub fn mkdir(&self, name: &str) -> Option<Arc<Inode>> {
+        self.create_inode(name, DiskInodeType::Directory)
+    }
+
+    /// Add `inode` to this directory as `name`, returning false if `name`
+    /// exists already or there is no room.
+    pub fn link(&self, name: &str, inode: &Inode) -> bool {
+        let mut fs = self.fs.lock();
+        if !self.add_entry(name, inode.inode_id, &mut fs) {
+            return false;
+        }
+        inode.modify_disk_inode(|disk_inode| disk_inode.nlink += 1);
+        block_cache_sync_all();
+        true
+    }
+
+    /// Remove `name` from this directory, freeing the inode if that was its
+    /// last link. Returns false if there is no `name` or it is a directory
+    /// that is not empty.
+    ///
+    /// Files still open are freed all the same, so unlink them only once
+    /// they are closed.
+    pub fn unlink(&self, name: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let Some(inode_id) = self.read_disk_inode(|dir_inode| {
+            dir_inode
+                .is_dir()
+                .then(|| self.find_inode_id(name, dir_inode))
+                .flatten()
+        }) else {
+            return false;
+        };
+        let busy = self.modify_disk_inode_of(&fs, inode_id, |disk_inode| {
+            disk_inode.is_dir() && disk_inode.size > 0
+        });
+        if busy {
+            return false;
+        }
+        self.remove_entry(name, &mut fs);
+        self.drop_link(inode_id, &mut fs);
+        block_cache_sync_all();
+        true
+    }
+
+    /// Move `old_name` in this directory to `new_name` in `new_dir`,
+    /// replacing any file there. Returns false if there is no `old_name`,
+    /// `new_name` is a directory, or there is no room.
+    pub fn rename(&self, old_name: &str, new_dir: &Inode, new_name: &str) -> bool {
+        let mut fs = self.fs.lock();
+        let Some(inode_id) = self.read_disk_inode(|dir_inode| {
+            dir_inode
+                .is_dir()
+                .then(|| self.find_inode_id(old_name, dir_inode))
+                .flatten()
+        }) else {
+            return false;
+        };
+        let replaced = new_dir.read_disk_inode(|dir_inode| {
+            dir_inode
+                .is_dir()
+                .then(|| new_dir.find_inode_id(new_name, dir_inode))
+        });
+        match replaced {
+            None => return false,
+            Some(Some(old_id)) if old_id == inode_id => return true,
+            Some(Some(old_id)) => {
+                if self.modify_disk_inode_of(&fs, old_id, |disk_inode| disk_inode.is_dir()) {
+                    return false;
+                }
+                new_dir.remove_entry(new_name, &mut fs);
+                self.drop_link(old_id, &mut fs);
+            }
+            Some(None) => {}
+        }
+        if !new_dir.add_entry(new_name, inode_id, &mut fs) {
+            block_cache_sync_all();
+            return false;
+        }
+        self.remove_entry(old_name, &mut fs);
+        block_cache_sync_all();
+        true
+    }
+
+    /// Return the names in this directory.
+    pub fn ls(&self) -> Vec<String> {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| {
+            let file_count = disk_inode.size as usize / DIRENT_SZ;
+            let mut v: Vec<String> = Vec::new();
+            for i in 0..file_count {
+                let mut dirent = DirEntry::empty();
+                assert_eq!(
+                    disk_inode.read_at(i * DIRENT_SZ, dirent.as_bytes_mut(), &self.block_device),
+                    DIRENT_SZ,
+                );
+                v.push(String::from(dirent.name()));
+            }
+            v
+        })
+    }
+
+    /// Read from `offset` into `buf`, returning how many bytes were read.
+    pub fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let _fs = self.fs.lock();
+        self.read_disk_inode(|disk_inode| disk_inode.read_at(offset, buf, &self.block_device))
+    }
+
+    /// Write `buf` at `offset`, growing the file as needed. Returns how many
+    /// bytes were written, which is 0 if the disk is full.
+    pub fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let mut fs = self.fs.lock();
+        let size = self.modify_disk_inode(|disk_inode| {
+            if !self.increase_size((offset + buf.len()) as u32, disk_inode, &mut fs) {
+                return 0;
+            }
+            disk_inode.write_at(offset, buf, &self.block_device)
+        });
+        block_cache_sync_all();
+        size
+    }
+
+    /// Truncate to zero size.
+    pub fn clear(&self) {
+        let mut fs = self.fs.lock();
+        self.modify_disk_inode(|disk_inode| {
+            let size = disk_inode.size;
+            let data_blocks_dealloc = disk_inode.clear_size(&self.block_device);
+            assert!(data_blocks_dealloc.len() == DiskInode::total_blocks(size) as usize);
+            for data_block in data_blocks_dealloc.into_iter() {
+                fs.dealloc_data(data_block);
+            }
+        });
+        block_cache_sync_all();
+    }
+
+    /// Return the size of the contents in bytes.
+    pub fn size(&self) -> usize {
+        self.read_disk_inode(|disk_inode| disk_inode.size as usize)
+    }
+
+    pub fn inode_id(&self) -> u32 {
+        self.inode_id
+    }
+
+    /// Return the number of directory entries naming this inode.
+    pub fn nlink(&self) -> u32 {
+        self.read_disk_inode(|disk_inode| disk_inode.nlink)
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.read_disk_inode(|disk_inode| disk_inode.is_dir())
+    }
+}
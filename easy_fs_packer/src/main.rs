@@ -0,0 +1,167 @@
+//! Pack the built user apps into an easy-fs disk image
+//!
+//! This runs on the host: `easy_fs_packer -s <app sources> -t <app ELFs>`
+//! formats `<app ELFs>/fs.img` and copies in every ELF with a source file in
+//! `<app sources>`, named after it.
+
+use std::{
+    fs::{read_dir, File, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+    process::exit,
+    sync::{Arc, Mutex},
+};
+
+use easy_fs::{BlockDevice, EasyFileSystem, BLOCK_SZ};
+
+/// 16 MiB
+const TOTAL_BLOCKS: u32 = 16 * 2048;
+/// room for 4096 inodes
+const INODE_BITMAP_BLOCKS: u32 = 1;
+
+/// A disk image file
+struct BlockFile(Mutex<File>);
+
+impl BlockDevice for BlockFile {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.read(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        let mut file = self.0.lock().unwrap();
+        file.seek(SeekFrom::Start((block_id * BLOCK_SZ) as u64))
+            .expect("Error when seeking!");
+        assert_eq!(file.write(buf).unwrap(), BLOCK_SZ, "Not a complete block!");
+    }
+}
+
+/// Create an image of `TOTAL_BLOCKS` blocks at `path` and format it.
+fn create_image(path: &Path) -> std::io::Result<Arc<easy_fs::Inode>> {
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(path)?;
+    file.set_len(TOTAL_BLOCKS as u64 * BLOCK_SZ as u64)?;
+    let block_file: Arc<dyn BlockDevice> = Arc::new(BlockFile(Mutex::new(file)));
+    let efs = EasyFileSystem::create(block_file, TOTAL_BLOCKS, INODE_BITMAP_BLOCKS);
+    Ok(Arc::new(EasyFileSystem::root_inode(&efs)))
+}
+
+/// Return the app names, which are the file stems in `src_dir`.
+fn app_names(src_dir: &Path) -> std::io::Result<Vec<String>> {
+    let mut apps = Vec::new();
+    for entry in read_dir(src_dir)? {
+        let path = entry?.path();
+        if let Some(stem) = path.file_stem().and_then(|stem| stem.to_str()) {
+            apps.push(String::from(stem));
+        }
+    }
+    apps.sort();
+    Ok(apps)
+}
+
+fn pack(src_dir: &Path, target_dir: &Path) -> std::io::Result<()> {
+    let root_inode = create_image(&target_dir.join("fs.img"))?;
+    for app in app_names(src_dir)? {
+        let mut elf_data = Vec::new();
+        File::open(target_dir.join(&app))?.read_to_end(&mut elf_data)?;
+        let inode = root_inode
+            .create(&app)
+            .unwrap_or_else(|| panic!("cannot create {}", app));
+        assert_eq!(
+            inode.write_at(0, &elf_data),
+            elf_data.len(),
+            "disk full at {}",
+            app
+        );
+        println!("{}: {} bytes", app, elf_data.len());
+    }
+    Ok(())
+}
+
+fn usage() -> ! {
+    eprintln!("usage: easy_fs_packer -s <app source dir> -t <app target dir>");
+    exit(1)
+}
+
+fn main() {
+    let mut src_dir = None;
+    let mut target_dir = None;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-s" => src_dir = args.next().map(PathBuf::from),
+            "-t" => target_dir = args.next().map(PathBuf::from),
+            _ => usage(),
+        }
+    }
+    let (Some(src_dir), Some(target_dir)) = (src_dir, target_dir) else {
+        usage();
+    };
+    if let Err(err) = pack(&src_dir, &target_dir) {
+        eprintln!("easy_fs_packer: {}", err);
+        exit(1);
+    }
+}
+
+#[test]
+fn efs_test() -> std::io::Result<()> {
+    let path = std::env::temp_dir().join(format!("efs_test_{}.img", std::process::id()));
+    let root_inode = create_image(&path)?;
+    root_inode.create("filea").unwrap();
+    root_inode.create("fileb").unwrap();
+    assert!(root_inode.create("filea").is_none());
+    assert!(root_inode.create("a_name_longer_than_the_limit").is_none());
+    assert_eq!(root_inode.ls(), ["filea", "fileb"]);
+
+    let filea = root_inode.find("filea").unwrap();
+    let greet_str = "Hello, world!";
+    filea.write_at(0, greet_str.as_bytes());
+    let mut buffer = [0u8; 233];
+    let len = filea.read_at(0, &mut buffer);
+    assert_eq!(greet_str.as_bytes(), &buffer[..len]);
+
+    // sizes that end in the direct, indirect1 and indirect2 blocks
+    for len in [
+        BLOCK_SZ / 2,
+        BLOCK_SZ * 30 + 7,
+        BLOCK_SZ * 300 + 13,
+        BLOCK_SZ * 1000,
+    ] {
+        filea.clear();
+        assert_eq!(filea.read_at(0, &mut buffer), 0);
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+        // write in odd-sized chunks
+        for (i, chunk) in data.chunks(333).enumerate() {
+            assert_eq!(filea.write_at(i * 333, chunk), chunk.len());
+        }
+        assert_eq!(filea.size(), len);
+        let mut read_back = vec![0u8; len];
+        assert_eq!(filea.read_at(0, &mut read_back), len);
+        assert_eq!(data, read_back);
+    }
+
+    // directories and links
+    let dir = root_inode.mkdir("dir").unwrap();
+    assert!(dir.is_dir());
+    assert!(dir.link("link", &filea));
+    assert_eq!(filea.nlink(), 2);
+    assert!(!root_inode.unlink("dir"));
+    assert!(root_inode.rename("fileb", &dir, "link"));
+    assert_eq!(filea.nlink(), 1);
+    assert_eq!(dir.ls(), ["link"]);
+    assert_eq!(root_inode.ls(), ["filea", "dir"]);
+    assert!(dir.unlink("link"));
+    assert!(root_inode.unlink("dir"));
+    // the last link frees the inode and its blocks for reuse
+    assert!(root_inode.unlink("filea"));
+    assert!(root_inode.find("filea").is_none());
+    let inode_id = filea.inode_id();
+    assert_eq!(root_inode.create("filec").unwrap().inode_id(), inode_id);
+    std::fs::remove_file(path)
+}
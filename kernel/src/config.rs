@@ -5,7 +5,17 @@ pub const MEMORY_END: usize = 0x80800000; // 8 MiB
 pub const USER_STACK_SIZE: usize = 0x4000; // 32 KiB
 pub const KERNEL_STACK_SIZE: usize = 0x4000; // 32 KiB
 pub const TRAMPOLINE: usize = usize::MAX - PAGE_SIZE + 1;
-pub const TRAP_CONTEXT: usize = TRAMPOLINE - PAGE_SIZE;
-pub const MAX_APP_NUM: usize = 16;
+/// the trap context of thread 0, with that of thread `tid` `tid` pages below
+pub const TRAP_CONTEXT_BASE: usize = TRAMPOLINE - PAGE_SIZE;
+/// user mappings must stay in the lower half of the Sv48 address space
+pub const USER_SPACE_END: usize = 1 << 47;
+/// the user stack of thread 0 ends here, leaving the heap room to grow below
+/// it; those of the other threads follow downwards, each below a guard page
+pub const USER_STACK_TOP: usize = 0x1_0000_0000;
 pub const CLOCK_FREQ: u64 = 12500000; // 12.5 MHz
 pub const TICKS_PER_SEC: u64 = 100;
+
+/// the first virtio-mmio slot of QEMU virt, where the disk is attached
+pub const VIRTIO0: usize = 0x1000_1000;
+/// device register ranges the kernel maps identically, as (start, len)
+pub const MMIO: &[(usize, usize)] = &[(VIRTIO0, 0x1000)];
@@ -0,0 +1,45 @@
+//! Block devices
+
+mod virtio_blk;
+
+use alloc::sync::Arc;
+use lazy_static::lazy_static;
+use log::info;
+
+use crate::config::VIRTIO0;
+
+pub use easy_fs::{BlockDevice, BLOCK_SZ};
+pub use virtio_blk::VirtIOBlock;
+
+lazy_static! {
+    /// The disk, if QEMU was started with one
+    pub static ref BLOCK_DEVICE: Option<Arc<VirtIOBlock>> = VirtIOBlock::probe(VIRTIO0).map(Arc::new);
+}
+
+pub fn init() {
+    match BLOCK_DEVICE.as_ref() {
+        Some(device) => info!("virtio-blk: {} blocks", device.num_blocks()),
+        None => info!("virtio-blk: no device at {:#x}", VIRTIO0),
+    }
+}
+
+#[test_case]
+fn block_device_test() {
+    let Some(device) = BLOCK_DEVICE.as_ref() else {
+        println!("block_device_test skipped, no disk");
+        return;
+    };
+    let block_id = device.num_blocks() - 1;
+    let mut saved = [0u8; BLOCK_SZ];
+    device.read_block(block_id, &mut saved);
+    let mut buf = [0u8; BLOCK_SZ];
+    for (i, byte) in buf.iter_mut().enumerate() {
+        *byte = i as u8 ^ 0x5a;
+    }
+    device.write_block(block_id, &buf);
+    let mut read_back = [0u8; BLOCK_SZ];
+    device.read_block(block_id, &mut read_back);
+    assert_eq!(buf, read_back);
+    device.write_block(block_id, &saved);
+    println!("block_device_test passed!");
+}
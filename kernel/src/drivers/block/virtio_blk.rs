@@ -0,0 +1,307 @@
+//! A polling virtio-mmio block driver
+//!
+//! Both the legacy (version 1) and the modern (version 2) virtio-mmio
+//! interfaces are supported. Requests are issued one at a time: every request
+//! is a chain of three descriptors (header, data, status), and the driver
+//! spins on the used ring until the device completes it. All DMA goes through
+//! a single frame holding the queue and a bounce buffer, so callers may pass
+//! buffers from anywhere in the kernel address space.
+
+use core::{
+    mem::size_of,
+    ptr::{read_volatile, write_volatile},
+    sync::atomic::{fence, Ordering},
+};
+
+use super::{BlockDevice, BLOCK_SZ};
+use crate::{
+    memory::{
+        address::{PhysAddr, PAGE_SIZE},
+        frame_alloc, FrameTracker,
+    },
+    sync::SpinLock,
+};
+
+// virtio-mmio register offsets
+const MAGIC_VALUE: usize = 0x000;
+const VERSION: usize = 0x004;
+const DEVICE_ID: usize = 0x008;
+const DEVICE_FEATURES_SEL: usize = 0x014;
+const DEVICE_FEATURES: usize = 0x010;
+const DRIVER_FEATURES: usize = 0x020;
+const DRIVER_FEATURES_SEL: usize = 0x024;
+const GUEST_PAGE_SIZE: usize = 0x028;
+const QUEUE_SEL: usize = 0x030;
+const QUEUE_NUM_MAX: usize = 0x034;
+const QUEUE_NUM: usize = 0x038;
+const QUEUE_ALIGN: usize = 0x03c;
+const QUEUE_PFN: usize = 0x040;
+const QUEUE_READY: usize = 0x044;
+const QUEUE_NOTIFY: usize = 0x050;
+const INTERRUPT_STATUS: usize = 0x060;
+const INTERRUPT_ACK: usize = 0x064;
+const STATUS: usize = 0x070;
+const QUEUE_DESC_LOW: usize = 0x080;
+const QUEUE_DESC_HIGH: usize = 0x084;
+const QUEUE_DRIVER_LOW: usize = 0x090;
+const QUEUE_DRIVER_HIGH: usize = 0x094;
+const QUEUE_DEVICE_LOW: usize = 0x0a0;
+const QUEUE_DEVICE_HIGH: usize = 0x0a4;
+/// `capacity` in 512-byte sectors, the first field of the block config space
+const CONFIG_CAPACITY: usize = 0x100;
+
+const MAGIC: u32 = 0x7472_6976; // "virt"
+const DEVICE_ID_BLOCK: u32 = 2;
+
+// device status bits
+const STATUS_ACKNOWLEDGE: u32 = 1;
+const STATUS_DRIVER: u32 = 2;
+const STATUS_DRIVER_OK: u32 = 4;
+const STATUS_FEATURES_OK: u32 = 8;
+
+/// feature bit 32 (bit 0 of feature word 1), which a modern driver has to
+/// accept
+const VIRTIO_F_VERSION_1: u32 = 1 << 0;
+
+const VIRTQ_DESC_F_NEXT: u16 = 1;
+const VIRTQ_DESC_F_WRITE: u16 = 2;
+
+const VIRTIO_BLK_T_IN: u32 = 0;
+const VIRTIO_BLK_T_OUT: u32 = 1;
+const VIRTIO_BLK_S_OK: u8 = 0;
+
+const QUEUE_SIZE: usize = 8;
+
+// layout of the DMA frame
+const DESC_OFFSET: usize = 0x000;
+const AVAIL_OFFSET: usize = DESC_OFFSET + QUEUE_SIZE * size_of::<VirtqDesc>();
+/// also the alignment handed to legacy devices
+const USED_OFFSET: usize = 0x100;
+const HEADER_OFFSET: usize = 0x200;
+const STATUS_OFFSET: usize = HEADER_OFFSET + size_of::<BlockRequestHeader>();
+const DATA_OFFSET: usize = 0x400;
+
+#[repr(C)]
+struct VirtqDesc {
+    addr: u64,
+    len: u32,
+    flags: u16,
+    next: u16,
+}
+
+#[repr(C)]
+struct VirtqAvail {
+    flags: u16,
+    idx: u16,
+    ring: [u16; QUEUE_SIZE],
+}
+
+#[repr(C)]
+struct VirtqUsedElem {
+    id: u32,
+    len: u32,
+}
+
+#[repr(C)]
+struct VirtqUsed {
+    flags: u16,
+    idx: u16,
+    ring: [VirtqUsedElem; QUEUE_SIZE],
+}
+
+#[repr(C)]
+struct BlockRequestHeader {
+    req_type: u32,
+    reserved: u32,
+    sector: u64,
+}
+
+pub struct VirtIOBlock {
+    inner: SpinLock<VirtIOBlockInner>,
+    capacity: usize,
+}
+
+struct VirtIOBlockInner {
+    base: usize,
+    /// queue, request header, status byte and bounce buffer
+    dma: FrameTracker,
+    /// `idx` of the used ring last time we looked
+    last_used: u16,
+}
+
+fn read_reg(base: usize, offset: usize) -> u32 {
+    unsafe { read_volatile((base + offset) as *const u32) }
+}
+
+fn write_reg(base: usize, offset: usize, value: u32) {
+    unsafe { write_volatile((base + offset) as *mut u32, value) }
+}
+
+impl VirtIOBlock {
+    /// Set up the virtio-mmio block device at `base`, or return `None` if
+    /// there is none.
+    pub fn probe(base: usize) -> Option<Self> {
+        if read_reg(base, MAGIC_VALUE) != MAGIC || read_reg(base, DEVICE_ID) != DEVICE_ID_BLOCK {
+            return None;
+        }
+        let version = read_reg(base, VERSION);
+        if version != 1 && version != 2 {
+            return None;
+        }
+        // reset, then tell the device we know how to drive it
+        write_reg(base, STATUS, 0);
+        let mut status = STATUS_ACKNOWLEDGE | STATUS_DRIVER;
+        write_reg(base, STATUS, status);
+        // no optional features are needed, apart from the modern interface
+        write_reg(base, DRIVER_FEATURES_SEL, 0);
+        write_reg(base, DRIVER_FEATURES, 0);
+        if version == 2 {
+            write_reg(base, DEVICE_FEATURES_SEL, 1);
+            if read_reg(base, DEVICE_FEATURES) & VIRTIO_F_VERSION_1 == 0 {
+                return None;
+            }
+            write_reg(base, DRIVER_FEATURES_SEL, 1);
+            write_reg(base, DRIVER_FEATURES, VIRTIO_F_VERSION_1);
+            status |= STATUS_FEATURES_OK;
+            write_reg(base, STATUS, status);
+            if read_reg(base, STATUS) & STATUS_FEATURES_OK == 0 {
+                return None;
+            }
+        } else {
+            write_reg(base, GUEST_PAGE_SIZE, PAGE_SIZE as u32);
+        }
+
+        // the queue lives in a zeroed frame, which the kernel maps identically
+        write_reg(base, QUEUE_SEL, 0);
+        if (read_reg(base, QUEUE_NUM_MAX) as usize) < QUEUE_SIZE {
+            return None;
+        }
+        write_reg(base, QUEUE_NUM, QUEUE_SIZE as u32);
+        let dma = frame_alloc().expect("no frame for virtio-blk");
+        let dma_pa = usize::from(PhysAddr::from(dma.ppn));
+        if version == 2 {
+            for (low, high, offset) in [
+                (QUEUE_DESC_LOW, QUEUE_DESC_HIGH, DESC_OFFSET),
+                (QUEUE_DRIVER_LOW, QUEUE_DRIVER_HIGH, AVAIL_OFFSET),
+                (QUEUE_DEVICE_LOW, QUEUE_DEVICE_HIGH, USED_OFFSET),
+            ] {
+                let pa = (dma_pa + offset) as u64;
+                write_reg(base, low, pa as u32);
+                write_reg(base, high, (pa >> 32) as u32);
+            }
+            write_reg(base, QUEUE_READY, 1);
+        } else {
+            write_reg(base, QUEUE_ALIGN, USED_OFFSET as u32);
+            write_reg(base, QUEUE_PFN, usize::from(dma.ppn) as u32);
+        }
+
+        status |= STATUS_DRIVER_OK;
+        write_reg(base, STATUS, status);
+        let capacity = unsafe { read_volatile((base + CONFIG_CAPACITY) as *const u64) } as usize;
+        Some(Self {
+            inner: SpinLock::new(
+                "VirtIOBlock::inner",
+                VirtIOBlockInner {
+                    base,
+                    dma,
+                    last_used: 0,
+                },
+            ),
+            capacity,
+        })
+    }
+
+    /// Return how many blocks the disk holds.
+    pub fn num_blocks(&self) -> usize {
+        self.capacity
+    }
+}
+
+impl VirtIOBlockInner {
+    fn dma_ptr<T>(&self, offset: usize) -> *mut T {
+        (usize::from(PhysAddr::from(self.dma.ppn)) + offset) as *mut T
+    }
+
+    fn set_desc(&self, i: usize, offset: usize, len: usize, flags: u16) {
+        let desc = VirtqDesc {
+            addr: self.dma_ptr::<u8>(offset) as u64,
+            len: len as u32,
+            flags,
+            next: (i + 1) as u16,
+        };
+        unsafe { write_volatile(self.dma_ptr::<VirtqDesc>(DESC_OFFSET).add(i), desc) };
+    }
+
+    /// Submit a request for the sector `sector` with the data already in the
+    /// bounce buffer, and wait for the device to complete it.
+    fn request(&mut self, req_type: u32, sector: usize) {
+        let header = BlockRequestHeader {
+            req_type,
+            reserved: 0,
+            sector: sector as u64,
+        };
+        unsafe {
+            write_volatile(self.dma_ptr(HEADER_OFFSET), header);
+            write_volatile(self.dma_ptr::<u8>(STATUS_OFFSET), 0xff);
+        }
+        let data_flags = if req_type == VIRTIO_BLK_T_IN {
+            VIRTQ_DESC_F_NEXT | VIRTQ_DESC_F_WRITE
+        } else {
+            VIRTQ_DESC_F_NEXT
+        };
+        self.set_desc(
+            0,
+            HEADER_OFFSET,
+            size_of::<BlockRequestHeader>(),
+            VIRTQ_DESC_F_NEXT,
+        );
+        self.set_desc(1, DATA_OFFSET, BLOCK_SZ, data_flags);
+        self.set_desc(2, STATUS_OFFSET, 1, VIRTQ_DESC_F_WRITE);
+
+        // publish the chain starting at descriptor 0
+        let avail = self.dma_ptr::<VirtqAvail>(AVAIL_OFFSET);
+        unsafe {
+            let idx = read_volatile(&(*avail).idx);
+            write_volatile(&mut (*avail).ring[idx as usize % QUEUE_SIZE], 0);
+            fence(Ordering::SeqCst);
+            write_volatile(&mut (*avail).idx, idx.wrapping_add(1));
+        }
+        fence(Ordering::SeqCst);
+        write_reg(self.base, QUEUE_NOTIFY, 0);
+
+        let used = self.dma_ptr::<VirtqUsed>(USED_OFFSET);
+        while unsafe { read_volatile(&(*used).idx) } == self.last_used {
+            core::hint::spin_loop();
+        }
+        fence(Ordering::SeqCst);
+        self.last_used = self.last_used.wrapping_add(1);
+        let interrupt_status = read_reg(self.base, INTERRUPT_STATUS);
+        write_reg(self.base, INTERRUPT_ACK, interrupt_status);
+        let status = unsafe { read_volatile(self.dma_ptr::<u8>(STATUS_OFFSET)) };
+        assert_eq!(
+            status, VIRTIO_BLK_S_OK,
+            "virtio-blk request for sector {} failed",
+            sector
+        );
+    }
+}
+
+impl BlockDevice for VirtIOBlock {
+    fn read_block(&self, block_id: usize, buf: &mut [u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        assert!(block_id < self.capacity, "block {} out of range", block_id);
+        let mut inner = self.inner.lock();
+        inner.request(VIRTIO_BLK_T_IN, block_id);
+        let data = inner.dma_ptr::<u8>(DATA_OFFSET);
+        buf.copy_from_slice(unsafe { core::slice::from_raw_parts(data, BLOCK_SZ) });
+    }
+
+    fn write_block(&self, block_id: usize, buf: &[u8]) {
+        assert_eq!(buf.len(), BLOCK_SZ);
+        assert!(block_id < self.capacity, "block {} out of range", block_id);
+        let mut inner = self.inner.lock();
+        let data = inner.dma_ptr::<u8>(DATA_OFFSET);
+        unsafe { core::slice::from_raw_parts_mut(data, BLOCK_SZ) }.copy_from_slice(buf);
+        inner.request(VIRTIO_BLK_T_OUT, block_id);
+    }
+}
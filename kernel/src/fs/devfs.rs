@@ -0,0 +1,82 @@
+//! A filesystem of device files, usually mounted at /dev
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+
+use super::vfs::{FileSystem, Inode};
+use crate::sync::lock_stats;
+
+/// The devices, by name
+const DEVICES: &[&str] = &["lockstat", "null", "zero"];
+
+pub struct DevFs;
+
+impl FileSystem for DevFs {
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::new(DevDir)
+    }
+}
+
+struct DevDir;
+
+impl Inode for DevDir {
+    fn is_dir(&self) -> bool {
+        true
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        match name {
+            "lockstat" => Some(Arc::new(LockStat)),
+            "null" => Some(Arc::new(Null)),
+            "zero" => Some(Arc::new(Zero)),
+            _ => None,
+        }
+    }
+    fn list(&self) -> Vec<String> {
+        DEVICES.iter().map(|&name| String::from(name)).collect()
+    }
+}
+
+/// Reads nothing and discards writes
+struct Null;
+
+impl Inode for Null {
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> usize {
+        buf.len()
+    }
+}
+
+/// Reads zeroes and discards writes
+struct Zero;
+
+impl Inode for Zero {
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn read_at(&self, _offset: usize, buf: &mut [u8]) -> usize {
+        buf.fill(0);
+        buf.len()
+    }
+    fn write_at(&self, _offset: usize, buf: &[u8]) -> usize {
+        buf.len()
+    }
+}
+
+/// Reads the hold times of the kernel lock classes, as of the read
+struct LockStat;
+
+impl Inode for LockStat {
+    fn is_dir(&self) -> bool {
+        false
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let stats = lock_stats();
+        let Some(rest) = stats.as_bytes().get(offset..) else {
+            return 0;
+        };
+        let len = rest.len().min(buf.len());
+        buf[..len].copy_from_slice(&rest[..len]);
+        len
+    }
+}
@@ -0,0 +1,75 @@
+//! easy-fs, the filesystem on the disk
+
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::any::Any;
+use easy_fs::EasyFileSystem;
+use spin::Mutex;
+
+use super::vfs::{FileSystem, Inode};
+use crate::drivers::block::BlockDevice;
+
+pub struct EasyFs(Arc<Mutex<EasyFileSystem>>);
+
+impl EasyFs {
+    /// Open the easy-fs on `block_device`, or return `None` if there is none.
+    pub fn open(block_device: Arc<dyn BlockDevice>) -> Option<Self> {
+        EasyFileSystem::open(block_device).map(Self)
+    }
+}
+
+impl FileSystem for EasyFs {
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        Arc::new(EasyFileSystem::root_inode(&self.0))
+    }
+}
+
+impl Inode for easy_fs::Inode {
+    fn is_dir(&self) -> bool {
+        easy_fs::Inode::is_dir(self)
+    }
+    fn ino(&self) -> usize {
+        self.inode_id() as usize
+    }
+    fn nlink(&self) -> usize {
+        easy_fs::Inode::nlink(self) as usize
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        self.find(name).map(|inode| inode as Arc<dyn Inode>)
+    }
+    fn create(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        easy_fs::Inode::create(self, name).map(|inode| inode as Arc<dyn Inode>)
+    }
+    fn mkdir(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        easy_fs::Inode::mkdir(self, name).map(|inode| inode as Arc<dyn Inode>)
+    }
+    fn link(&self, name: &str, inode: &Arc<dyn Inode>) -> bool {
+        match (inode.as_ref() as &dyn Any).downcast_ref() {
+            Some(inode) => easy_fs::Inode::link(self, name, inode),
+            None => false,
+        }
+    }
+    fn unlink(&self, name: &str) -> bool {
+        easy_fs::Inode::unlink(self, name)
+    }
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn Inode>, new_name: &str) -> bool {
+        match (new_dir.as_ref() as &dyn Any).downcast_ref() {
+            Some(new_dir) => easy_fs::Inode::rename(self, old_name, new_dir, new_name),
+            None => false,
+        }
+    }
+    fn list(&self) -> Vec<String> {
+        self.ls()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        easy_fs::Inode::read_at(self, offset, buf)
+    }
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        easy_fs::Inode::write_at(self, offset, buf)
+    }
+    fn clear(&self) {
+        easy_fs::Inode::clear(self)
+    }
+    fn size(&self) -> usize {
+        easy_fs::Inode::size(self)
+    }
+}
@@ -0,0 +1,213 @@
+//! Files opened by path
+
+use alloc::{sync::Arc, vec::Vec};
+use bitflags::bitflags;
+use spin::Mutex;
+
+use super::{
+    vfs::{self, Inode},
+    File, Stat, S_IFDIR, S_IFREG,
+};
+use crate::memory::UserBuffer;
+
+/// A file or directory opened through `sys_open`, with its own offset,
+/// which for a directory counts entries
+pub struct OSInode {
+    readable: bool,
+    writable: bool,
+    /// every write goes to the end of the file
+    append: bool,
+    inner: Mutex<OSInodeInner>,
+}
+
+pub struct OSInodeInner {
+    offset: usize,
+    inode: Arc<dyn Inode>,
+}
+
+impl OSInode {
+    pub fn new(readable: bool, writable: bool, append: bool, inode: Arc<dyn Inode>) -> Self {
+        Self {
+            readable,
+            writable,
+            append,
+            inner: Mutex::new(OSInodeInner { offset: 0, inode }),
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.inner.lock().inode.is_dir()
+    }
+
+    /// Read from the current offset to the end of the file.
+    pub fn read_all(&self) -> Vec<u8> {
+        let mut inner = self.inner.lock();
+        let mut buffer = [0u8; 512];
+        let mut v: Vec<u8> = Vec::with_capacity(inner.inode.size());
+        loop {
+            let len = inner.inode.read_at(inner.offset, &mut buffer);
+            if len == 0 {
+                break;
+            }
+            inner.offset += len;
+            v.extend_from_slice(&buffer[..len]);
+        }
+        v
+    }
+}
+
+/// Print the apps in the root directory.
+pub fn list_apps() {
+    println!("/**** APPS ****");
+    for app in vfs::lookup("/").unwrap().list() {
+        println!("{}", app);
+    }
+    println!("**************/");
+}
+
+bitflags! {
+    /// The `flags` argument of `sys_open`
+    #[derive(Clone, Copy)]
+    pub struct OpenFlags: u32 {
+        const RDONLY = 0;
+        const WRONLY = 1 << 0;
+        const RDWR = 1 << 1;
+        const CREATE = 1 << 9;
+        const TRUNC = 1 << 10;
+        const APPEND = 1 << 11;
+    }
+}
+
+impl OpenFlags {
+    /// Return (readable, writable).
+    pub fn read_write(&self) -> (bool, bool) {
+        if self.contains(Self::WRONLY) {
+            (false, true)
+        } else if self.contains(Self::RDWR) {
+            (true, true)
+        } else {
+            (true, false)
+        }
+    }
+}
+
+/// Open the file at the normalized absolute `path`, creating it if `flags`
+/// has `CREATE`. Directories can only be opened read-only, and then only
+/// for [`File::read_dir`].
+pub fn open_file(path: &str, flags: OpenFlags) -> Option<Arc<OSInode>> {
+    let (readable, writable) = flags.read_write();
+    let inode = match vfs::lookup(path) {
+        Some(inode) => inode,
+        None if flags.contains(OpenFlags::CREATE) => {
+            let (parent, name) = vfs::lookup_parent(path)?;
+            parent.create(&name?)?
+        }
+        None => return None,
+    };
+    if inode.is_dir() && (writable || flags.intersects(OpenFlags::CREATE | OpenFlags::TRUNC)) {
+        return None;
+    }
+    let readable = readable && !inode.is_dir();
+    if flags.contains(OpenFlags::TRUNC) {
+        inode.clear();
+    }
+    let append = flags.contains(OpenFlags::APPEND);
+    Some(Arc::new(OSInode::new(readable, writable, append, inode)))
+}
+
+impl File for OSInode {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        let mut inner = self.inner.lock();
+        let mut total_read_size = 0usize;
+        for slice in buf.buffers.iter_mut() {
+            let read_size = inner.inode.read_at(inner.offset, slice);
+            if read_size == 0 {
+                break;
+            }
+            inner.offset += read_size;
+            total_read_size += read_size;
+        }
+        total_read_size
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        let mut inner = self.inner.lock();
+        if self.append {
+            inner.offset = inner.inode.size();
+        }
+        let mut total_write_size = 0usize;
+        for slice in buf.buffers.iter() {
+            let write_size = inner.inode.write_at(inner.offset, slice);
+            inner.offset += write_size;
+            total_write_size += write_size;
+            if write_size < slice.len() {
+                break;
+            }
+        }
+        total_write_size
+    }
+    fn stat(&self) -> Stat {
+        let inode = &self.inner.lock().inode;
+        let mode = if inode.is_dir() {
+            S_IFDIR | 0o755
+        } else {
+            S_IFREG | 0o755
+        };
+        let size = inode.size();
+        Stat {
+            ino: inode.ino() as u64,
+            mode,
+            nlink: inode.nlink() as u32,
+            size: size as i64,
+            blksize: 512,
+            blocks: size.div_ceil(512) as i64,
+            ..Stat::default()
+        }
+    }
+    fn read_dir(&self, mut buf: UserBuffer) -> Option<usize> {
+        let mut inner = self.inner.lock();
+        if !inner.inode.is_dir() {
+            return None;
+        }
+        let names = inner.inode.list();
+        let mut dirents: Vec<u8> = Vec::new();
+        while let Some(name) = names.get(inner.offset) {
+            // skip entries removed since the listing
+            if let Some(inode) = inner.inode.lookup(name) {
+                // `struct linux_dirent64`, padded to 8 bytes
+                let reclen = (DIRENT64_NAME_OFFSET + name.len() + 1).next_multiple_of(8);
+                if dirents.len() + reclen > buf.len() {
+                    break;
+                }
+                let d_type = if inode.is_dir() { DT_DIR } else { DT_REG };
+                dirents.extend_from_slice(&(inode.ino() as u64).to_ne_bytes());
+                dirents.extend_from_slice(&(inner.offset as i64 + 1).to_ne_bytes());
+                dirents.extend_from_slice(&(reclen as u16).to_ne_bytes());
+                dirents.push(d_type);
+                dirents.extend_from_slice(name.as_bytes());
+                dirents.resize(
+                    dirents.len() + reclen - DIRENT64_NAME_OFFSET - name.len(),
+                    0,
+                );
+            }
+            inner.offset += 1;
+        }
+        if dirents.is_empty() && inner.offset < names.len() {
+            return None;
+        }
+        for (dst, src) in buf.iter_mut().zip(dirents.iter()) {
+            *dst = *src;
+        }
+        Some(dirents.len())
+    }
+}
+
+/// Where `d_name` starts in a `struct linux_dirent64`
+const DIRENT64_NAME_OFFSET: usize = 19;
+const DT_DIR: u8 = 4;
+const DT_REG: u8 = 8;
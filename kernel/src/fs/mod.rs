@@ -0,0 +1,103 @@
+//! Files that can be installed in a process's fd table
+
+mod devfs;
+mod efs;
+mod inode;
+mod pipe;
+mod stdio;
+mod tmpfs;
+mod vfs;
+
+use alloc::sync::Arc;
+use log::warn;
+
+use crate::{drivers::block::BLOCK_DEVICE, memory::UserBuffer};
+
+pub use devfs::DevFs;
+pub use inode::{list_apps, open_file, OpenFlags};
+pub use pipe::make_pipe;
+pub use stdio::{foreground_pgid, poll_console, set_foreground_pgid, Stdin, Stdout};
+pub use tmpfs::TmpFs;
+pub use vfs::{link, lookup, mkdir, mount, normalize, rename, umount, unlink, FileSystem};
+
+/// Anything that can be read from or written to through a file descriptor
+pub trait File: Send + Sync {
+    fn readable(&self) -> bool;
+    fn writable(&self) -> bool;
+    /// Read into `buf`, returning how many bytes were read.
+    fn read(&self, buf: UserBuffer) -> usize;
+    /// Write from `buf`, returning how many bytes were written.
+    fn write(&self, buf: UserBuffer) -> usize;
+    fn stat(&self) -> Stat;
+    /// Fill `buf` with the next entries of this directory as `getdents64`
+    /// does, returning their total length, or `None` if this is not a
+    /// directory or the next entry does not fit.
+    fn read_dir(&self, _buf: UserBuffer) -> Option<usize> {
+        None
+    }
+    /// Return whether this is the console, the only terminal.
+    fn is_tty(&self) -> bool {
+        false
+    }
+}
+
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+/// `struct stat` as Linux lays it out on riscv64
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    /// the file type, one of `S_IF*`, and the permission bits
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    pad1: u64,
+    pub size: i64,
+    pub blksize: i32,
+    pad2: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: i64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: i64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: i64,
+    unused: [u32; 2],
+}
+
+impl Stat {
+    /// A file of `mode` with a single link and no contents
+    pub fn with_mode(mode: u32) -> Self {
+        Self {
+            mode,
+            nlink: 1,
+            ..Self::default()
+        }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe {
+            core::slice::from_raw_parts(self as *const _ as *const u8, core::mem::size_of::<Self>())
+        }
+    }
+}
+
+/// Return the filesystem to mount at `/`: the disk if it holds an easy-fs,
+/// and otherwise an empty tmpfs.
+fn root_fs() -> Arc<dyn FileSystem> {
+    if let Some(efs) = BLOCK_DEVICE
+        .clone()
+        .and_then(|block_device| efs::EasyFs::open(block_device))
+    {
+        return Arc::new(efs);
+    }
+    warn!("no easy-fs disk, mounting a tmpfs as root");
+    Arc::new(TmpFs::new())
+}
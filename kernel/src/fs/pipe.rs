@@ -0,0 +1,162 @@
+//! Anonymous pipes backed by a fixed-size ring buffer
+
+use alloc::sync::{Arc, Weak};
+
+use super::{File, Stat, S_IFIFO};
+use crate::{
+    memory::UserBuffer,
+    sync::SpinLock,
+    task::{signal_pending, suspend_current_and_run_next_task},
+};
+
+/// One end of a pipe
+pub struct Pipe {
+    readable: bool,
+    writable: bool,
+    buffer: Arc<SpinLock<PipeRingBuffer>>,
+}
+
+impl Pipe {
+    fn read_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: true,
+            writable: false,
+            buffer,
+        }
+    }
+    fn write_end_with_buffer(buffer: Arc<SpinLock<PipeRingBuffer>>) -> Self {
+        Self {
+            readable: false,
+            writable: true,
+            buffer,
+        }
+    }
+}
+
+const RING_BUFFER_SIZE: usize = 32;
+
+struct PipeRingBuffer {
+    arr: [u8; RING_BUFFER_SIZE],
+    head: usize,
+    len: usize,
+    /// used to tell when every read end or every write end has been dropped
+    read_end: Weak<Pipe>,
+    write_end: Weak<Pipe>,
+}
+
+impl PipeRingBuffer {
+    fn new() -> Self {
+        Self {
+            arr: [0; RING_BUFFER_SIZE],
+            head: 0,
+            len: 0,
+            read_end: Weak::new(),
+            write_end: Weak::new(),
+        }
+    }
+    fn read_byte(&mut self) -> u8 {
+        let c = self.arr[self.head];
+        self.head = (self.head + 1) % RING_BUFFER_SIZE;
+        self.len -= 1;
+        c
+    }
+    fn write_byte(&mut self, c: u8) {
+        self.arr[(self.head + self.len) % RING_BUFFER_SIZE] = c;
+        self.len += 1;
+    }
+    fn available_read(&self) -> usize {
+        self.len
+    }
+    fn available_write(&self) -> usize {
+        RING_BUFFER_SIZE - self.len
+    }
+    fn all_read_ends_closed(&self) -> bool {
+        self.read_end.upgrade().is_none()
+    }
+    fn all_write_ends_closed(&self) -> bool {
+        self.write_end.upgrade().is_none()
+    }
+}
+
+/// Create a pipe, returning its (read end, write end).
+pub fn make_pipe() -> (Arc<Pipe>, Arc<Pipe>) {
+    let buffer = Arc::new(SpinLock::new("Pipe::buffer", PipeRingBuffer::new()));
+    let read_end = Arc::new(Pipe::read_end_with_buffer(buffer.clone()));
+    let write_end = Arc::new(Pipe::write_end_with_buffer(buffer.clone()));
+    let mut ring_buffer = buffer.lock();
+    ring_buffer.read_end = Arc::downgrade(&read_end);
+    ring_buffer.write_end = Arc::downgrade(&write_end);
+    drop(ring_buffer);
+    (read_end, write_end)
+}
+
+impl File for Pipe {
+    fn readable(&self) -> bool {
+        self.readable
+    }
+    fn writable(&self) -> bool {
+        self.writable
+    }
+    /// Block until some data arrives, then return what is buffered. Returns 0
+    /// (EOF) once the pipe is empty and every write end is closed, or -1 if a
+    /// signal interrupts the wait.
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        assert!(self.readable());
+        let want = buf.len();
+        if want == 0 {
+            return 0;
+        }
+        loop {
+            let mut ring_buffer = self.buffer.lock();
+            let available = ring_buffer.available_read();
+            if available == 0 {
+                if ring_buffer.all_write_ends_closed() {
+                    return 0;
+                }
+                drop(ring_buffer);
+                if signal_pending() {
+                    return usize::MAX;
+                }
+                suspend_current_and_run_next_task();
+                continue;
+            }
+            let read = available.min(want);
+            for byte in buf.iter_mut().take(read) {
+                *byte = ring_buffer.read_byte();
+            }
+            return read;
+        }
+    }
+    /// Block until all of `buf` is written. Stops early, returning how much
+    /// was written, if every read end is closed or a signal interrupts the
+    /// wait; an interrupted write that wrote nothing returns -1.
+    fn write(&self, buf: UserBuffer) -> usize {
+        assert!(self.writable());
+        let want = buf.len();
+        let mut bytes = buf.iter();
+        let mut written = 0;
+        while written < want {
+            let mut ring_buffer = self.buffer.lock();
+            if ring_buffer.all_read_ends_closed() {
+                break;
+            }
+            let available = ring_buffer.available_write();
+            if available == 0 {
+                drop(ring_buffer);
+                if signal_pending() {
+                    return if written == 0 { usize::MAX } else { written };
+                }
+                suspend_current_and_run_next_task();
+                continue;
+            }
+            for &c in bytes.by_ref().take(available) {
+                ring_buffer.write_byte(c);
+                written += 1;
+            }
+        }
+        written
+    }
+    fn stat(&self) -> Stat {
+        Stat::with_mode(S_IFIFO | 0o600)
+    }
+}
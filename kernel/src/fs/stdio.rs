@@ -0,0 +1,143 @@
+//! Console-backed standard input and output
+//!
+//! The console is also the terminal of job control: Ctrl-C and Ctrl-Z typed
+//! on it send SIGINT and SIGTSTP to its foreground process group instead of
+//! being read. Input is polled on every timer interrupt as well as by readers,
+//! so that a job that never reads can still be interrupted.
+
+use alloc::collections::VecDeque;
+use core::sync::atomic::{AtomicUsize, Ordering};
+
+use super::{File, Stat, S_IFCHR};
+use crate::{
+    memory::UserBuffer,
+    sbi::{console_getchar, console_putchar},
+    sync::SpinLock,
+    task::{
+        process_group, send_signal, signal_pending, suspend_current_and_run_next_task, SignalFlags,
+    },
+};
+
+/// Reads from the SBI console
+pub struct Stdin;
+
+/// Writes to the SBI console, used for both stdout and stderr
+pub struct Stdout;
+
+/// Return the next console input byte, if one has arrived.
+fn try_getchar() -> Option<u8> {
+    match console_getchar() {
+        // the legacy SBI call returns -1 when there is no input
+        usize::MAX => None,
+        c => Some(c as u8),
+    }
+}
+
+/// Ctrl-C
+const ETX: u8 = 0x03;
+/// Ctrl-Z
+const SUB: u8 = 0x1a;
+
+/// Input polled from the console but not read yet
+static INPUT: SpinLock<VecDeque<u8>> = SpinLock::new("INPUT", VecDeque::new());
+
+/// The process group that console signals go to, or 0 for none
+static FOREGROUND_PGID: AtomicUsize = AtomicUsize::new(0);
+
+pub fn foreground_pgid() -> usize {
+    FOREGROUND_PGID.load(Ordering::Relaxed)
+}
+
+pub fn set_foreground_pgid(pgid: usize) {
+    FOREGROUND_PGID.store(pgid, Ordering::Relaxed);
+}
+
+/// Move the input that has arrived on the console into [`INPUT`], sending
+/// the foreground process group a signal for each Ctrl-C or Ctrl-Z.
+pub fn poll_console() {
+    while let Some(c) = try_getchar() {
+        let signal = match c {
+            ETX => SignalFlags::SIGINT,
+            SUB => SignalFlags::SIGTSTP,
+            c => {
+                INPUT.lock().push_back(c);
+                continue;
+            }
+        };
+        for process in process_group(foreground_pgid()) {
+            send_signal(&process, signal);
+        }
+    }
+}
+
+impl File for Stdin {
+    fn readable(&self) -> bool {
+        true
+    }
+    fn writable(&self) -> bool {
+        false
+    }
+    /// Block until some input arrives, then return what is available
+    /// without waiting for more. A signal that interrupts the wait makes
+    /// this fail.
+    fn read(&self, mut buf: UserBuffer) -> usize {
+        if buf.len() == 0 {
+            return 0;
+        }
+        loop {
+            poll_console();
+            if !INPUT.lock().is_empty() {
+                break;
+            }
+            if signal_pending() {
+                return usize::MAX;
+            }
+            suspend_current_and_run_next_task();
+        }
+        let mut input = INPUT.lock();
+        let mut read = 0;
+        for byte in buf.iter_mut() {
+            let Some(c) = input.pop_front() else {
+                break;
+            };
+            *byte = c;
+            read += 1;
+        }
+        read
+    }
+    /// Stdin is not writable, so this fails.
+    fn write(&self, _buf: UserBuffer) -> usize {
+        usize::MAX
+    }
+    fn stat(&self) -> Stat {
+        Stat::with_mode(S_IFCHR | 0o620)
+    }
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
+
+impl File for Stdout {
+    fn readable(&self) -> bool {
+        false
+    }
+    fn writable(&self) -> bool {
+        true
+    }
+    /// Stdout is not readable, so this fails.
+    fn read(&self, _buf: UserBuffer) -> usize {
+        usize::MAX
+    }
+    fn write(&self, buf: UserBuffer) -> usize {
+        for &c in buf.iter() {
+            console_putchar(c);
+        }
+        buf.len()
+    }
+    fn stat(&self) -> Stat {
+        Stat::with_mode(S_IFCHR | 0o620)
+    }
+    fn is_tty(&self) -> bool {
+        true
+    }
+}
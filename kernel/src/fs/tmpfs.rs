@@ -0,0 +1,270 @@
+//! A filesystem in memory, whose file contents live in frames
+//!
+//! Nothing survives a reboot. It backs /tmp, and `/` when there is no disk.
+
+use alloc::{
+    collections::BTreeMap,
+    string::{String, ToString},
+    sync::Arc,
+    vec::Vec,
+};
+use core::any::Any;
+use spin::Mutex;
+
+use super::vfs::{FileSystem, Inode};
+use crate::memory::{address::PAGE_SIZE, frame_alloc, FrameTracker};
+
+pub struct TmpFs {
+    root: Arc<TmpInode>,
+}
+
+impl TmpFs {
+    /// Create an empty filesystem.
+    pub fn new() -> Self {
+        Self {
+            root: Arc::new(TmpInode::new_dir()),
+        }
+    }
+}
+
+impl FileSystem for TmpFs {
+    fn root_inode(&self) -> Arc<dyn Inode> {
+        self.root.clone()
+    }
+}
+
+enum TmpInode {
+    File(Mutex<TmpFile>),
+    /// entries by name, which may be linked in from other directories
+    Dir(Mutex<BTreeMap<String, Arc<dyn Inode>>>),
+}
+
+struct TmpFile {
+    /// the number of directory entries naming this file
+    nlink: usize,
+    size: usize,
+    /// page `i` of the contents, up to the last page written
+    frames: Vec<FrameTracker>,
+}
+
+impl TmpInode {
+    fn new_file() -> Self {
+        Self::File(Mutex::new(TmpFile {
+            nlink: 1,
+            size: 0,
+            frames: Vec::new(),
+        }))
+    }
+
+    fn new_dir() -> Self {
+        Self::Dir(Mutex::new(BTreeMap::new()))
+    }
+
+    fn entries(&self) -> Option<spin::MutexGuard<'_, BTreeMap<String, Arc<dyn Inode>>>> {
+        match self {
+            Self::Dir(entries) => Some(entries.lock()),
+            Self::File(_) => None,
+        }
+    }
+
+    /// Add a new inode named `name` to this directory.
+    fn add(&self, name: &str, inode: TmpInode) -> Option<Arc<dyn Inode>> {
+        let mut entries = self.entries()?;
+        if entries.contains_key(name) {
+            return None;
+        }
+        let inode: Arc<dyn Inode> = Arc::new(inode);
+        entries.insert(name.to_string(), inode.clone());
+        Some(inode)
+    }
+
+    /// Count a link to `inode` made or dropped, if it is a tmpfs file.
+    fn count_link(inode: &Arc<dyn Inode>, made: bool) {
+        if let Some(Self::File(file)) = (inode.as_ref() as &dyn Any).downcast_ref::<Self>() {
+            let mut file = file.lock();
+            if made {
+                file.nlink += 1;
+            } else {
+                file.nlink -= 1;
+            }
+        }
+    }
+}
+
+impl Inode for TmpInode {
+    fn is_dir(&self) -> bool {
+        matches!(self, Self::Dir(_))
+    }
+    fn lookup(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        self.entries()?.get(name).cloned()
+    }
+    fn create(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        self.add(name, TmpInode::new_file())
+    }
+    fn mkdir(&self, name: &str) -> Option<Arc<dyn Inode>> {
+        self.add(name, TmpInode::new_dir())
+    }
+    fn link(&self, name: &str, inode: &Arc<dyn Inode>) -> bool {
+        let Some(mut entries) = self.entries() else {
+            return false;
+        };
+        if entries.contains_key(name) {
+            return false;
+        }
+        entries.insert(name.to_string(), inode.clone());
+        Self::count_link(inode, true);
+        true
+    }
+    fn unlink(&self, name: &str) -> bool {
+        let Some(mut entries) = self.entries() else {
+            return false;
+        };
+        match entries.get(name) {
+            Some(inode) if inode.is_dir() && !inode.list().is_empty() => false,
+            Some(_) => {
+                Self::count_link(&entries.remove(name).unwrap(), false);
+                true
+            }
+            None => false,
+        }
+    }
+    fn rename(&self, old_name: &str, new_dir: &Arc<dyn Inode>, new_name: &str) -> bool {
+        // only one directory is locked at a time, as `new_dir` may be `self`
+        let Some(inode) = self.lookup(old_name) else {
+            return false;
+        };
+        if let Some(old) = new_dir.lookup(new_name) {
+            if Arc::ptr_eq(&old, &inode) {
+                return true;
+            }
+            if old.is_dir() || !new_dir.unlink(new_name) {
+                return false;
+            }
+        }
+        if !new_dir.link(new_name, &inode) {
+            return false;
+        }
+        let inode = self.entries().unwrap().remove(old_name).unwrap();
+        Self::count_link(&inode, false);
+        true
+    }
+    fn ino(&self) -> usize {
+        self as *const Self as usize
+    }
+    fn nlink(&self) -> usize {
+        match self {
+            Self::File(file) => file.lock().nlink,
+            Self::Dir(_) => 1,
+        }
+    }
+    fn list(&self) -> Vec<String> {
+        self.entries()
+            .map(|entries| entries.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+    fn read_at(&self, offset: usize, buf: &mut [u8]) -> usize {
+        let Self::File(file) = self else {
+            return 0;
+        };
+        let file = file.lock();
+        let end = (offset + buf.len()).min(file.size);
+        let mut pos = offset;
+        while pos < end {
+            let page_end = ((pos / PAGE_SIZE + 1) * PAGE_SIZE).min(end);
+            let page = file.frames[pos / PAGE_SIZE].ppn.get_bytes_array();
+            buf[pos - offset..page_end - offset]
+                .copy_from_slice(&page[pos % PAGE_SIZE..(page_end - 1) % PAGE_SIZE + 1]);
+            pos = page_end;
+        }
+        end.saturating_sub(offset)
+    }
+    /// Write as much of `buf` as there are frames for.
+    fn write_at(&self, offset: usize, buf: &[u8]) -> usize {
+        let Self::File(file) = self else {
+            return 0;
+        };
+        let mut file = file.lock();
+        let pages = (offset + buf.len()).div_ceil(PAGE_SIZE);
+        while file.frames.len() < pages {
+            let Some(frame) = frame_alloc() else {
+                break;
+            };
+            file.frames.push(frame);
+        }
+        let end = (offset + buf.len()).min(file.frames.len() * PAGE_SIZE);
+        let mut pos = offset;
+        while pos < end {
+            let page_end = ((pos / PAGE_SIZE + 1) * PAGE_SIZE).min(end);
+            let page = file.frames[pos / PAGE_SIZE].ppn.get_bytes_array();
+            page[pos % PAGE_SIZE..(page_end - 1) % PAGE_SIZE + 1]
+                .copy_from_slice(&buf[pos - offset..page_end - offset]);
+            pos = page_end;
+        }
+        file.size = file.size.max(end);
+        end.saturating_sub(offset)
+    }
+    fn clear(&self) {
+        if let Self::File(file) = self {
+            let mut file = file.lock();
+            file.size = 0;
+            file.frames.clear();
+        }
+    }
+    fn size(&self) -> usize {
+        match self {
+            Self::File(file) => file.lock().size,
+            Self::Dir(_) => 0,
+        }
+    }
+}
+
+#[test_case]
+fn tmpfs_test() {
+    let fs = TmpFs::new();
+    let root = fs.root_inode();
+    let file = root.create("a").unwrap();
+    assert!(root.create("a").is_none());
+
+    // across pages, with a gap that reads back as zeroes
+    let data: Vec<u8> = (0..PAGE_SIZE * 2).map(|i| (i % 251) as u8).collect();
+    assert_eq!(file.write_at(100, &data), data.len());
+    assert_eq!(file.size(), 100 + data.len());
+    let mut buf = alloc::vec![0xffu8; file.size() + 10];
+    assert_eq!(file.read_at(0, &mut buf), 100 + data.len());
+    assert!(buf[..100].iter().all(|&b| b == 0));
+    assert_eq!(&buf[100..100 + data.len()], &data[..]);
+    // appending
+    assert_eq!(file.write_at(file.size(), b"tail"), 4);
+    assert_eq!(file.read_at(100 + data.len(), &mut buf), 4);
+    assert_eq!(&buf[..4], b"tail");
+    file.clear();
+    assert_eq!(file.size(), 0);
+    assert_eq!(file.read_at(0, &mut buf), 0);
+
+    // directories, unlinking and renaming
+    let dir = root.mkdir("d").unwrap();
+    assert!(root.mkdir("d").is_none());
+    assert!(root.lookup("a").unwrap().create("x").is_none());
+    dir.create("b").unwrap().write_at(0, b"bee");
+    assert!(!root.unlink("d"));
+    assert!(root.rename("a", &dir, "c"));
+    assert!(root.lookup("a").is_none());
+    assert_eq!(dir.list(), ["b", "c"]);
+    // replacing a file
+    assert!(dir.rename("b", &dir, "c"));
+    assert_eq!(dir.list(), ["c"]);
+    assert_eq!(dir.lookup("c").unwrap().read_at(0, &mut buf), 3);
+    assert!(!dir.unlink("b"));
+    // hard links
+    let file = dir.lookup("c").unwrap();
+    assert!(root.link("e", &file));
+    assert!(!root.link("e", &file));
+    assert_eq!(file.nlink(), 2);
+    assert_eq!(root.lookup("e").unwrap().ino(), file.ino());
+    assert!(dir.unlink("c"));
+    assert_eq!(file.nlink(), 1);
+    assert!(root.unlink("e"));
+    assert!(root.unlink("d"));
+    assert!(root.list().is_empty());
+    println!("tmpfs_test passed!");
+}
@@ -0,0 +1,299 @@
+//! The virtual filesystem: every mounted filesystem behind one path namespace
+//!
+//! Paths are resolved lexically: a relative path is joined to the cwd, and
+//! `.` and `..` are folded away before anything is looked up. The resulting
+//! absolute path is then walked from the root of the filesystem mounted at
+//! its longest matching mount point, so `..` crosses mount points like any
+//! other directory.
+
+use alloc::{
+    string::{String, ToString},
+    sync::Arc,
+    vec,
+    vec::Vec,
+};
+use core::any::Any;
+use lazy_static::lazy_static;
+
+use crate::sync::SpinLock;
+
+/// A file or directory of some mounted filesystem
+///
+/// Directory operations default to failing, and file operations to reading
+/// and writing nothing, so each kind of inode only implements its own.
+pub trait Inode: Any + Send + Sync {
+    fn is_dir(&self) -> bool;
+    /// Return a number identifying this inode within its filesystem.
+    fn ino(&self) -> usize {
+        0
+    }
+    /// Return the number of directory entries naming this inode.
+    fn nlink(&self) -> usize {
+        1
+    }
+    /// Look `name` up in this directory.
+    fn lookup(&self, _name: &str) -> Option<Arc<dyn Inode>> {
+        None
+    }
+    /// Create the empty file `name` in this directory, returning `None` if it
+    /// already exists or there is no room.
+    fn create(&self, _name: &str) -> Option<Arc<dyn Inode>> {
+        None
+    }
+    /// Create the empty directory `name` in this directory.
+    fn mkdir(&self, _name: &str) -> Option<Arc<dyn Inode>> {
+        None
+    }
+    /// Add `inode`, from the same filesystem, to this directory as `name`.
+    fn link(&self, _name: &str, _inode: &Arc<dyn Inode>) -> bool {
+        false
+    }
+    /// Remove `name` from this directory, which fails if it is a directory
+    /// that is not empty.
+    fn unlink(&self, _name: &str) -> bool {
+        false
+    }
+    /// Move `old_name` in this directory to `new_name` in `new_dir`, from the
+    /// same filesystem, replacing any file already there.
+    fn rename(&self, _old_name: &str, _new_dir: &Arc<dyn Inode>, _new_name: &str) -> bool {
+        false
+    }
+    /// Return the names in this directory.
+    fn list(&self) -> Vec<String> {
+        Vec::new()
+    }
+    /// Read from `offset` into `buf`, returning how many bytes were read.
+    fn read_at(&self, _offset: usize, _buf: &mut [u8]) -> usize {
+        0
+    }
+    /// Write `buf` at `offset`, returning how many bytes were written.
+    fn write_at(&self, _offset: usize, _buf: &[u8]) -> usize {
+        0
+    }
+    /// Truncate to zero size.
+    fn clear(&self) {}
+    fn size(&self) -> usize {
+        0
+    }
+}
+
+/// A filesystem that can be mounted
+pub trait FileSystem: Send + Sync {
+    fn root_inode(&self) -> Arc<dyn Inode>;
+}
+
+struct Mount {
+    /// absolute and normalized, see [`normalize`]
+    path: String,
+    fs: Arc<dyn FileSystem>,
+}
+
+lazy_static! {
+    static ref MOUNTS: SpinLock<Vec<Mount>> = SpinLock::new(
+        "MOUNTS",
+        vec![Mount {
+            path: String::from("/"),
+            fs: super::root_fs(),
+        }]
+    );
+}
+
+/// Join `path` to `cwd` if it is relative, and fold away `.`, `..` and
+/// repeated slashes. `..` at the root stays at the root.
+pub fn normalize(cwd: &str, path: &str) -> String {
+    let mut components: Vec<&str> = Vec::new();
+    let base = if path.starts_with('/') { "" } else { cwd };
+    for component in base.split('/').chain(path.split('/')) {
+        match component {
+            "" | "." => {}
+            ".." => {
+                components.pop();
+            }
+            name => components.push(name),
+        }
+    }
+    let mut normalized = String::new();
+    for name in components {
+        normalized.push('/');
+        normalized.push_str(name);
+    }
+    if normalized.is_empty() {
+        normalized.push('/');
+    }
+    normalized
+}
+
+/// Return the part of `path` below the mount point `mount`, or `None` if
+/// `path` is not under it.
+fn strip_mount<'a>(path: &'a str, mount: &str) -> Option<&'a str> {
+    if mount == "/" {
+        return Some(path);
+    }
+    let rest = path.strip_prefix(mount)?;
+    (rest.is_empty() || rest.starts_with('/')).then_some(rest)
+}
+
+/// Find the filesystem the normalized absolute `path` is on, returning its
+/// mount point, the filesystem, and the part of `path` below the mount point.
+fn resolve(path: &str) -> (String, Arc<dyn FileSystem>, String) {
+    let mounts = MOUNTS.lock();
+    let (mount, rest) = mounts
+        .iter()
+        .filter_map(|mount| Some((mount, strip_mount(path, &mount.path)?)))
+        .max_by_key(|(mount, _)| mount.path.len())
+        .unwrap();
+    (mount.path.clone(), mount.fs.clone(), rest.to_string())
+}
+
+/// Return whether a filesystem is mounted at the normalized absolute `path`
+/// or below it.
+fn is_busy(path: &str) -> bool {
+    MOUNTS
+        .lock()
+        .iter()
+        .any(|mount| strip_mount(&mount.path, path).is_some())
+}
+
+/// Look up the normalized absolute `path`.
+pub fn lookup(path: &str) -> Option<Arc<dyn Inode>> {
+    let (_, fs, rest) = resolve(path);
+    let mut inode = fs.root_inode();
+    for name in rest.split('/').filter(|name| !name.is_empty()) {
+        inode = inode.lookup(name)?;
+    }
+    Some(inode)
+}
+
+/// Split the normalized absolute `path` into its parent directory and its
+/// last component, which is `None` for the root.
+pub fn lookup_parent(path: &str) -> Option<(Arc<dyn Inode>, Option<String>)> {
+    if path == "/" {
+        return Some((lookup(path)?, None));
+    }
+    let (parent, name) = path.rsplit_once('/').unwrap();
+    let parent = lookup(if parent.is_empty() { "/" } else { parent })?;
+    parent.is_dir().then(|| (parent, Some(name.to_string())))
+}
+
+/// Create the empty directory at the normalized absolute `path`.
+pub fn mkdir(path: &str) -> bool {
+    match lookup_parent(path) {
+        Some((parent, Some(name))) => lookup(path).is_none() && parent.mkdir(&name).is_some(),
+        _ => false,
+    }
+}
+
+/// Remove the file, or with `dir` the empty directory, at the normalized
+/// absolute `path`. Returns false if it is the other kind, or a filesystem
+/// is mounted there or below.
+pub fn unlink(path: &str, dir: bool) -> bool {
+    let Some((parent, Some(name))) = lookup_parent(path) else {
+        return false;
+    };
+    if is_busy(path) {
+        return false;
+    }
+    match parent.lookup(&name) {
+        Some(inode) if inode.is_dir() == dir => parent.unlink(&name),
+        _ => false,
+    }
+}
+
+/// Make `new_path` another name for the file at `old_path`, both normalized
+/// and absolute. Directories cannot be linked, and both paths must be on the
+/// same filesystem.
+pub fn link(old_path: &str, new_path: &str) -> bool {
+    let (Some(inode), Some((parent, Some(name)))) = (lookup(old_path), lookup_parent(new_path))
+    else {
+        return false;
+    };
+    !inode.is_dir()
+        && lookup(new_path).is_none()
+        && resolve(old_path).0 == resolve(new_path).0
+        && parent.link(&name, &inode)
+}
+
+/// Move the file or directory at `old_path` to `new_path`, both normalized
+/// and absolute, replacing any file there. Both paths must be on the same
+/// filesystem, and a directory cannot move below itself.
+pub fn rename(old_path: &str, new_path: &str) -> bool {
+    if old_path == new_path {
+        return lookup(old_path).is_some();
+    }
+    let (Some((old_parent, Some(old_name))), Some((new_parent, Some(new_name)))) =
+        (lookup_parent(old_path), lookup_parent(new_path))
+    else {
+        return false;
+    };
+    let Some(inode) = old_parent.lookup(&old_name) else {
+        return false;
+    };
+    if is_busy(old_path)
+        || is_busy(new_path)
+        || strip_mount(new_path, old_path).is_some()
+        || resolve(old_path).0 != resolve(new_path).0
+    {
+        return false;
+    }
+    if inode.is_dir() && new_parent.lookup(&new_name).is_some() {
+        return false;
+    }
+    old_parent.rename(&old_name, &new_parent, &new_name)
+}
+
+/// Mount `fs` at the normalized absolute `path`, whose parent must be a
+/// directory. Returns false if something is mounted there already or `path`
+/// names a file.
+pub fn mount(path: &str, fs: Arc<dyn FileSystem>) -> bool {
+    match lookup_parent(path) {
+        Some((_, None)) | None => return false,
+        Some(_) => {}
+    }
+    if lookup(path).is_some_and(|inode| !inode.is_dir()) {
+        return false;
+    }
+    let mut mounts = MOUNTS.lock();
+    if mounts.iter().any(|mount| mount.path == path) {
+        return false;
+    }
+    mounts.push(Mount {
+        path: path.to_string(),
+        fs,
+    });
+    true
+}
+
+/// Unmount the filesystem at the normalized absolute `path`. Returns false
+/// if nothing is mounted there, it is the root, or other filesystems are
+/// mounted below it.
+pub fn umount(path: &str) -> bool {
+    if path == "/" {
+        return false;
+    }
+    let mut mounts = MOUNTS.lock();
+    let Some(idx) = mounts.iter().position(|mount| mount.path == path) else {
+        return false;
+    };
+    if mounts
+        .iter()
+        .any(|mount| mount.path != path && strip_mount(&mount.path, path).is_some())
+    {
+        return false;
+    }
+    mounts.remove(idx);
+    true
+}
+
+#[test_case]
+fn normalize_test() {
+    assert_eq!(normalize("/", "."), "/");
+    assert_eq!(normalize("/", ".."), "/");
+    assert_eq!(normalize("/a/b", "c"), "/a/b/c");
+    assert_eq!(normalize("/a/b", "../c/./d/"), "/a/c/d");
+    assert_eq!(normalize("/a/b", "/x//y/.."), "/x");
+    assert_eq!(normalize("/a", "../../.."), "/");
+    assert_eq!(strip_mount("/dev/null", "/dev"), Some("/null"));
+    assert_eq!(strip_mount("/device", "/dev"), None);
+    assert_eq!(strip_mount("/dev", "/dev"), Some(""));
+    println!("normalize_test passed!");
+}
@@ -13,10 +13,13 @@
 #[macro_use]
 mod console;
 mod config;
+mod drivers;
+mod fs;
 mod lang_items;
 mod logging;
 mod memory;
 mod sbi;
+mod sync;
 mod syscall;
 mod task;
 mod timer;
@@ -27,7 +30,6 @@ use core::arch::global_asm;
 use log::*;
 
 global_asm!(include_str!("entry.asm"));
-global_asm!(include_str!(concat!(env!("OUT_DIR"), "/link_apps.S")));
 
 #[cfg(test)]
 pub fn test_runner(tests: &[&dyn Fn()]) {
@@ -79,9 +81,11 @@ pub fn rust_main() -> ! {
 
     memory::init();
     trap::init();
+    drivers::block::init();
 
     #[cfg(test)]
     test_main();
 
-    task::run_processes();
+    fs::list_apps();
+    task::run_threads();
 }
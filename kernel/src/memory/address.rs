@@ -75,6 +75,7 @@ impl From<usize> for VirtPageNum {
     }
 }
 
+#[derive(Clone, Copy)]
 pub struct VPNRange {
     pub start: VirtPageNum,
     pub end: VirtPageNum,
@@ -87,6 +88,15 @@ impl VPNRange {
     pub fn iter(&self) -> impl Iterator<Item = VirtPageNum> {
         (self.start.0..self.end.0).map(|vpn| VirtPageNum(vpn))
     }
+    pub fn contains(&self, vpn: VirtPageNum) -> bool {
+        self.start <= vpn && vpn < self.end
+    }
+    pub fn overlaps(&self, other: &VPNRange) -> bool {
+        self.start < other.end && other.start < self.end
+    }
+    pub fn len(&self) -> usize {
+        self.end.0.saturating_sub(self.start.0)
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -3,7 +3,7 @@ use core::ops::Range;
 use alloc::vec::Vec;
 use lazy_static::lazy_static;
 
-use crate::{config::MEMORY_END, memory::address::PhysAddr};
+use crate::{config::MEMORY_END, memory::address::PhysAddr, sync::SpinLock};
 
 use super::address::{PhysPageNum, PAGE_SIZE};
 
@@ -71,8 +71,8 @@ impl Drop for FrameTracker {
 type FrameAllocatorImpl = StackFrameAllocator;
 
 lazy_static! {
-    static ref FRAME_ALLOCATOR: spin::Mutex<FrameAllocatorImpl> =
-        spin::Mutex::new(FrameAllocatorImpl::new());
+    static ref FRAME_ALLOCATOR: SpinLock<FrameAllocatorImpl> =
+        SpinLock::new("FRAME_ALLOCATOR", FrameAllocatorImpl::new());
 }
 
 pub fn init_frame_allocator() {
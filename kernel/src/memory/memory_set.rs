@@ -1,24 +1,43 @@
-use core::arch::asm;
+use core::{
+    arch::asm,
+    mem::{align_of, size_of},
+    sync::atomic::{AtomicUsize, Ordering},
+};
 
 use super::{
     address::{PhysPageNum, VPNRange, VirtAddr, VirtPageNum},
     frame_allocator::{frame_alloc, FrameTracker},
-    page_table::{PTEFlags, PageTable, PageTableEntry},
+    page_table::{translated_byte_buffer, PTEFlags, PageTable, PageTableEntry, UserBuffer},
 };
 use crate::{
-    config::{MEMORY_END, TRAMPOLINE, TRAP_CONTEXT, USER_STACK_SIZE},
+    config::{MEMORY_END, MMIO, TRAMPOLINE, USER_SPACE_END},
     memory::address::{PhysAddr, PAGE_SIZE},
+    sync::SpinLock,
 };
 use alloc::{collections::BTreeMap, sync::Arc, vec::Vec};
 use bitflags::bitflags;
 use lazy_static::lazy_static;
 use log::trace;
 use riscv::register::satp;
-use spin::Mutex;
+use xmas_elf::{header::Class, program::ProgramHeader64};
 
 lazy_static! {
-    pub static ref KERNEL_SPACE: Arc<Mutex<MemorySet>> =
-        Arc::new(Mutex::new(MemorySet::new_kernel()));
+    pub static ref KERNEL_SPACE: Arc<SpinLock<MemorySet>> =
+        Arc::new(SpinLock::new("KERNEL_SPACE", MemorySet::new_kernel()));
+}
+
+/// Pages shared copy-on-write by `MemorySet::from_existed`
+static COW_SHARED_PAGES: AtomicUsize = AtomicUsize::new(0);
+/// Shared pages that had to be copied on a write
+static COW_COPIED_PAGES: AtomicUsize = AtomicUsize::new(0);
+
+/// Return how many pages fork has shared copy-on-write so far, and how many
+/// of them were copied afterwards.
+pub fn cow_stats() -> (usize, usize) {
+    (
+        COW_SHARED_PAGES.load(Ordering::Relaxed),
+        COW_COPIED_PAGES.load(Ordering::Relaxed),
+    )
 }
 
 pub struct MemorySet {
@@ -40,19 +59,19 @@ extern "C" {
 }
 
 impl MemorySet {
-    pub fn new_bare() -> Self {
-        let page_table = PageTable::new();
-        Self {
+    pub fn new_bare() -> Result<Self, &'static str> {
+        let page_table = PageTable::new()?;
+        Ok(Self {
             page_table,
             areas: Vec::new(),
-        }
+        })
     }
 
     /// Without kernel stacks.
     pub fn new_kernel() -> Self {
-        let mut memory_set = Self::new_bare();
+        let mut memory_set = Self::new_bare().unwrap();
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.map_trampoline().unwrap();
         // map kernel sections
         trace!(".text [{:#x}, {:#x})", stext as usize, etext as usize);
         trace!(".rodata [{:#x}, {:#x})", srodata as usize, erodata as usize);
@@ -112,110 +131,194 @@ impl MemorySet {
             ),
             None,
         );
+        trace!("mapping memory-mapped registers");
+        for &(start, len) in MMIO {
+            memory_set.push(
+                MapArea::new(
+                    start.into(),
+                    (start + len).into(),
+                    MapType::Identical,
+                    MapPermission::R | MapPermission::W,
+                ),
+                None,
+            );
+        }
         memory_set
     }
 
-    /// Include sections in elf and trampoline and TrapContext and user stack,
-    /// also returns user_sp and entry point.
-    pub fn from_elf(elf_data: &[u8]) -> (Self, usize, usize) {
-        let mut memory_set = Self::new_bare();
-        // map trampoline
-        memory_set.map_trampoline();
-        // map program headers of elf, with U flag
-        let elf = xmas_elf::ElfFile::new(elf_data).unwrap();
+    /// Include sections in elf and trampoline, also returns the bottom of the
+    /// (empty) heap and entry point. The user stacks and TrapContexts belong
+    /// to threads, which map their own.
+    ///
+    /// The heap starts one guard page after the last segment and grows up
+    /// through [`MemorySet::append_to`], towards the user stacks far above.
+    ///
+    /// With the `demand-paging` feature, segments are only recorded here, and
+    /// get their frames in [`MemorySet::handle_page_fault`].
+    ///
+    /// Fails if `elf_data` is not a 64-bit ELF whose segments lie within the
+    /// file and user space without overlapping, or if memory runs out.
+    pub fn from_elf(elf_data: &[u8]) -> Result<(Self, usize, usize), &'static str> {
+        let elf = xmas_elf::ElfFile::new(elf_data)?;
         let elf_header = elf.header;
-        let magic = elf_header.pt1.magic;
-        assert_eq!(magic, [0x7f, 0x45, 0x4c, 0x46], "invalid elf!");
+        if elf_header.pt1.class() != Class::SixtyFour {
+            return Err("not a 64-bit ELF");
+        }
+        // xmas_elf panics on program headers past the end of the file or
+        // misaligned, so those are rejected here
         let ph_count = elf_header.pt2.ph_count();
+        let ph_offset = elf_header.pt2.ph_offset() as usize;
+        let ph_size = size_of::<ProgramHeader64>();
+        let ph_end = (ph_count as usize)
+            .checked_mul(ph_size)
+            .and_then(|size| size.checked_add(ph_offset));
+        if ph_count > 0
+            && (elf_header.pt2.ph_entry_size() as usize != ph_size
+                || !(elf_data.as_ptr() as usize)
+                    .wrapping_add(ph_offset)
+                    .is_multiple_of(align_of::<ProgramHeader64>())
+                || !ph_end.is_some_and(|end| end <= elf_data.len()))
+        {
+            return Err("program headers out of bounds");
+        }
+        let mut memory_set = Self::new_bare()?;
+        // map trampoline
+        memory_set.map_trampoline()?;
+        // map program headers of elf, with U flag
         let mut max_end_vpn = VirtPageNum(0);
         for i in 0..ph_count {
-            let ph = elf.program_header(i).unwrap();
-            if ph.get_type().unwrap() == xmas_elf::program::Type::Load {
-                let start_va: VirtAddr = (ph.virtual_addr() as usize).into();
-                let end_va: VirtAddr = ((ph.virtual_addr() + ph.mem_size()) as usize).into();
-                trace!(
-                    "mapping [{:#x}, {:#x}), offset={:#x}, file_size={:#x}",
-                    usize::from(start_va),
-                    usize::from(end_va),
-                    ph.offset(),
-                    ph.file_size()
-                );
-                let mut map_perm = MapPermission::U;
-                let ph_flags = ph.flags();
-                if ph_flags.is_read() {
-                    map_perm |= MapPermission::R;
-                }
-                if ph_flags.is_write() {
-                    map_perm |= MapPermission::W;
-                }
-                if ph_flags.is_execute() {
-                    map_perm |= MapPermission::X;
-                }
-                let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
-                max_end_vpn = map_area.range.end;
-                memory_set.push(
-                    map_area,
-                    Some(&elf.input[ph.offset() as usize..(ph.offset() + ph.file_size()) as usize]),
-                );
+            let ph = elf.program_header(i)?;
+            if ph.get_type()? != xmas_elf::program::Type::Load {
+                continue;
+            }
+            let offset = ph.offset() as usize;
+            let data = offset
+                .checked_add(ph.file_size() as usize)
+                .and_then(|end| elf_data.get(offset..end))
+                .ok_or("segment out of file bounds")?;
+            if ph.file_size() > ph.mem_size() {
+                return Err("segment larger in the file than in memory");
+            }
+            let start = ph.virtual_addr() as usize;
+            let end = start
+                .checked_add(ph.mem_size() as usize)
+                .filter(|&end| end <= USER_SPACE_END)
+                .ok_or("segment out of user space")?;
+            let start_va: VirtAddr = start.into();
+            let end_va: VirtAddr = end.into();
+            trace!(
+                "mapping [{:#x}, {:#x}), offset={:#x}, file_size={:#x}",
+                usize::from(start_va),
+                usize::from(end_va),
+                ph.offset(),
+                ph.file_size()
+            );
+            let mut map_perm = MapPermission::U;
+            let ph_flags = ph.flags();
+            if ph_flags.is_read() {
+                map_perm |= MapPermission::R;
+            }
+            if ph_flags.is_write() {
+                map_perm |= MapPermission::W;
+            }
+            if ph_flags.is_execute() {
+                map_perm |= MapPermission::X;
+            }
+            let map_area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+            if memory_set
+                .areas
+                .iter()
+                .any(|other| other.range.overlaps(&map_area.range))
+            {
+                return Err("segments overlap");
+            }
+            max_end_vpn = max_end_vpn.max(map_area.range.end);
+            if cfg!(feature = "demand-paging") {
+                memory_set.push_lazy(map_area, Some(AreaData::new(start_va, data)));
+            } else {
+                memory_set.try_push(map_area, Some(data))?;
             }
         }
-        // map user stack with U flags
+        // map an empty heap with U flags
         let max_end_va: VirtAddr = max_end_vpn.into();
-        let mut user_stack_bottom: usize = max_end_va.into();
+        let mut heap_bottom: usize = max_end_va.into();
         // guard page
-        user_stack_bottom += PAGE_SIZE;
-        let user_stack_top = user_stack_bottom + USER_STACK_SIZE;
-        trace!(
-            "mapping user stack [{:#x}, {:#x})",
-            user_stack_bottom,
-            user_stack_top
-        );
+        heap_bottom += PAGE_SIZE;
+        trace!("mapping heap at {:#x}", heap_bottom);
         memory_set.push(
             MapArea::new(
-                user_stack_bottom.into(),
-                user_stack_top.into(),
+                heap_bottom.into(),
+                heap_bottom.into(),
                 MapType::Framed,
                 MapPermission::R | MapPermission::W | MapPermission::U,
             ),
             None,
         );
-        // map TrapContext
-        trace!("mapping TrapContext");
-        memory_set.push(
-            MapArea::new(
-                TRAP_CONTEXT.into(),
-                TRAMPOLINE.into(),
-                MapType::Framed,
-                MapPermission::R | MapPermission::W,
-            ),
-            None,
-        );
-        (
+        Ok((
             memory_set,
-            user_stack_top,
+            heap_bottom,
             elf.header.pt2.entry_point() as usize,
-        )
+        ))
     }
 
-    pub fn from_existed(user_space: &MemorySet) -> Self {
-        let mut memory_set = Self::new_bare();
+    /// Clone a user address space for fork.
+    ///
+    /// User pages are not copied: both spaces map the same frames with
+    /// writing disabled, and the first store to such a page copies it (see
+    /// [`MemorySet::resolve_cow`]). Kernel-only areas like the TrapContext
+    /// are still copied right away.
+    ///
+    /// Fails if frames run out, after which `user_space` still works as
+    /// before, with some of its pages copy-on-write.
+    pub fn from_existed(user_space: &mut MemorySet) -> Result<Self, &'static str> {
+        let mut memory_set = Self::new_bare()?;
         // map trampoline
-        memory_set.map_trampoline();
+        memory_set.map_trampoline()?;
         for area in &user_space.areas {
-            let new_area = MapArea::new(
+            let mut new_area = MapArea::new(
                 area.range.start.into(),
                 area.range.end.into(),
                 area.map_type,
                 area.map_perm,
             );
-            memory_set.push(new_area, None);
+            if area.map_type == MapType::Framed && area.map_perm.contains(MapPermission::U) {
+                // pages not loaded yet stay lazy in the child as well
+                new_area.data = area.data.clone();
+                let mut shared = 0;
+                for (&vpn, frame) in area.data_frames.iter() {
+                    let pte = user_space.page_table.translate(vpn).unwrap();
+                    if pte.writable() && Arc::strong_count(frame) > 1 {
+                        // held by a `UserBuffer` the kernel may still write
+                        // through, which the child must not see, or by a
+                        // futex waiter, which must find the parent's word
+                        let copy = frame_alloc().ok_or("Frame allocation failed")?;
+                        copy.ppn
+                            .get_bytes_array()
+                            .copy_from_slice(frame.ppn.get_bytes_array());
+                        memory_set.page_table.map(vpn, copy.ppn, pte.flags())?;
+                        new_area.data_frames.insert(vpn, Arc::new(copy));
+                        continue;
+                    }
+                    // should the fork fail, the parent's page is still a valid
+                    // copy-on-write one, made writable again on its next write
+                    let pte_flags = pte.flags() - PTEFlags::W;
+                    pte.set_flags(pte_flags);
+                    memory_set.page_table.map(vpn, frame.ppn, pte_flags)?;
+                    new_area.data_frames.insert(vpn, frame.clone());
+                    shared += 1;
+                }
+                COW_SHARED_PAGES.fetch_add(shared, Ordering::Relaxed);
+                memory_set.areas.push(new_area);
+                continue;
+            }
+            memory_set.try_push(new_area, None)?;
             for vpn in area.range.iter() {
                 let src = user_space.translate(vpn).unwrap().ppn().get_bytes_array();
                 let dst = memory_set.translate(vpn).unwrap().ppn().get_bytes_array();
                 dst.copy_from_slice(src);
             }
         }
-        memory_set
+        Ok(memory_set)
     }
 }
 
@@ -233,48 +336,148 @@ impl MemorySet {
         ((((mode as usize) << 16) | asid) << 44) | ppn.0
     }
 
-    fn map_trampoline(&mut self) {
+    fn map_trampoline(&mut self) -> Result<(), &'static str> {
         trace!("mapping trampoline");
-        self.page_table
-            .map(
-                VirtAddr::from(TRAMPOLINE).page_number_floor(),
-                PhysAddr::from(strampoline as usize).page_number_floor(),
-                PTEFlags::R | PTEFlags::X,
-            )
-            .unwrap();
+        self.page_table.map(
+            VirtAddr::from(TRAMPOLINE).page_number_floor(),
+            PhysAddr::from(strampoline as usize).page_number_floor(),
+            PTEFlags::R | PTEFlags::X,
+        )
     }
 
+    /// Map `[start_va, end_va)` to newly allocated frames, failing if it
+    /// overlaps an existing area or frames run out.
     pub fn insert_framed_area(
         &mut self,
         start_va: VirtAddr,
         end_va: VirtAddr,
         map_perm: MapPermission,
-    ) {
-        self.push(
-            MapArea::new(start_va, end_va, MapType::Framed, map_perm),
-            None,
-        );
+    ) -> Result<(), &'static str> {
+        let mut area = MapArea::new(start_va, end_va, MapType::Framed, map_perm);
+        if self
+            .areas
+            .iter()
+            .any(|other| other.range.overlaps(&area.range))
+        {
+            return Err("MapArea overlaps an existing one");
+        }
+        if cfg!(feature = "demand-paging") && map_perm.contains(MapPermission::U) {
+            self.push_lazy(area, None);
+            return Ok(());
+        }
+        if let Err(err) = area.apply_mapping(&mut self.page_table) {
+            let _ = area.unapply_mapping(&mut self.page_table);
+            return Err(err);
+        }
+        self.areas.push(area);
+        Ok(())
     }
 
-    pub fn remove_area(&mut self, start_va: VirtAddr, end_va: VirtAddr) {
-        let (i, area) = self
+    /// Unmap `[start_va, end_va)`, which may cover several areas or only
+    /// part of one; what is left of a partially covered area stays mapped.
+    ///
+    /// Fails without unmapping anything if some page in the range is not
+    /// mapped by any area.
+    pub fn remove_area(
+        &mut self,
+        start_va: VirtAddr,
+        end_va: VirtAddr,
+    ) -> Result<(), &'static str> {
+        let range = VPNRange::new(start_va.page_number_floor(), end_va.page_number_ceil());
+        let covered: usize = self
             .areas
-            .iter_mut()
-            .enumerate()
-            .find(|(_, area)| {
-                area.range.start == start_va.page_number_floor()
-                    && area.range.end == end_va.page_number_ceil()
+            .iter()
+            .filter(|area| area.range.overlaps(&range))
+            .map(|area| {
+                VPNRange::new(
+                    area.range.start.max(range.start),
+                    area.range.end.min(range.end),
+                )
+                .len()
             })
+            .sum();
+        if covered != range.len() {
+            return Err("MapArea not found");
+        }
+        let mut areas = Vec::new();
+        for mut area in core::mem::take(&mut self.areas) {
+            if !area.range.overlaps(&range) {
+                areas.push(area);
+                continue;
+            }
+            if area.range.end > range.end {
+                areas.push(area.split_off(range.end));
+            }
+            let mut removed = if area.range.start < range.start {
+                let removed = area.split_off(range.start);
+                areas.push(area);
+                removed
+            } else {
+                area
+            };
+            removed.unapply_mapping(&mut self.page_table)?;
+        }
+        self.areas = areas;
+        Ok(())
+    }
+    /// Shrink the area starting at `start` so that it ends at `new_end`.
+    pub fn shrink_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> Result<(), &'static str> {
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.range.start == start.page_number_floor())
+            .ok_or("MapArea not found")?;
+        area.shrink_to(&mut self.page_table, new_end.page_number_ceil())
+    }
+
+    /// Grow the area starting at `start` so that it ends at `new_end`,
+    /// failing if it would run into another area.
+    pub fn append_to(&mut self, start: VirtAddr, new_end: VirtAddr) -> Result<(), &'static str> {
+        let start_vpn = start.page_number_floor();
+        let new_end_vpn = new_end.page_number_ceil();
+        let grown = self
+            .areas
+            .iter()
+            .find(|area| area.range.start == start_vpn)
+            .map(|area| VPNRange::new(area.range.end, new_end_vpn))
+            .ok_or("MapArea not found")?;
+        if self.areas.iter().any(|area| area.range.overlaps(&grown)) {
+            return Err("MapArea overlaps an existing one");
+        }
+        let area = self
+            .areas
+            .iter_mut()
+            .find(|area| area.range.start == start_vpn)
             .unwrap();
-        area.unapply_mapping(&mut self.page_table).unwrap();
-        self.areas.remove(i);
+        area.append_to(&mut self.page_table, new_end_vpn)
+    }
+
+    /// Release every area and its frames, keeping only the page table itself.
+    pub fn recycle_data_pages(&mut self) {
+        self.areas.clear();
+    }
+    fn push(&mut self, area: MapArea, data: Option<&[u8]>) {
+        self.try_push(area, data).unwrap();
     }
-    fn push(&mut self, mut area: MapArea, data: Option<&[u8]>) {
-        area.apply_mapping(&mut self.page_table).unwrap();
+    /// Like [`MemorySet::push`], but fails, leaving nothing mapped, if a page
+    /// cannot be mapped.
+    fn try_push(&mut self, mut area: MapArea, data: Option<&[u8]>) -> Result<(), &'static str> {
+        if let Err(err) = area.apply_mapping(&mut self.page_table) {
+            let _ = area.unapply_mapping(&mut self.page_table);
+            return Err(err);
+        }
         if let Some(data) = data {
             area.copy_data(&mut self.page_table, data);
         }
         self.areas.push(area);
+        Ok(())
+    }
+    /// Record a framed area without mapping anything; each page is loaded
+    /// from `data` (or zeroed) on its first access.
+    fn push_lazy(&mut self, mut area: MapArea, data: Option<AreaData>) {
+        assert_eq!(area.map_type, MapType::Framed);
+        area.data = data;
+        self.areas.push(area);
     }
 }
 
@@ -282,13 +485,163 @@ impl MemorySet {
     pub fn translate(&self, vpn: VirtPageNum) -> Result<&mut PageTableEntry, &'static str> {
         self.page_table.translate(vpn)
     }
+
+    /// Make a copy-on-write page writable again, copying its frame if it is
+    /// still shared with another address space.
+    ///
+    /// Returns false if `vpn` is not a copy-on-write page, in which case a
+    /// store to it is a real fault.
+    pub fn resolve_cow(&mut self, vpn: VirtPageNum) -> bool {
+        let Some(area) = self.areas.iter_mut().find(|area| area.range.contains(vpn)) else {
+            return false;
+        };
+        if area.map_type != MapType::Framed || !area.map_perm.contains(MapPermission::W) {
+            return false;
+        }
+        let Some(frame) = area.data_frames.get(&vpn) else {
+            return false;
+        };
+        let pte = self.page_table.translate(vpn).unwrap();
+        if pte.writable() {
+            return false;
+        }
+        let pte_flags = pte.flags() | PTEFlags::W;
+        if Arc::strong_count(frame) == 1 {
+            // every other sharer has copied or dropped it already
+            pte.set_flags(pte_flags);
+            return true;
+        }
+        let Some(new_frame) = frame_alloc() else {
+            return false;
+        };
+        new_frame
+            .ppn
+            .get_bytes_array()
+            .copy_from_slice(frame.ppn.get_bytes_array());
+        *pte = PageTableEntry::new(new_frame.ppn, pte_flags);
+        area.data_frames.insert(vpn, Arc::new(new_frame));
+        COW_COPIED_PAGES.fetch_add(1, Ordering::Relaxed);
+        trace!("copied cow page {:#x}", vpn.0);
+        true
+    }
+
+    /// Handle a user page fault at `vpn` by an access that needs `perm`,
+    /// either by loading a lazily mapped page or by resolving copy-on-write.
+    ///
+    /// Returns false if the access is not allowed, i.e. a real fault.
+    pub fn handle_page_fault(&mut self, vpn: VirtPageNum, perm: MapPermission) -> bool {
+        let Some(area) = self.areas.iter_mut().find(|area| area.range.contains(vpn)) else {
+            return false;
+        };
+        if area.map_type != MapType::Framed || !area.map_perm.contains(perm | MapPermission::U) {
+            return false;
+        }
+        if !area.data_frames.contains_key(&vpn) {
+            return area.load_page(&mut self.page_table, vpn).is_ok();
+        }
+        perm.contains(MapPermission::W) && self.resolve_cow(vpn)
+    }
+
+    /// Make `[start, start + len)` accessible with `perm` before the kernel
+    /// touches it through the physical frames, loading lazy pages and
+    /// breaking copy-on-write sharing as a user access would.
+    ///
+    /// Returns false if part of the range is not valid user memory.
+    pub fn fault_in(&mut self, start: usize, len: usize, perm: MapPermission) -> bool {
+        let range = VPNRange::new(
+            VirtAddr::from(start).page_number_floor(),
+            VirtAddr::from(start + len).page_number_ceil(),
+        );
+        let pte_flags = PTEFlags::from_bits_retain((perm | MapPermission::U).bits()) | PTEFlags::V;
+        range.iter().all(|vpn| {
+            matches!(self.translate(vpn), Ok(pte) if pte.flags().contains(pte_flags))
+                || self.handle_page_fault(vpn, perm)
+        })
+    }
+
+    /// Return the frame of the framed page `vpn`, if it is loaded.
+    ///
+    /// Fork copies a writable page whose frame is held this way for the
+    /// child, instead of sharing it copy-on-write.
+    pub fn frame(&self, vpn: VirtPageNum) -> Option<Arc<FrameTracker>> {
+        let area = self.areas.iter().find(|area| area.range.contains(vpn))?;
+        area.data_frames.get(&vpn).cloned()
+    }
+
+    /// Fault in `[ptr, ptr + len)` with `perm` as [`MemorySet::fault_in`]
+    /// does, and return it as a [`UserBuffer`] that holds on to its frames.
+    ///
+    /// A syscall that blocks while using the buffer drops the process lock,
+    /// so another thread may unmap the pages or fork meanwhile. The frames
+    /// held stay allocated until the buffer is dropped, and fork copies a
+    /// held writable page for the child instead of sharing it.
+    pub fn user_buffer(
+        &mut self,
+        ptr: *const u8,
+        len: usize,
+        perm: MapPermission,
+    ) -> Option<UserBuffer> {
+        if !self.fault_in(ptr as usize, len, perm) {
+            return None;
+        }
+        let range = VPNRange::new(
+            VirtAddr::from(ptr as usize).page_number_floor(),
+            VirtAddr::from(ptr as usize + len).page_number_ceil(),
+        );
+        let frames = range.iter().map(|vpn| self.frame(vpn).unwrap()).collect();
+        Some(UserBuffer::new(
+            translated_byte_buffer(self.satp_token(), ptr, len),
+            frames,
+        ))
+    }
+
+    /// Like [`MemorySet::fault_in`] for the readable NUL-terminated string at
+    /// `ptr`, whose length is only known while walking it.
+    pub fn fault_in_str(&mut self, ptr: usize) -> bool {
+        let mut va = VirtAddr::from(ptr);
+        loop {
+            if !self.fault_in(va.into(), 1, MapPermission::R) {
+                return false;
+            }
+            let vpn = va.page_number_floor();
+            let page = self.translate(vpn).unwrap().ppn().get_bytes_array();
+            if page[va.page_offset()..].contains(&0) {
+                return true;
+            }
+            va = VirtPageNum(vpn.0 + 1).into();
+        }
+    }
+}
+
+/// Initial contents of a lazily loaded area: `bytes` placed at `start`, with
+/// the rest of the area zeroed.
+///
+/// The bytes are copied out of the ELF file, which is gone once exec returns,
+/// and shared by the halves of a split area and by forked address spaces.
+#[derive(Clone)]
+struct AreaData {
+    start: usize,
+    bytes: Arc<[u8]>,
+}
+
+impl AreaData {
+    fn new(start: VirtAddr, bytes: &[u8]) -> Self {
+        Self {
+            start: start.into(),
+            bytes: Arc::from(bytes),
+        }
+    }
 }
 
 struct MapArea {
     range: VPNRange, // [start_vpn, end_vpn)
-    data_frames: BTreeMap<VirtPageNum, FrameTracker>,
+    /// frames may be shared with other address spaces after a fork, and a
+    /// lazily loaded area has no frame for pages not accessed yet
+    data_frames: BTreeMap<VirtPageNum, Arc<FrameTracker>>,
     map_type: MapType,
     map_perm: MapPermission,
+    /// where to load missing pages from, see [`MemorySet::push_lazy`]
+    data: Option<AreaData>,
 }
 
 impl MapArea {
@@ -305,23 +658,24 @@ impl MapArea {
             data_frames: BTreeMap::new(),
             map_type,
             map_perm,
+            data: None,
         }
     }
     pub fn apply_mapping(&mut self, page_table: &mut PageTable) -> Result<(), &'static str> {
         for vpn in self.range.iter() {
-            let ppn: PhysPageNum;
             let pte_flags = PTEFlags::from_bits_retain(self.map_perm.bits());
             match self.map_type {
                 MapType::Identical => {
-                    ppn = PhysPageNum(vpn.0);
+                    page_table.map(vpn, PhysPageNum(vpn.0), pte_flags)?;
                 }
                 MapType::Framed => {
                     let frame = frame_alloc().ok_or("Frame allocation failed")?;
-                    ppn = frame.ppn;
-                    self.data_frames.insert(vpn.into(), frame);
+                    page_table.map(vpn, frame.ppn, pte_flags)?;
+                    // only pages that got mapped are recorded, so that
+                    // `unapply_mapping` undoes a partial failure exactly
+                    self.data_frames.insert(vpn, Arc::new(frame));
                 }
             }
-            page_table.map(vpn, ppn, pte_flags)?;
         }
         Ok(())
     }
@@ -331,13 +685,91 @@ impl MapArea {
             match self.map_type {
                 MapType::Identical => {}
                 MapType::Framed => {
-                    self.data_frames.remove(&vpn.into());
+                    if self.data_frames.remove(&vpn.into()).is_none() {
+                        // never loaded
+                        continue;
+                    }
                 }
             }
             page_table.unmap(vpn.into())?;
         }
         Ok(())
     }
+    /// Unmap the pages from `new_end` on and end the range there.
+    pub fn shrink_to(
+        &mut self,
+        page_table: &mut PageTable,
+        new_end: VirtPageNum,
+    ) -> Result<(), &'static str> {
+        if new_end < self.range.start || new_end > self.range.end {
+            return Err("MapArea cannot shrink past its bounds");
+        }
+        let mut removed = self.split_off(new_end);
+        removed.unapply_mapping(page_table)
+    }
+    /// Extend the range to `new_end`, mapping the new pages unless they are
+    /// loaded on demand.
+    pub fn append_to(
+        &mut self,
+        page_table: &mut PageTable,
+        new_end: VirtPageNum,
+    ) -> Result<(), &'static str> {
+        if new_end < self.range.end {
+            return Err("MapArea cannot append backwards");
+        }
+        let mut appended = MapArea {
+            range: VPNRange::new(self.range.end, new_end),
+            data_frames: BTreeMap::new(),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            data: self.data.clone(),
+        };
+        if !cfg!(feature = "demand-paging") {
+            if let Err(err) = appended.apply_mapping(page_table) {
+                let _ = appended.unapply_mapping(page_table);
+                return Err(err);
+            }
+        }
+        self.data_frames.append(&mut appended.data_frames);
+        self.range.end = new_end;
+        Ok(())
+    }
+    /// Shrink to `[start, vpn)` and return the rest as a new area, which is
+    /// empty if `vpn` is the end.
+    fn split_off(&mut self, vpn: VirtPageNum) -> MapArea {
+        assert!(self.range.start <= vpn && vpn <= self.range.end);
+        let tail = MapArea {
+            range: VPNRange::new(vpn, self.range.end),
+            data_frames: self.data_frames.split_off(&vpn),
+            map_type: self.map_type,
+            map_perm: self.map_perm,
+            data: self.data.clone(),
+        };
+        self.range.end = vpn;
+        tail
+    }
+    /// Allocate and map the frame for `vpn` of a lazily loaded area.
+    pub fn load_page(
+        &mut self,
+        page_table: &mut PageTable,
+        vpn: VirtPageNum,
+    ) -> Result<(), &'static str> {
+        let frame = frame_alloc().ok_or("Frame allocation failed")?;
+        if let Some(data) = &self.data {
+            let page_start: usize = VirtAddr::from(vpn).into();
+            let start = page_start.max(data.start);
+            let end = (page_start + PAGE_SIZE).min(data.start + data.bytes.len());
+            if start < end {
+                frame.ppn.get_bytes_array()[start - page_start..end - page_start]
+                    .copy_from_slice(&data.bytes[start - data.start..end - data.start]);
+            }
+        }
+        let pte_flags = PTEFlags::from_bits_retain(self.map_perm.bits());
+        page_table.map(vpn, frame.ppn, pte_flags)?;
+        self.data_frames.insert(vpn, Arc::new(frame));
+        trace!("loaded lazy page {:#x}", vpn.0);
+        Ok(())
+    }
     /// data: start-aligned but maybe with shorter length
     /// assume that all frames were cleared before
     pub fn copy_data(&mut self, page_table: &mut PageTable, data: &[u8]) {
@@ -410,3 +842,63 @@ fn remap_test() {
     );
     println!("remap_test passed!");
 }
+
+#[test_case]
+fn cow_test() {
+    let vpn = VirtPageNum(0x10);
+    let mut parent = MemorySet::new_bare().unwrap();
+    parent
+        .insert_framed_area(
+            vpn.into(),
+            VirtPageNum(vpn.0 + 1).into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        )
+        .unwrap();
+    assert!(parent.fault_in(VirtAddr::from(vpn).into(), PAGE_SIZE, MapPermission::W));
+    parent.translate(vpn).unwrap().ppn().get_bytes_array()[0] = 42;
+    let mut child = MemorySet::from_existed(&mut parent).unwrap();
+    let shared = parent.translate(vpn).unwrap().ppn();
+    assert_eq!(child.translate(vpn).unwrap().ppn(), shared);
+    assert!(!parent.translate(vpn).unwrap().writable());
+    assert!(!child.translate(vpn).unwrap().writable());
+    // the first writer gets its own copy, the last one keeps the frame
+    assert!(child.resolve_cow(vpn));
+    let copied = child.translate(vpn).unwrap().ppn();
+    assert_ne!(copied, shared);
+    assert_eq!(copied.get_bytes_array()[0], 42);
+    assert!(parent.resolve_cow(vpn));
+    assert_eq!(parent.translate(vpn).unwrap().ppn(), shared);
+    assert!(parent.translate(vpn).unwrap().writable());
+    println!("cow_test passed!");
+}
+
+#[test_case]
+fn user_buffer_test() {
+    let vpn = VirtPageNum(0x10);
+    let mut parent = MemorySet::new_bare().unwrap();
+    parent
+        .insert_framed_area(
+            vpn.into(),
+            VirtPageNum(vpn.0 + 1).into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        )
+        .unwrap();
+    let ptr = usize::from(VirtAddr::from(vpn)) as *const u8;
+    let mut buf = parent
+        .user_buffer(ptr, PAGE_SIZE, MapPermission::W)
+        .unwrap();
+    let held = parent.translate(vpn).unwrap().ppn();
+    // a fork while the buffer is held leaves the page to the parent
+    let child = MemorySet::from_existed(&mut parent).unwrap();
+    assert_eq!(parent.translate(vpn).unwrap().ppn(), held);
+    assert!(parent.translate(vpn).unwrap().writable());
+    assert_ne!(child.translate(vpn).unwrap().ppn(), held);
+    *buf.iter_mut().next().unwrap() = 42;
+    assert_eq!(child.translate(vpn).unwrap().ppn().get_bytes_array()[0], 0);
+    // and an unmap leaves the frame to the buffer until it is dropped
+    parent.recycle_data_pages();
+    *buf.iter_mut().next().unwrap() = 43;
+    assert_eq!(held.get_bytes_array()[0], 43);
+    drop(buf);
+    println!("user_buffer_test passed!");
+}
@@ -6,9 +6,13 @@ mod heap_allocator;
 mod memory_set;
 mod page_table;
 
-pub use memory_set::{MapPermission, MemorySet, KERNEL_SPACE};
+pub use frame_allocator::{frame_alloc, FrameTracker};
+pub use memory_set::{cow_stats, MapPermission, MemorySet, KERNEL_SPACE};
 pub use page_table::translated_byte_buffer;
+pub use page_table::translated_ref;
+pub use page_table::translated_refmut;
 pub use page_table::translated_str;
+pub use page_table::UserBuffer;
 
 pub fn init() {
     heap_allocator::init_heap();
@@ -1,3 +1,4 @@
+use alloc::sync::Arc;
 use alloc::vec::Vec;
 use alloc::{string::String, vec};
 use bitflags::*;
@@ -5,7 +6,7 @@ use riscv::register::satp;
 
 use super::address::PAGE_SIZE;
 use super::{
-    address::{PhysPageNum, VirtAddr, VirtPageNum},
+    address::{PhysAddr, PhysPageNum, VirtAddr, VirtPageNum},
     frame_allocator::{frame_alloc, FrameTracker},
 };
 
@@ -15,13 +16,13 @@ pub struct PageTable {
 }
 
 impl PageTable {
-    pub fn new() -> Self {
-        let frame = frame_alloc().unwrap();
+    pub fn new() -> Result<Self, &'static str> {
+        let frame = frame_alloc().ok_or("Frame allocation failed")?;
         let page_table = Self {
             root_ppn: frame.ppn,
             frames: vec![frame],
         };
-        page_table
+        Ok(page_table)
     }
     pub fn from_satp_token(satp: usize) -> Self {
         Self {
@@ -99,6 +100,7 @@ pub struct PageTableEntry {
 
 bitflags! {
     /// page table entry flags
+    #[derive(Clone, Copy)]
     pub struct PTEFlags: u8 {
         const V = 1 << 0;
         const R = 1 << 1;
@@ -126,6 +128,10 @@ impl PageTableEntry {
     pub fn flags(&self) -> PTEFlags {
         PTEFlags::from_bits_truncate(self.bits as u8)
     }
+    /// replace the flags, keeping the ppn
+    pub fn set_flags(&mut self, flags: PTEFlags) {
+        self.bits = (self.bits & !0xff) | flags.bits() as usize;
+    }
     pub fn is_valid(&self) -> bool {
         self.flags().contains(PTEFlags::V)
     }
@@ -182,3 +188,43 @@ pub fn translated_str(token: usize, ptr: *const u8) -> String {
         start_va = VirtPageNum::from(vpn.0 + 1).into();
     }
 }
+
+/// translate a pointer to a reference of `T` through page table
+pub fn translated_ref<T>(token: usize, ptr: *const T) -> &'static T {
+    translated_refmut(token, ptr as *mut T)
+}
+
+/// translate a pointer to a mutable reference of `T` through page table
+pub fn translated_refmut<T>(token: usize, ptr: *mut T) -> &'static mut T {
+    let page_table = PageTable::from_satp_token(token);
+    let va = VirtAddr::from(ptr as usize);
+    let ppn = page_table.translate(va.page_number_floor()).unwrap().ppn();
+    let pa = usize::from(PhysAddr::from(ppn)) + va.page_offset();
+    unsafe { (pa as *mut T).as_mut().unwrap() }
+}
+
+/// An array of translated user buffers, which together form one contiguous
+/// buffer in user space
+pub struct UserBuffer {
+    pub buffers: Vec<&'static mut [u8]>,
+    /// the frames under `buffers`, kept allocated while they are in use
+    _frames: Vec<Arc<FrameTracker>>,
+}
+
+impl UserBuffer {
+    pub fn new(buffers: Vec<&'static mut [u8]>, frames: Vec<Arc<FrameTracker>>) -> Self {
+        Self {
+            buffers,
+            _frames: frames,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.buffers.iter().map(|buffer| buffer.len()).sum()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = &u8> {
+        self.buffers.iter().flat_map(|buffer| buffer.iter())
+    }
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut u8> {
+        self.buffers.iter_mut().flat_map(|buffer| buffer.iter_mut())
+    }
+}
@@ -0,0 +1,56 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::{Mutex, SpinLock};
+use crate::task::{
+    block_current_and_run_next_task, current_thread, signal_pending, wakeup_thread, Thread,
+};
+
+/// A condition variable, waited on with a [`Mutex`] held
+pub struct Condvar {
+    wait_queue: SpinLock<VecDeque<Arc<Thread>>>,
+}
+
+impl Condvar {
+    pub fn new() -> Self {
+        Self {
+            wait_queue: SpinLock::new("Condvar::wait_queue", VecDeque::new()),
+        }
+    }
+
+    /// Wake up one of the waiting threads, if any.
+    pub fn signal(&self) {
+        if let Some(waiter) = self.wait_queue.lock().pop_front() {
+            wakeup_thread(waiter);
+        }
+    }
+
+    /// Release `mutex`, wait to be signalled and lock `mutex` again. Returns
+    /// false without waiting if `mutex` is not locked, and false as well if
+    /// a signal interrupts the wait, after locking `mutex` again unless that
+    /// is interrupted too.
+    pub fn wait(&self, mutex: Arc<dyn Mutex>) -> bool {
+        if !mutex.unlock() {
+            return false;
+        }
+        // nothing runs in between, so no signal is missed
+        let thread = current_thread();
+        // compared by address, as in `MutexBlocking::lock`
+        let id = Arc::as_ptr(&thread);
+        self.wait_queue.lock().push_back(thread);
+        let signalled = loop {
+            let interrupted = signal_pending();
+            let mut wait_queue = self.wait_queue.lock();
+            // `signal` takes us off the queue
+            let Some(index) = wait_queue.iter().position(|other| Arc::as_ptr(other) == id) else {
+                break true;
+            };
+            if interrupted {
+                wait_queue.remove(index);
+                break false;
+            }
+            drop(wait_queue);
+            block_current_and_run_next_task();
+        };
+        mutex.lock() && signalled
+    }
+}
@@ -0,0 +1,114 @@
+//! Futexes, wait queues keyed by the physical address of a user word
+//!
+//! Keying by physical address rather than by process and virtual address
+//! lets threads of different processes wait on a word they share. Each
+//! waiter holds the frame of the word, so that it is not reused while the
+//! queue still refers to it, and so that a fork leaves it to its process
+//! instead of sharing it copy-on-write, which would move the word to another
+//! frame on the next write. A wait with a timeout is also in the timer
+//! queue, which wakes the thread up if [`futex_wake`] does not first.
+
+use alloc::{collections::BTreeMap, collections::VecDeque, sync::Arc};
+
+use super::SpinLock;
+use crate::{
+    memory::FrameTracker,
+    task::{
+        block_current_and_run_next_task, current_thread, signal_pending, wakeup_thread, Thread,
+    },
+    timer::{self, add_timer, remove_timer},
+};
+
+struct Waiter {
+    thread: Arc<Thread>,
+    /// the frame of the word waited on
+    _frame: Arc<FrameTracker>,
+}
+
+static FUTEX_QUEUES: SpinLock<BTreeMap<usize, VecDeque<Waiter>>> =
+    SpinLock::new("FUTEX_QUEUES", BTreeMap::new());
+
+/// Park the current thread on the futex at `pa`, in `frame`, until it is
+/// woken by [`futex_wake`], returning true, or until `deadline`, a
+/// `timer::read()` value, passes or a signal interrupts the wait, returning
+/// false.
+pub fn futex_wait(pa: usize, frame: Arc<FrameTracker>, deadline: Option<u64>) -> bool {
+    let thread = current_thread();
+    // compared by address, as an `Arc` kept on the stack across the wait
+    // would never be dropped if the process exits meanwhile
+    let id = Arc::as_ptr(&thread);
+    FUTEX_QUEUES
+        .lock()
+        .entry(pa)
+        .or_default()
+        .push_back(Waiter {
+            thread,
+            _frame: frame,
+        });
+    loop {
+        let interrupted = signal_pending();
+        let mut queues = FUTEX_QUEUES.lock();
+        // `futex_wake` takes the threads it wakes off the queue
+        let Some(queue) = queues.get_mut(&pa) else {
+            return true;
+        };
+        let Some(index) = queue
+            .iter()
+            .position(|waiter| Arc::as_ptr(&waiter.thread) == id)
+        else {
+            return true;
+        };
+        let timed_out = deadline.is_some_and(|deadline| timer::read() >= deadline);
+        if interrupted || timed_out {
+            queue.remove(index);
+            if queue.is_empty() {
+                queues.remove(&pa);
+            }
+            remove_timer(&current_thread());
+            return false;
+        }
+        drop(queues);
+        // a signal that does not interrupt the wait may have taken the
+        // thread off the timer queue to wake it up
+        if let Some(deadline) = deadline {
+            let thread = current_thread();
+            remove_timer(&thread);
+            add_timer(deadline, thread);
+        }
+        block_current_and_run_next_task();
+    }
+}
+
+/// Wake up at most `count` of the threads waiting on the futex at `pa`,
+/// returning how many were woken.
+pub fn futex_wake(pa: usize, count: usize) -> usize {
+    let mut queues = FUTEX_QUEUES.lock();
+    let Some(queue) = queues.get_mut(&pa) else {
+        return 0;
+    };
+    let mut woken = 0;
+    while woken < count {
+        let Some(waiter) = queue.pop_front() else {
+            break;
+        };
+        remove_timer(&waiter.thread);
+        // threads of processes that have exited since are skipped
+        if wakeup_thread(waiter.thread) {
+            woken += 1;
+        }
+    }
+    if queue.is_empty() {
+        queues.remove(&pa);
+    }
+    woken
+}
+
+/// Take `thread`, which is exiting, off every futex queue, so that neither
+/// it nor the frame it waits on is kept.
+pub fn futex_purge(thread: &Arc<Thread>) {
+    let mut queues = FUTEX_QUEUES.lock();
+    queues.retain(|_, queue| {
+        queue.retain(|waiter| !Arc::ptr_eq(&waiter.thread, thread));
+        !queue.is_empty()
+    });
+}
@@ -0,0 +1,20 @@
+//! Synchronization primitives
+//!
+//! [`SpinLock`] guards kernel data. The rest are for user threads: each
+//! process holds its own tables of these, indexed by the ids handed out by
+//! the `sys_*_create` syscalls. Apart from [`MutexSpin`], which yields while
+//! it waits, a thread that has to wait is parked on the wait queue of the
+//! primitive and left off the ready queue until it is woken up. A futex
+//! wait queue is not held by any process, but found by physical address.
+
+mod condvar;
+mod futex;
+mod mutex;
+mod semaphore;
+mod spin_lock;
+
+pub use condvar::Condvar;
+pub use futex::{futex_purge, futex_wait, futex_wake};
+pub use mutex::{Mutex, MutexBlocking, MutexSpin};
+pub use semaphore::Semaphore;
+pub use spin_lock::{lock_stats, SpinLock, SpinLockGuard};
@@ -0,0 +1,122 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next_task, current_thread, signal_pending,
+    suspend_current_and_run_next_task, wakeup_thread, Thread,
+};
+
+pub trait Mutex: Sync + Send {
+    /// Lock the mutex, waiting for it if need be. Returns false, without the
+    /// mutex, if a signal interrupts the wait.
+    fn lock(&self) -> bool;
+    /// Release the mutex, returning false if it was not locked.
+    fn unlock(&self) -> bool;
+}
+
+/// A mutex whose waiters yield until it is free
+pub struct MutexSpin {
+    locked: SpinLock<bool>,
+}
+
+impl MutexSpin {
+    pub fn new() -> Self {
+        Self {
+            locked: SpinLock::new("MutexSpin::locked", false),
+        }
+    }
+}
+
+impl Mutex for MutexSpin {
+    fn lock(&self) -> bool {
+        loop {
+            let mut locked = self.locked.lock();
+            if !*locked {
+                *locked = true;
+                return true;
+            }
+            drop(locked);
+            if signal_pending() {
+                return false;
+            }
+            suspend_current_and_run_next_task();
+        }
+    }
+
+    fn unlock(&self) -> bool {
+        core::mem::replace(&mut *self.locked.lock(), false)
+    }
+}
+
+/// A mutex whose waiters block until it is handed to them
+pub struct MutexBlocking {
+    inner: SpinLock<MutexBlockingInner>,
+}
+
+struct MutexBlockingInner {
+    locked: bool,
+    wait_queue: VecDeque<Arc<Thread>>,
+}
+
+impl MutexBlocking {
+    pub fn new() -> Self {
+        Self {
+            inner: SpinLock::new(
+                "MutexBlocking::inner",
+                MutexBlockingInner {
+                    locked: false,
+                    wait_queue: VecDeque::new(),
+                },
+            ),
+        }
+    }
+}
+
+impl Mutex for MutexBlocking {
+    fn lock(&self) -> bool {
+        let mut inner = self.inner.lock();
+        if !inner.locked {
+            inner.locked = true;
+            return true;
+        }
+        let thread = current_thread();
+        // compared by address, as an `Arc` kept on the stack across the wait
+        // would never be dropped if the process exits meanwhile
+        let id = Arc::as_ptr(&thread);
+        inner.wait_queue.push_back(thread);
+        drop(inner);
+        loop {
+            let interrupted = signal_pending();
+            let mut inner = self.inner.lock();
+            // the unlocking thread takes us off the queue and leaves the
+            // mutex locked for us
+            let Some(index) = inner
+                .wait_queue
+                .iter()
+                .position(|other| Arc::as_ptr(other) == id)
+            else {
+                return true;
+            };
+            if interrupted {
+                inner.wait_queue.remove(index);
+                return false;
+            }
+            drop(inner);
+            block_current_and_run_next_task();
+        }
+    }
+
+    fn unlock(&self) -> bool {
+        let mut inner = self.inner.lock();
+        if !inner.locked {
+            return false;
+        }
+        match inner.wait_queue.pop_front() {
+            Some(waiter) => {
+                wakeup_thread(waiter);
+            }
+            None => inner.locked = false,
+        }
+        true
+    }
+}
@@ -0,0 +1,75 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::SpinLock;
+use crate::task::{
+    block_current_and_run_next_task, current_thread, signal_pending, wakeup_thread, Thread,
+};
+
+/// A counting semaphore
+pub struct Semaphore {
+    inner: SpinLock<SemaphoreInner>,
+}
+
+struct SemaphoreInner {
+    /// the resources left, or if negative, how many threads wait for one
+    count: isize,
+    wait_queue: VecDeque<Arc<Thread>>,
+}
+
+impl Semaphore {
+    pub fn new(count: usize) -> Self {
+        Self {
+            inner: SpinLock::new(
+                "Semaphore::inner",
+                SemaphoreInner {
+                    count: count as isize,
+                    wait_queue: VecDeque::new(),
+                },
+            ),
+        }
+    }
+
+    pub fn up(&self) {
+        let mut inner = self.inner.lock();
+        inner.count += 1;
+        if inner.count <= 0 {
+            if let Some(waiter) = inner.wait_queue.pop_front() {
+                wakeup_thread(waiter);
+            }
+        }
+    }
+
+    /// Take a resource, waiting for one if need be. Returns false, without
+    /// one, if a signal interrupts the wait.
+    pub fn down(&self) -> bool {
+        let mut inner = self.inner.lock();
+        inner.count -= 1;
+        if inner.count >= 0 {
+            return true;
+        }
+        let thread = current_thread();
+        // compared by address, as in `MutexBlocking::lock`
+        let id = Arc::as_ptr(&thread);
+        inner.wait_queue.push_back(thread);
+        drop(inner);
+        loop {
+            let interrupted = signal_pending();
+            let mut inner = self.inner.lock();
+            // `up` takes us off the queue when it hands us a resource
+            let Some(index) = inner
+                .wait_queue
+                .iter()
+                .position(|other| Arc::as_ptr(other) == id)
+            else {
+                return true;
+            };
+            if interrupted {
+                inner.wait_queue.remove(index);
+                inner.count += 1;
+                return false;
+            }
+            drop(inner);
+            block_current_and_run_next_task();
+        }
+    }
+}
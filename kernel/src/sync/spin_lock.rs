@@ -0,0 +1,272 @@
+//! Kernel spin locks that check the order they are taken in
+//!
+//! A [`SpinLock`] belongs to a lock class, a name shared by every lock that
+//! plays the same part, such as the inner lock of each process. In debug
+//! builds, each acquire is checked against the locks the hart holds already
+//! (the kernel runs on a single hart): taking a lock it holds already, or two
+//! classes in the opposite order to one seen before, would hang, so it panics
+//! instead, naming the places both locks were taken. How long each class is
+//! held is recorded as well, for [`lock_stats`]. Release builds only spin.
+//!
+//! Locks of one class may nest, as a parent process locks its children, and
+//! such nesting is not checked.
+
+use alloc::string::String;
+use core::ops::{Deref, DerefMut};
+#[cfg(debug_assertions)]
+use core::panic::Location;
+
+pub struct SpinLock<T> {
+    #[cfg(debug_assertions)]
+    class: &'static str,
+    inner: spin::Mutex<T>,
+}
+
+pub struct SpinLockGuard<'a, T> {
+    lock: &'a SpinLock<T>,
+    guard: spin::MutexGuard<'a, T>,
+}
+
+impl<T> SpinLock<T> {
+    pub const fn new(class: &'static str, value: T) -> Self {
+        #[cfg(not(debug_assertions))]
+        let _ = class;
+        Self {
+            #[cfg(debug_assertions)]
+            class,
+            inner: spin::Mutex::new(value),
+        }
+    }
+
+    #[track_caller]
+    pub fn lock(&self) -> SpinLockGuard<'_, T> {
+        #[cfg(debug_assertions)]
+        lockdep::acquire(self.class, self.addr(), Location::caller());
+        SpinLockGuard {
+            lock: self,
+            guard: self.inner.lock(),
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn addr(&self) -> usize {
+        self as *const Self as usize
+    }
+}
+
+impl<T> Deref for SpinLockGuard<'_, T> {
+    type Target = T;
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for SpinLockGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for SpinLockGuard<'_, T> {
+    fn drop(&mut self) {
+        #[cfg(debug_assertions)]
+        lockdep::release(self.lock.addr());
+        #[cfg(not(debug_assertions))]
+        let _ = self.lock;
+    }
+}
+
+/// Report, for each lock class, how many times it was taken and for how
+/// long it was held in total and at most.
+pub fn lock_stats() -> String {
+    #[cfg(debug_assertions)]
+    return lockdep::stats();
+    #[cfg(not(debug_assertions))]
+    String::from("lock statistics are only kept in debug builds\n")
+}
+
+#[cfg(debug_assertions)]
+mod lockdep {
+    use alloc::{collections::BTreeMap, format, string::String, vec, vec::Vec};
+    use core::{fmt::Write, panic::Location};
+
+    use crate::{config::CLOCK_FREQ, timer};
+
+    type Site = &'static Location<'static>;
+
+    /// A lock the hart holds
+    struct Held {
+        class: &'static str,
+        addr: usize,
+        site: Site,
+        since: u64,
+    }
+
+    #[derive(Default)]
+    struct Stats {
+        count: usize,
+        total: u64,
+        max: u64,
+    }
+
+    pub struct LockDep {
+        held: Vec<Held>,
+        /// `(a, b)` if `b` has been taken while `a` was held, with the
+        /// places the two were taken the first time
+        order: BTreeMap<(&'static str, &'static str), (Site, Site)>,
+        stats: BTreeMap<&'static str, Stats>,
+    }
+
+    /// The bookkeeping of all `SpinLock`s, itself a plain spin lock
+    static LOCKDEP: spin::Mutex<LockDep> = spin::Mutex::new(LockDep::new());
+
+    pub fn acquire(class: &'static str, addr: usize, site: Site) {
+        let result = LOCKDEP.lock().acquire(class, addr, site, timer::read());
+        if let Err(message) = result {
+            panic!("{}", message);
+        }
+    }
+
+    pub fn release(addr: usize) {
+        LOCKDEP.lock().release(addr, timer::read());
+    }
+
+    pub fn stats() -> String {
+        LOCKDEP.lock().stats()
+    }
+
+    impl LockDep {
+        pub const fn new() -> Self {
+            Self {
+                held: Vec::new(),
+                order: BTreeMap::new(),
+                stats: BTreeMap::new(),
+            }
+        }
+
+        /// Record that the lock at `addr` of `class` is being taken at
+        /// `site`, or describe the deadlock that would follow.
+        pub fn acquire(
+            &mut self,
+            class: &'static str,
+            addr: usize,
+            site: Site,
+            now: u64,
+        ) -> Result<(), String> {
+            if let Some(held) = self.held.iter().find(|held| held.addr == addr) {
+                return Err(format!(
+                    "recursive acquire of {} lock at {}, already taken at {}",
+                    class, site, held.site
+                ));
+            }
+            for held in self.held.iter().filter(|held| held.class != class) {
+                if let Some(path) = self.path(class, held.class) {
+                    let (first, then) = self.order[&(path[0], path[1])];
+                    return Err(format!(
+                        "lock order inversion: taking {} at {} while holding {} taken at {}, \
+                         but the order {} was seen before, with {} taken at {} before {} at {}",
+                        class,
+                        site,
+                        held.class,
+                        held.site,
+                        path.join(" -> "),
+                        path[0],
+                        first,
+                        path[1],
+                        then
+                    ));
+                }
+            }
+            for held in self.held.iter().filter(|held| held.class != class) {
+                self.order
+                    .entry((held.class, class))
+                    .or_insert((held.site, site));
+            }
+            self.held.push(Held {
+                class,
+                addr,
+                site,
+                since: now,
+            });
+            Ok(())
+        }
+
+        pub fn release(&mut self, addr: usize, now: u64) {
+            let Some(index) = self.held.iter().rposition(|held| held.addr == addr) else {
+                return;
+            };
+            let held = self.held.remove(index);
+            let stats = self.stats.entry(held.class).or_default();
+            let time = now - held.since;
+            stats.count += 1;
+            stats.total += time;
+            stats.max = stats.max.max(time);
+        }
+
+        /// Return a chain of classes each taken while the one before it was
+        /// held, from `from` to `to`, if there is one.
+        fn path(&self, from: &'static str, to: &'static str) -> Option<Vec<&'static str>> {
+            let mut stack = vec![vec![from]];
+            let mut visited = vec![from];
+            while let Some(path) = stack.pop() {
+                let last = *path.last().unwrap();
+                for &(_, next) in self.order.keys().filter(|(before, _)| *before == last) {
+                    let mut next_path = path.clone();
+                    next_path.push(next);
+                    if next == to {
+                        return Some(next_path);
+                    }
+                    if !visited.contains(&next) {
+                        visited.push(next);
+                        stack.push(next_path);
+                    }
+                }
+            }
+            None
+        }
+
+        pub fn stats(&self) -> String {
+            let us = |ticks: u64| ticks * 1_000_000 / CLOCK_FREQ;
+            let mut report = String::from("class count total(us) max(us)\n");
+            for (class, stats) in self.stats.iter() {
+                writeln!(
+                    report,
+                    "{} {} {} {}",
+                    class,
+                    stats.count,
+                    us(stats.total),
+                    us(stats.max)
+                )
+                .unwrap();
+            }
+            report
+        }
+    }
+
+    #[test_case]
+    fn lockdep_test() {
+        let (a, b, c) = (Location::caller(), Location::caller(), Location::caller());
+        let mut lockdep = LockDep::new();
+        // A -> B, then B -> C
+        lockdep.acquire("A", 1, a, 0).unwrap();
+        lockdep.acquire("B", 2, b, 1).unwrap();
+        assert!(lockdep.acquire("B", 2, b, 2).is_err());
+        lockdep.release(2, 3);
+        lockdep.release(1, 4);
+        lockdep.acquire("B", 2, b, 5).unwrap();
+        lockdep.acquire("C", 3, c, 6).unwrap();
+        lockdep.release(3, 7);
+        lockdep.release(2, 8);
+        // C -> A closes the cycle A -> B -> C -> A
+        lockdep.acquire("C", 3, c, 9).unwrap();
+        assert!(lockdep.acquire("A", 1, a, 10).is_err());
+        // locks of one class may nest
+        lockdep.acquire("C", 4, c, 11).unwrap();
+        lockdep.release(4, 12);
+        lockdep.release(3, 13);
+        assert_eq!(lockdep.stats["B"].count, 2);
+        assert_eq!(lockdep.stats["B"].max, 3);
+        assert!(lockdep.held.is_empty());
+        println!("lockdep_test passed!");
+    }
+}
@@ -2,23 +2,384 @@
 
 use log::trace;
 
-use crate::{memory::translated_byte_buffer, task::current_user_token};
+use alloc::{string::String, sync::Arc};
 
-const FD_STDOUT: usize = 1;
+use crate::{
+    fs::{
+        self, foreground_pgid, make_pipe, open_file, set_foreground_pgid, DevFs, FileSystem,
+        OpenFlags, Stat, TmpFs,
+    },
+    memory::{translated_byte_buffer, translated_refmut, MapPermission},
+    task::{current_process, process_group},
+};
 
 /// write buf of length `len`  to a file with `fd`
 pub fn sys_write(fd: usize, buf: *const u8, len: usize) -> isize {
-    match fd {
-        FD_STDOUT => {
-            trace!("sys_write: fd={}, buf={:p}, len={}", fd, buf, len);
-            let buffers = translated_byte_buffer(current_user_token(), buf, len);
-            for buffer in buffers {
-                print!("{}", core::str::from_utf8(buffer).unwrap());
+    trace!("sys_write: fd={}, buf={:p}, len={}", fd, buf, len);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(file) = inner.file(fd) else {
+        return -1;
+    };
+    if !file.writable() {
+        return -1;
+    }
+    let Some(buf) = inner.memory_set.user_buffer(buf, len, MapPermission::R) else {
+        return -1;
+    };
+    // the write may block, so it must not hold the process lock
+    drop(inner);
+    file.write(buf) as isize
+}
+
+/// read up to `len` bytes into buf from a file with `fd`
+pub fn sys_read(fd: usize, buf: *mut u8, len: usize) -> isize {
+    trace!("sys_read: fd={}, buf={:p}, len={}", fd, buf, len);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(file) = inner.file(fd) else {
+        return -1;
+    };
+    if !file.readable() {
+        return -1;
+    }
+    let Some(buf) = inner.memory_set.user_buffer(buf, len, MapPermission::W) else {
+        return -1;
+    };
+    drop(inner);
+    file.read(buf) as isize
+}
+
+/// open the file at `path`, returning its fd
+pub fn sys_open(path: *const u8, flags: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(path) = inner.user_path(path) else {
+        return -1;
+    };
+    trace!("sys_open: path={:?}, flags={:#x}", path, flags);
+    let Some(flags) = OpenFlags::from_bits(flags) else {
+        return -1;
+    };
+    let Some(inode) = open_file(path.as_str(), flags) else {
+        return -1;
+    };
+    let fd = inner.alloc_fd();
+    inner.fd_table[fd] = Some(inode);
+    fd as isize
+}
+
+/// create a pipe, storing its read end fd and write end fd in `pipe[0]` and
+/// `pipe[1]`
+pub fn sys_pipe(pipe: *mut usize) -> isize {
+    trace!("sys_pipe: pipe={:p}", pipe);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let size = 2 * core::mem::size_of::<usize>();
+    if !inner
+        .memory_set
+        .fault_in(pipe as usize, size, MapPermission::W)
+    {
+        return -1;
+    }
+    let token = inner.memory_set.satp_token();
+    let (pipe_read, pipe_write) = make_pipe();
+    let read_fd = inner.alloc_fd();
+    inner.fd_table[read_fd] = Some(pipe_read);
+    let write_fd = inner.alloc_fd();
+    inner.fd_table[write_fd] = Some(pipe_write);
+    *translated_refmut(token, pipe) = read_fd;
+    *translated_refmut(token, unsafe { pipe.add(1) }) = write_fd;
+    0
+}
+
+/// close the file at `fd`
+pub fn sys_close(fd: usize) -> isize {
+    trace!("sys_close: fd={}", fd);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    match inner.fd_table.get_mut(fd) {
+        Some(file @ Some(_)) => {
+            *file = None;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// duplicate `fd` onto the lowest free fd, returning the new fd
+pub fn sys_dup(fd: usize) -> isize {
+    trace!("sys_dup: fd={}", fd);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(file) = inner.file(fd) else {
+        return -1;
+    };
+    let new_fd = inner.alloc_fd();
+    inner.fd_table[new_fd] = Some(file);
+    new_fd as isize
+}
+
+/// change the cwd to the directory at `path`
+pub fn sys_chdir(path: *const u8) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(path) = inner.user_path(path) else {
+        return -1;
+    };
+    trace!("sys_chdir: path={:?}", path);
+    match fs::lookup(&path) {
+        Some(inode) if inode.is_dir() => {
+            inner.cwd = path;
+            0
+        }
+        _ => -1,
+    }
+}
+
+/// copy the cwd, NUL-terminated, into buf of length `len`, returning the
+/// length copied
+pub fn sys_getcwd(buf: *mut u8, len: usize) -> isize {
+    trace!("sys_getcwd: buf={:p}, len={}", buf, len);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let size = inner.cwd.len() + 1;
+    if len < size
+        || !inner
+            .memory_set
+            .fault_in(buf as usize, size, MapPermission::W)
+    {
+        return -1;
+    }
+    let buffers = translated_byte_buffer(inner.memory_set.satp_token(), buf, size);
+    let cwd = inner.cwd.as_bytes().iter().chain(core::iter::once(&0));
+    for (dst, src) in buffers.into_iter().flatten().zip(cwd) {
+        *dst = *src;
+    }
+    size as isize
+}
+
+/// mount a filesystem of type `fs_type` at the directory `target`
+///
+/// `devfs` and `tmpfs` are known, both of which ignore `source`.
+pub fn sys_mount(_source: *const u8, target: *const u8, fs_type: *const u8) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let (Some(target), Some(fs_type)) = (inner.user_path(target), inner.user_str(fs_type)) else {
+        return -1;
+    };
+    trace!("sys_mount: target={:?}, fs_type={:?}", target, fs_type);
+    let fs: Arc<dyn FileSystem> = match fs_type.as_str() {
+        "devfs" => Arc::new(DevFs),
+        "tmpfs" => Arc::new(TmpFs::new()),
+        _ => return -1,
+    };
+    if fs::mount(&target, fs) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// unmount the filesystem mounted at `target`
+pub fn sys_umount2(target: *const u8, _flags: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(target) = inner.user_path(target) else {
+        return -1;
+    };
+    trace!("sys_umount2: target={:?}", target);
+    if fs::umount(&target) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// `dirfd` for the `*at` syscalls meaning the cwd
+const AT_FDCWD: isize = -100;
+/// `unlinkat` removes a directory rather than a file
+const AT_REMOVEDIR: u32 = 0x200;
+
+/// Read the path at `path` for the `*at` syscalls and resolve it against the
+/// cwd. Relative paths are only supported with `dirfd == AT_FDCWD`.
+fn user_path_at(dirfd: isize, path: *const u8) -> Option<String> {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let path = inner.user_str(path)?;
+    if !path.starts_with('/') && dirfd != AT_FDCWD {
+        return None;
+    }
+    Some(fs::normalize(&inner.cwd, &path))
+}
+
+/// create the directory at `path`, relative to `dirfd`
+pub fn sys_mkdirat(dirfd: isize, path: *const u8, _mode: u32) -> isize {
+    let Some(path) = user_path_at(dirfd, path) else {
+        return -1;
+    };
+    trace!("sys_mkdirat: path={:?}", path);
+    if fs::mkdir(&path) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// remove the file, or with `AT_REMOVEDIR` in `flags` the empty directory, at
+/// `path`, relative to `dirfd`
+pub fn sys_unlinkat(dirfd: isize, path: *const u8, flags: u32) -> isize {
+    let Some(path) = user_path_at(dirfd, path) else {
+        return -1;
+    };
+    trace!("sys_unlinkat: path={:?}, flags={:#x}", path, flags);
+    if flags & !AT_REMOVEDIR != 0 {
+        return -1;
+    }
+    if fs::unlink(&path, flags & AT_REMOVEDIR != 0) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// make `new_path` another name for the file at `old_path`, each relative to
+/// its dirfd
+pub fn sys_linkat(
+    old_dirfd: isize,
+    old_path: *const u8,
+    new_dirfd: isize,
+    new_path: *const u8,
+    flags: u32,
+) -> isize {
+    let (Some(old_path), Some(new_path)) = (
+        user_path_at(old_dirfd, old_path),
+        user_path_at(new_dirfd, new_path),
+    ) else {
+        return -1;
+    };
+    trace!(
+        "sys_linkat: old_path={:?}, new_path={:?}",
+        old_path,
+        new_path
+    );
+    if flags != 0 {
+        return -1;
+    }
+    if fs::link(&old_path, &new_path) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// move the file or directory at `old_path` to `new_path`, each relative to
+/// its dirfd. No `flags` are supported.
+pub fn sys_renameat2(
+    old_dirfd: isize,
+    old_path: *const u8,
+    new_dirfd: isize,
+    new_path: *const u8,
+    flags: u32,
+) -> isize {
+    let (Some(old_path), Some(new_path)) = (
+        user_path_at(old_dirfd, old_path),
+        user_path_at(new_dirfd, new_path),
+    ) else {
+        return -1;
+    };
+    trace!(
+        "sys_renameat2: old_path={:?}, new_path={:?}",
+        old_path,
+        new_path
+    );
+    if flags != 0 {
+        return -1;
+    }
+    if fs::rename(&old_path, &new_path) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// fill buf of length `len` with `struct linux_dirent64` entries of the
+/// directory at `fd`, returning the length filled, or 0 at the end
+pub fn sys_getdents64(fd: usize, buf: *mut u8, len: usize) -> isize {
+    trace!("sys_getdents64: fd={}, buf={:p}, len={}", fd, buf, len);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(file) = inner.file(fd) else {
+        return -1;
+    };
+    let Some(buf) = inner.memory_set.user_buffer(buf, len, MapPermission::W) else {
+        return -1;
+    };
+    drop(inner);
+    match file.read_dir(buf) {
+        Some(len) => len as isize,
+        None => -1,
+    }
+}
+
+/// store the `struct stat` of the file at `fd` in `stat`
+pub fn sys_fstat(fd: usize, stat: *mut u8) -> isize {
+    trace!("sys_fstat: fd={}, stat={:p}", fd, stat);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let Some(file) = inner.file(fd) else {
+        return -1;
+    };
+    let size = core::mem::size_of::<Stat>();
+    if !inner
+        .memory_set
+        .fault_in(stat as usize, size, MapPermission::W)
+    {
+        return -1;
+    }
+    let buffers = translated_byte_buffer(inner.memory_set.satp_token(), stat, size);
+    let stat = file.stat();
+    for (dst, src) in buffers.into_iter().flatten().zip(stat.as_bytes()) {
+        *dst = *src;
+    }
+    0
+}
+
+/// `request` of `sys_ioctl`: store the foreground process group of the
+/// terminal in the `i32` at `arg`
+const TIOCGPGRP: usize = 0x540f;
+/// `request` of `sys_ioctl`: make the `i32` at `arg` the foreground process
+/// group of the terminal
+const TIOCSPGRP: usize = 0x5410;
+
+/// control the device at `fd`. Only the terminal's `TIOCGPGRP` and
+/// `TIOCSPGRP` are supported.
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    trace!("sys_ioctl: fd={}, request={:#x}", fd, request);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    if !inner.file(fd).is_some_and(|file| file.is_tty()) {
+        return -1;
+    }
+    match request {
+        TIOCGPGRP => {
+            if inner.write_user(arg as *mut i32, &(foreground_pgid() as i32)) {
+                0
+            } else {
+                -1
             }
-            len as isize
         }
-        _ => {
-            panic!("Unsupported fd in sys_write!");
+        TIOCSPGRP => {
+            let Some(pgid) = inner.read_user(arg as *const i32) else {
+                return -1;
+            };
+            drop(inner);
+            if pgid <= 0 || process_group(pgid as usize).is_empty() {
+                return -1;
+            }
+            set_foreground_pgid(pgid as usize);
+            0
         }
+        _ => -1,
     }
 }
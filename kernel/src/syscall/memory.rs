@@ -0,0 +1,125 @@
+//! Memory management syscalls
+
+use log::{info, warn};
+
+use crate::{
+    config::USER_SPACE_END,
+    memory::{address::PAGE_SIZE, MapPermission},
+    task::current_process,
+};
+
+const PROT_READ: usize = 1 << 0;
+const PROT_WRITE: usize = 1 << 1;
+const PROT_EXEC: usize = 1 << 2;
+
+/// check that `[start, start + len)` is a page-aligned, non-empty user range
+fn user_range_valid(start: usize, len: usize) -> bool {
+    start.is_multiple_of(PAGE_SIZE)
+        && len > 0
+        && start
+            .checked_add(len)
+            .is_some_and(|end| end <= USER_SPACE_END)
+}
+
+/// Map `len` bytes (rounded up to pages) of zeroed anonymous memory at
+/// `start` with the `PROT_*` bits in `prot`. Returns 0 on success and -1 if
+/// the arguments are invalid or the range overlaps an existing mapping.
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    if !user_range_valid(start, len)
+        || prot & !(PROT_READ | PROT_WRITE | PROT_EXEC) != 0
+        || prot == 0
+    {
+        warn!(
+            "sys_mmap: invalid arguments {:#x} {:#x} {:#x}",
+            start, len, prot
+        );
+        return -1;
+    }
+    let mut map_perm = MapPermission::U;
+    if prot & PROT_READ != 0 {
+        map_perm |= MapPermission::R;
+    }
+    if prot & PROT_WRITE != 0 {
+        // the MMU does not allow writable pages that are not readable
+        map_perm |= MapPermission::R | MapPermission::W;
+    }
+    if prot & PROT_EXEC != 0 {
+        map_perm |= MapPermission::X;
+    }
+    let process = current_process();
+    let result = process.lock_inner().memory_set.insert_framed_area(
+        start.into(),
+        (start + len).into(),
+        map_perm,
+    );
+    match result {
+        Ok(()) => {
+            info!("sys_mmap: [{:#x}, {:#x})", start, start + len);
+            0
+        }
+        Err(err) => {
+            warn!("sys_mmap: {}", err);
+            -1
+        }
+    }
+}
+
+/// Unmap `len` bytes (rounded up to pages) at `start`, which may be part of
+/// one or more earlier mappings. Returns -1 if any page in the range is not
+/// mapped.
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    if !user_range_valid(start, len) {
+        warn!("sys_munmap: invalid arguments {:#x} {:#x}", start, len);
+        return -1;
+    }
+    let process = current_process();
+    let result = process
+        .lock_inner()
+        .memory_set
+        .remove_area(start.into(), (start + len).into());
+    match result {
+        Ok(()) => {
+            info!("sys_munmap: [{:#x}, {:#x})", start, start + len);
+            0
+        }
+        Err(err) => {
+            warn!("sys_munmap: {}", err);
+            -1
+        }
+    }
+}
+
+/// Move the program break by `increment` bytes, mapping or unmapping heap
+/// pages as needed. Returns the old break, or -1 if the heap would shrink
+/// below its bottom or run into another mapping.
+pub fn sys_sbrk(increment: i32) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let heap_bottom = inner.heap_bottom;
+    let old_brk = inner.program_brk;
+    let Some(new_brk) = old_brk
+        .checked_add_signed(increment as isize)
+        .filter(|&brk| brk >= heap_bottom && brk <= USER_SPACE_END)
+    else {
+        return -1;
+    };
+    let result = if increment < 0 {
+        inner
+            .memory_set
+            .shrink_to(heap_bottom.into(), new_brk.into())
+    } else {
+        inner
+            .memory_set
+            .append_to(heap_bottom.into(), new_brk.into())
+    };
+    match result {
+        Ok(()) => {
+            inner.program_brk = new_brk;
+            old_brk as isize
+        }
+        Err(err) => {
+            warn!("sys_sbrk: {}", err);
+            -1
+        }
+    }
+}
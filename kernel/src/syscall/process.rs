@@ -1,28 +1,43 @@
-use log::{info, warn};
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::mem::size_of;
+use log::{info, log, warn, Level};
 
 use crate::{
-    memory::translated_str,
+    config::USER_STACK_SIZE,
+    fs::{open_file, OpenFlags},
+    memory::{
+        cow_stats, translated_ref, translated_refmut, translated_str, MapPermission, MemorySet,
+    },
     task::{
-        add_process, current_process, current_user_token, exit_current_and_run_next_task,
-        suspend_current_and_run_next_task, APP_MANAGER,
+        add_process, add_thread, args_size, charge_kernel_time, current_process, current_thread,
+        exit_current_and_run_next_task, pid2process, process_group,
+        suspend_current_and_run_next_task, MIN_PRIORITY,
     },
+    timer::ticks_to_us,
 };
 
-/// task exits and submit an exit code
+/// the current thread exits and submits an exit code, which ends the process
+/// if it is the main thread or the last one
 pub fn sys_exit(exit_code: i32) -> ! {
-    match exit_code {
-        0 => info!(
-            "Process {:?} exited with code {}",
-            current_process().pid,
-            exit_code
-        ),
-        _ => warn!(
-            "Process {:?} exited with code {}",
-            current_process().pid,
-            exit_code
-        ),
-    }
-    exit_current_and_run_next_task();
+    charge_kernel_time();
+    let usage = current_process().cpu_times.usage();
+    let level = match exit_code {
+        0 => Level::Info,
+        _ => Level::Warn,
+    };
+    log!(
+        level,
+        "Thread {} of process {:?} exited with code {}, the process has used {}us in user \
+         space and {}us in the kernel, with {} voluntary and {} involuntary switches",
+        current_thread().tid,
+        current_process().pid,
+        exit_code,
+        ticks_to_us(usage.utime),
+        ticks_to_us(usage.stime),
+        usage.nvcsw,
+        usage.nivcsw
+    );
+    exit_current_and_run_next_task(exit_code);
     unreachable!("Unreachable in sys_exit");
 }
 
@@ -32,18 +47,278 @@ pub fn sys_yield() -> isize {
     0
 }
 
+/// set the priority of the current process, which gets CPU time in
+/// proportion to it, returning it, or -1 if it is below `MIN_PRIORITY`
+pub fn sys_set_priority(prio: isize) -> isize {
+    if prio < MIN_PRIORITY as isize {
+        return -1;
+    }
+    current_process().sched.set_priority(prio as usize);
+    prio
+}
+
+pub fn sys_getpid() -> isize {
+    current_process().pid.0 as isize
+}
+
+/// Move the process `pid`, which is the caller or one of its children, into
+/// the process group `pgid`. A `pid` of 0 stands for the caller, and a
+/// `pgid` of 0 for `pid` itself, starting a new group.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    let process = current_process();
+    let target = if pid == 0 || pid == process.pid.0 {
+        process
+    } else {
+        let child = process
+            .lock_inner()
+            .children
+            .iter()
+            .find(|child| child.pid.0 == pid)
+            .cloned();
+        match child {
+            Some(child) => child,
+            None => return -1,
+        }
+    };
+    let pgid = if pgid == 0 { target.pid.0 } else { pgid };
+    if pgid != target.pid.0 && process_group(pgid).is_empty() {
+        return -1;
+    }
+    target.lock_inner().pgid = pgid;
+    0
+}
+
+/// return the process group of the process `pid`, 0 standing for the caller
+pub fn sys_getpgid(pid: usize) -> isize {
+    let process = if pid == 0 {
+        Some(current_process())
+    } else {
+        pid2process(pid)
+    };
+    match process {
+        Some(process) => process.lock_inner().pgid as isize,
+        None => -1,
+    }
+}
+
+/// Create a child process with a copy of the address space, in which only the
+/// calling thread runs. Returns -1 if memory runs out.
 pub fn sys_fork() -> isize {
-    let new_process = current_process().fork();
-    new_process.lock_inner().trap_cx().x[10] = 0;
+    let new_process = match current_process().fork(&current_thread()) {
+        Ok(process) => process,
+        Err(err) => {
+            warn!("Fork: {}", err);
+            return -1;
+        }
+    };
+    let new_thread = new_process
+        .lock_inner()
+        .thread(current_thread().tid)
+        .unwrap();
+    new_thread.lock_inner().trap_cx().x[10] = 0;
     add_process(new_process.clone());
-    info!("Fork: {} -> {}", current_process().pid.0, new_process.pid.0);
+    let (shared, copied) = cow_stats();
+    info!(
+        "Fork: {} -> {}, cow pages shared {}, copied {}",
+        current_process().pid.0,
+        new_process.pid.0,
+        shared,
+        copied
+    );
     new_process.pid.0 as isize
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
-    let path = translated_str(current_user_token(), path);
+/// Replace the current program with the ELF file at `path`, which is
+/// relative to the cwd unless absolute.
+///
+/// `argv` and `envp` are NULL-terminated arrays of C strings and may
+/// themselves be NULL. Returns -1 if no app matches, it is not a valid
+/// ELF, the arguments take more than a quarter of the user stack, or other
+/// threads are still running, and otherwise `argc`, which becomes `a0` of
+/// the new program.
+pub fn sys_exec(path: *const u8, argv: *const usize, envp: *const usize) -> isize {
+    let thread = current_thread();
     let process = current_process();
-    process.exec(APP_MANAGER.lock().load_app(path.parse().unwrap()));
+    let mut inner = process.lock_inner();
+    if inner
+        .threads
+        .iter()
+        .flatten()
+        .any(|other| !Arc::ptr_eq(other, &thread) && other.lock_inner().exit_code.is_none())
+    {
+        return -1;
+    }
+    let Some(path) = inner.user_path(path) else {
+        return -1;
+    };
+    let (Some(args), Some(envs)) = (
+        translated_str_array(&mut inner.memory_set, argv),
+        translated_str_array(&mut inner.memory_set, envp),
+    ) else {
+        return -1;
+    };
+    drop(inner);
+    // like Linux, the arguments may take up to a quarter of the stack
+    if args_size(&args, &envs) > USER_STACK_SIZE / 4 {
+        warn!("Exec: arguments too long");
+        return -1;
+    }
+    let Some(app_inode) =
+        open_file(path.as_str(), OpenFlags::RDONLY).filter(|inode| !inode.is_dir())
+    else {
+        warn!("Exec: app {:?} not found", path);
+        return -1;
+    };
+    let elf_data = app_inode.read_all();
+    let argc = args.len();
+    if let Err(err) = process.exec(&thread, &elf_data, args, envs) {
+        warn!("Exec: {:?}: {}", path, err);
+        return -1;
+    }
     info!("Exec: {:?}", path);
-    0
+    argc as isize
+}
+
+/// collect a NULL-terminated array of C strings from user space
+fn translated_str_array(memory_set: &mut MemorySet, mut ptr: *const usize) -> Option<Vec<String>> {
+    let token = memory_set.satp_token();
+    let mut strs = Vec::new();
+    if ptr.is_null() {
+        return Some(strs);
+    }
+    loop {
+        if !memory_set.fault_in(ptr as usize, size_of::<usize>(), MapPermission::R) {
+            return None;
+        }
+        let str_ptr = *translated_ref(token, ptr);
+        if str_ptr == 0 {
+            break;
+        }
+        if !memory_set.fault_in_str(str_ptr) {
+            return None;
+        }
+        strs.push(translated_str(token, str_ptr as *const u8));
+        unsafe {
+            ptr = ptr.add(1);
+        }
+    }
+    Some(strs)
+}
+
+/// `options` of `sys_waitpid`: also report children that have stopped
+const WUNTRACED: u32 = 2;
+
+/// Wait for the child `pid` (or any child if `pid` is -1) to exit, or with
+/// `WUNTRACED` in `options` also to stop.
+///
+/// Returns -1 if there is no such child, -2 if it is still running, and
+/// otherwise the pid of the child. The exit code of a reaped child is written
+/// to `exit_code_ptr`, while a stop is written as Linux encodes it,
+/// `0x7f | signum << 8`, and reported only once.
+pub fn sys_waitpid(pid: isize, exit_code_ptr: *mut i32, options: u32) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    if options & !WUNTRACED != 0
+        || !inner
+            .children
+            .iter()
+            .any(|child| pid == -1 || pid as usize == child.pid.0)
+    {
+        return -1;
+    }
+    let untraced = options & WUNTRACED != 0;
+    let found = inner.children.iter().position(|child| {
+        let child_inner = child.lock_inner();
+        (child_inner.is_zombie() || untraced && child_inner.unreported_stop.is_some())
+            && (pid == -1 || pid as usize == child.pid.0)
+    });
+    match found {
+        Some(idx) => {
+            if !inner.memory_set.fault_in(
+                exit_code_ptr as usize,
+                size_of::<i32>(),
+                MapPermission::W,
+            ) {
+                return -1;
+            }
+            let child_pid = inner.children[idx].pid.0;
+            let stop = {
+                let mut child_inner = inner.children[idx].lock_inner();
+                if child_inner.is_zombie() {
+                    None
+                } else {
+                    child_inner.unreported_stop.take()
+                }
+            };
+            if let Some(signum) = stop {
+                let token = inner.memory_set.satp_token();
+                *translated_refmut(token, exit_code_ptr) = 0x7f | (signum as i32) << 8;
+                return child_pid as isize;
+            }
+            let child = inner.children.remove(idx);
+            // the zombie is no longer in the ready queue or on the processor,
+            // so dropping it here releases its pid and the kernel stacks of
+            // its threads
+            assert_eq!(Arc::strong_count(&child), 1);
+            let found_pid = child.pid.0;
+            let exit_code = child.lock_inner().exit_code;
+            process.cpu_times.add_child(&child.cpu_times);
+            *translated_refmut(inner.memory_set.satp_token(), exit_code_ptr) = exit_code;
+            info!("Waitpid: {} reaped {}", process.pid.0, found_pid);
+            found_pid as isize
+        }
+        None => -2,
+    }
+}
+
+/// Create a thread in the current process that runs from `entry` with `arg`
+/// in `a0`, returning its tid, or -1 if its stack cannot be mapped. The
+/// thread must end with `sys_exit`.
+pub fn sys_thread_create(entry: usize, arg: usize) -> isize {
+    let thread = match current_process().create_thread(entry, arg) {
+        Ok(thread) => thread,
+        Err(err) => {
+            warn!("Thread create: {}", err);
+            return -1;
+        }
+    };
+    let tid = thread.tid;
+    add_thread(thread);
+    info!("Thread create: {} in {}", tid, current_process().pid.0);
+    tid as isize
+}
+
+pub fn sys_gettid() -> isize {
+    current_thread().tid as isize
+}
+
+/// Wait for the thread `tid` of the current process to exit.
+///
+/// Returns -1 if there is no such thread, or it is the caller or the main
+/// thread, whose exit ends the process; -2 if it is still running; and
+/// otherwise `tid`, writing its exit code to `exit_code_ptr`.
+pub fn sys_waittid(tid: usize, exit_code_ptr: *mut i32) -> isize {
+    let thread = current_thread();
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    if tid == thread.tid || tid == 0 {
+        return -1;
+    }
+    let Some(waited) = inner.thread(tid) else {
+        return -1;
+    };
+    let Some(exit_code) = waited.lock_inner().exit_code else {
+        return -2;
+    };
+    if !inner
+        .memory_set
+        .fault_in(exit_code_ptr as usize, size_of::<i32>(), MapPermission::W)
+    {
+        return -1;
+    }
+    *translated_refmut(inner.memory_set.satp_token(), exit_code_ptr) = exit_code;
+    // dropping the thread here releases its kernel stack
+    inner.threads[tid] = None;
+    inner.tid_allocator.dealloc(tid);
+    tid as isize
 }
@@ -0,0 +1,122 @@
+//! Signal-related syscalls
+
+use log::trace;
+
+use crate::task::{
+    current_process, current_thread, pid2process, process_group, sanitize_mask, send_signal,
+    SignalAction, SignalFlags,
+};
+
+/// `how` of `sys_sigprocmask`: add `set` to the mask
+const SIG_BLOCK: usize = 0;
+/// `how` of `sys_sigprocmask`: remove `set` from the mask
+const SIG_UNBLOCK: usize = 1;
+/// `how` of `sys_sigprocmask`: replace the mask with `set`
+const SIG_SETMASK: usize = 2;
+
+/// send signal `signum` to the process `pid`, or with `signum` 0 only check
+/// that it exists
+///
+/// A `pid` of 0 stands for the process group of the caller, and `-pgid` for
+/// the process group `pgid`.
+pub fn sys_kill(pid: isize, signum: usize) -> isize {
+    trace!("sys_kill: pid={}, signum={}", pid, signum);
+    let processes = match pid {
+        0 => {
+            let pgid = current_process().lock_inner().pgid;
+            process_group(pgid)
+        }
+        pid if pid > 0 => pid2process(pid as usize).into_iter().collect(),
+        pid if pid < -1 => process_group(pid.unsigned_abs()),
+        _ => return -1,
+    };
+    if processes.is_empty() {
+        return -1;
+    }
+    if signum == 0 {
+        return 0;
+    }
+    let Some(signal) = SignalFlags::from_signum(signum) else {
+        return -1;
+    };
+    for process in processes {
+        send_signal(&process, signal);
+    }
+    0
+}
+
+/// set how signal `signum` is handled to `action` unless it is NULL, storing
+/// the previous action in `old_action` unless that is NULL
+pub fn sys_sigaction(
+    signum: usize,
+    action: *const SignalAction,
+    old_action: *mut SignalAction,
+) -> isize {
+    trace!("sys_sigaction: signum={}", signum);
+    if SignalFlags::from_signum(signum).is_none() {
+        return -1;
+    }
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let new_action = if action.is_null() {
+        None
+    } else {
+        match inner.read_user(action) {
+            Some(action) if action.valid_for(signum) => Some(action),
+            _ => return -1,
+        }
+    };
+    let old = inner.signal_actions[signum];
+    if !old_action.is_null() && !inner.write_user(old_action, &old) {
+        return -1;
+    }
+    if let Some(mut action) = new_action {
+        action.mask = sanitize_mask(action.mask);
+        inner.signal_actions[signum] = action;
+    }
+    0
+}
+
+/// change the signal mask by `set` as `how` says unless `set` is NULL,
+/// storing the previous mask in `old_set` unless that is NULL
+pub fn sys_sigprocmask(how: usize, set: *const SignalFlags, old_set: *mut SignalFlags) -> isize {
+    trace!("sys_sigprocmask: how={}", how);
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let old = inner.signal_mask;
+    if !old_set.is_null() && !inner.write_user(old_set, &old) {
+        return -1;
+    }
+    if set.is_null() {
+        return 0;
+    }
+    let Some(set) = inner.read_user(set) else {
+        return -1;
+    };
+    let mask = match how {
+        SIG_BLOCK => old | set,
+        SIG_UNBLOCK => old - set,
+        SIG_SETMASK => set,
+        _ => return -1,
+    };
+    inner.signal_mask = sanitize_mask(mask);
+    0
+}
+
+/// return from a signal handler to where the thread was interrupted
+///
+/// This returns the interrupted `a0`, so that it is restored too.
+pub fn sys_sigreturn() -> isize {
+    trace!("sys_sigreturn");
+    let thread = current_thread();
+    let mut inner = thread.lock_inner();
+    let Some(backup) = inner.trap_cx_backup.take() else {
+        return -1;
+    };
+    inner.handling_sig = None;
+    // the kernel stack and the like stay those of this thread
+    let trap_cx = inner.trap_cx();
+    trap_cx.x = backup.x;
+    trap_cx.sepc = backup.sepc;
+    trap_cx.x[10] as isize
+}
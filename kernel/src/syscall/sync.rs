@@ -0,0 +1,204 @@
+//! Syscalls on the mutexes, semaphores and condition variables of a process,
+//! and on futexes
+//!
+//! Each of the former returns -1 if the id given is not one the process has
+//! created.
+
+use alloc::sync::Arc;
+
+use crate::{
+    memory::{
+        address::{PhysAddr, VirtAddr},
+        translated_ref, MapPermission,
+    },
+    sync::{futex_wait, futex_wake, Condvar, Mutex, MutexBlocking, MutexSpin, Semaphore},
+    task::current_process,
+    timer::{self, TimeSpec},
+};
+
+/// `op` of `sys_futex`: wait while the word holds `val`
+const FUTEX_WAIT: usize = 0;
+/// `op` of `sys_futex`: wake up at most `val` waiters
+const FUTEX_WAKE: usize = 1;
+/// may be or-ed into `op`, and is ignored, as every futex is keyed the same
+const FUTEX_PRIVATE_FLAG: usize = 128;
+
+/// create a mutex, which blocks its waiters if `blocking` and otherwise has
+/// them yield, returning its id
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    let mutex: Arc<dyn Mutex> = if blocking {
+        Arc::new(MutexBlocking::new())
+    } else {
+        Arc::new(MutexSpin::new())
+    };
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    inner.mutex_list.push(mutex);
+    (inner.mutex_list.len() - 1) as isize
+}
+
+/// lock the mutex `mutex_id`, failing if a signal interrupts the wait
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    let Some(mutex) = current_process()
+        .lock_inner()
+        .mutex_list
+        .get(mutex_id)
+        .cloned()
+    else {
+        return -1;
+    };
+    if mutex.lock() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// unlock the mutex `mutex_id`, failing if it is not locked
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    let Some(mutex) = current_process()
+        .lock_inner()
+        .mutex_list
+        .get(mutex_id)
+        .cloned()
+    else {
+        return -1;
+    };
+    if mutex.unlock() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// create a semaphore with `count` resources, returning its id
+pub fn sys_semaphore_create(count: usize) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    inner.semaphore_list.push(Arc::new(Semaphore::new(count)));
+    (inner.semaphore_list.len() - 1) as isize
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    let Some(sem) = current_process()
+        .lock_inner()
+        .semaphore_list
+        .get(sem_id)
+        .cloned()
+    else {
+        return -1;
+    };
+    sem.up();
+    0
+}
+
+/// take a resource of the semaphore `sem_id`, failing if a signal interrupts
+/// the wait
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    let Some(sem) = current_process()
+        .lock_inner()
+        .semaphore_list
+        .get(sem_id)
+        .cloned()
+    else {
+        return -1;
+    };
+    if sem.down() {
+        0
+    } else {
+        -1
+    }
+}
+
+/// create a condition variable, returning its id
+pub fn sys_condvar_create() -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    inner.condvar_list.push(Arc::new(Condvar::new()));
+    (inner.condvar_list.len() - 1) as isize
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    let Some(condvar) = current_process()
+        .lock_inner()
+        .condvar_list
+        .get(condvar_id)
+        .cloned()
+    else {
+        return -1;
+    };
+    condvar.signal();
+    0
+}
+
+/// wait on the condition variable `condvar_id` with the mutex `mutex_id`
+/// held, which is released while waiting; fails if it is not locked or a
+/// signal interrupts the wait
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    let process = current_process();
+    let inner = process.lock_inner();
+    let (Some(condvar), Some(mutex)) = (
+        inner.condvar_list.get(condvar_id).cloned(),
+        inner.mutex_list.get(mutex_id).cloned(),
+    ) else {
+        return -1;
+    };
+    drop(inner);
+    drop(process);
+    if condvar.wait(mutex) {
+        0
+    } else {
+        -1
+    }
+}
+
+/// Wait on or wake up the futex at `uaddr`, an aligned `u32`, as `op` says.
+///
+/// `FUTEX_WAIT` blocks only if the word still holds `val`, returning -1 at
+/// once otherwise, until a `FUTEX_WAKE` on the same word, returning 0, or
+/// until the `TimeSpec` at `timeout` has passed, if it is not NULL, or a
+/// signal interrupts the wait, returning -1. `FUTEX_WAKE` wakes up at most
+/// `val` waiters and returns how many it woke. Bad arguments give -1.
+pub fn sys_futex(uaddr: *const u32, op: usize, val: usize, timeout: *const TimeSpec) -> isize {
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    if uaddr as usize % 4 != 0
+        || !inner
+            .memory_set
+            .fault_in(uaddr as usize, 4, MapPermission::W)
+    {
+        return -1;
+    }
+    // the write fault has broken any copy-on-write sharing, so the word is
+    // on a frame of this process's own
+    let va = VirtAddr::from(uaddr as usize);
+    let frame = inner.memory_set.frame(va.page_number_floor()).unwrap();
+    let pa = usize::from(PhysAddr::from(frame.ppn)) + va.page_offset();
+    match op & !FUTEX_PRIVATE_FLAG {
+        FUTEX_WAIT => {
+            let deadline = if timeout.is_null() {
+                None
+            } else {
+                match inner.read_user(timeout).and_then(|timeout| timeout.ticks()) {
+                    Some(ticks) => Some(timer::read().saturating_add(ticks)),
+                    None => return -1,
+                }
+            };
+            if *translated_ref(inner.memory_set.satp_token(), uaddr) != val as u32 {
+                return -1;
+            }
+            drop(inner);
+            drop(process);
+            if futex_wait(pa, frame, deadline) {
+                0
+            } else {
+                -1
+            }
+        }
+        FUTEX_WAKE => {
+            drop(inner);
+            futex_wake(pa, val) as isize
+        }
+        _ => -1,
+    }
+}
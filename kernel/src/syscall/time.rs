@@ -1,5 +1,126 @@
-use crate::timer;
+use crate::{
+    task::{
+        block_current_and_run_next_task, current_process, current_thread, signal_pending, Usage,
+    },
+    timer::{self, add_timer, TimeSpec, TimeVal},
+};
 
 pub fn sys_get_time() -> isize {
     timer::read() as isize
 }
+
+/// block the current thread for the span at `req`, off the ready queue,
+/// returning 0, or -1 if `req` is not readable or not a valid span, or if a
+/// signal cuts the sleep short, in which case the time left is written to
+/// `rem` unless it is null
+pub fn sys_nanosleep(req: *const TimeSpec, rem: *mut TimeSpec) -> isize {
+    let ticks = current_process()
+        .lock_inner()
+        .read_user(req)
+        .and_then(|req| req.ticks());
+    let Some(ticks) = ticks else {
+        return -1;
+    };
+    let deadline = timer::read().saturating_add(ticks);
+    loop {
+        add_timer(deadline, current_thread());
+        block_current_and_run_next_task();
+        let now = timer::read();
+        if now >= deadline {
+            return 0;
+        }
+        // woken up early by a signal, which may not interrupt it, though
+        if signal_pending() {
+            if !rem.is_null() {
+                let left = TimeSpec::from_ticks(deadline - now);
+                current_process().lock_inner().write_user(rem, &left);
+            }
+            return -1;
+        }
+    }
+}
+
+/// CPU times in timer ticks, like Linux's `struct tms`
+#[repr(C)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    /// of the children reaped so far, with theirs
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+/// write the CPU times of the current process to `tms`, returning the
+/// current time, or -1 if `tms` is not writable
+pub fn sys_times(tms: *mut Tms) -> isize {
+    let process = current_process();
+    let own = process.cpu_times.usage();
+    let children = process.cpu_times.children_usage();
+    let times = Tms {
+        utime: own.utime as usize,
+        stime: own.stime as usize,
+        cutime: children.utime as usize,
+        cstime: children.stime as usize,
+    };
+    if !process.lock_inner().write_user(tms, &times) {
+        return -1;
+    }
+    timer::read() as isize
+}
+
+/// `who` of `sys_getrusage`: the current process
+const RUSAGE_SELF: isize = 0;
+/// `who` of `sys_getrusage`: its children reaped so far, with theirs
+const RUSAGE_CHILDREN: isize = -1;
+
+/// Linux's `struct rusage`, of which only the times and the context switch
+/// counts are kept; the rest stay zero
+#[repr(C)]
+#[derive(Default)]
+pub struct RUsage {
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    pub maxrss: usize,
+    pub ixrss: usize,
+    pub idrss: usize,
+    pub isrss: usize,
+    pub minflt: usize,
+    pub majflt: usize,
+    pub nswap: usize,
+    pub inblock: usize,
+    pub oublock: usize,
+    pub msgsnd: usize,
+    pub msgrcv: usize,
+    pub nsignals: usize,
+    /// voluntary context switches
+    pub nvcsw: usize,
+    /// involuntary context switches
+    pub nivcsw: usize,
+}
+
+/// write the resource usage of `who`, `RUSAGE_SELF` or `RUSAGE_CHILDREN`, to
+/// `usage`, returning 0, or -1 if `who` is neither or `usage` is not writable
+pub fn sys_getrusage(who: isize, usage: *mut RUsage) -> isize {
+    let process = current_process();
+    let Usage {
+        utime,
+        stime,
+        nvcsw,
+        nivcsw,
+    } = match who {
+        RUSAGE_SELF => process.cpu_times.usage(),
+        RUSAGE_CHILDREN => process.cpu_times.children_usage(),
+        _ => return -1,
+    };
+    let rusage = RUsage {
+        utime: TimeVal::from_ticks(utime),
+        stime: TimeVal::from_ticks(stime),
+        nvcsw: nvcsw as usize,
+        nivcsw: nivcsw as usize,
+        ..Default::default()
+    };
+    if !process.lock_inner().write_user(usage, &rusage) {
+        return -1;
+    }
+    0
+}
@@ -0,0 +1,122 @@
+//! CPU time accounting
+//!
+//! Each thread remembers when its time was last charged. On a trap from user
+//! space, the time since then is charged to its process as user time; on the
+//! return to user space, and when the thread is switched out, as kernel time.
+//! The time it spends waiting to run is not charged, as it is switched in.
+
+use core::sync::atomic::{AtomicU64, Ordering};
+
+use super::{processor::current_thread, thread::Thread};
+use crate::timer;
+
+/// CPU time in timer ticks, and how often the CPU was given up, voluntarily
+/// by yielding or blocking, or involuntarily at the end of a time slice
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Usage {
+    pub utime: u64,
+    pub stime: u64,
+    pub nvcsw: u64,
+    pub nivcsw: u64,
+}
+
+#[derive(Default)]
+struct Counters {
+    utime: AtomicU64,
+    stime: AtomicU64,
+    nvcsw: AtomicU64,
+    nivcsw: AtomicU64,
+}
+
+impl Counters {
+    fn load(&self) -> Usage {
+        Usage {
+            utime: self.utime.load(Ordering::Relaxed),
+            stime: self.stime.load(Ordering::Relaxed),
+            nvcsw: self.nvcsw.load(Ordering::Relaxed),
+            nivcsw: self.nivcsw.load(Ordering::Relaxed),
+        }
+    }
+
+    fn add(&self, usage: Usage) {
+        self.utime.fetch_add(usage.utime, Ordering::Relaxed);
+        self.stime.fetch_add(usage.stime, Ordering::Relaxed);
+        self.nvcsw.fetch_add(usage.nvcsw, Ordering::Relaxed);
+        self.nivcsw.fetch_add(usage.nivcsw, Ordering::Relaxed);
+    }
+}
+
+/// What the threads of a process have used, and the children it has reaped
+///
+/// Like `SchedInfo`, this is not in `ProcessInner`, so that it can be
+/// updated on every trap and switch without locking the process.
+#[derive(Default)]
+pub struct CpuTimes {
+    own: Counters,
+    /// the children reaped so far, with what they had reaped
+    children: Counters,
+}
+
+impl CpuTimes {
+    pub fn usage(&self) -> Usage {
+        self.own.load()
+    }
+
+    pub fn children_usage(&self) -> Usage {
+        self.children.load()
+    }
+
+    /// Add what the reaped `child` and its own children used.
+    pub fn add_child(&self, child: &CpuTimes) {
+        self.children.add(child.usage());
+        self.children.add(child.children_usage());
+    }
+
+    fn count_switch(&self, voluntary: bool) {
+        let counter = if voluntary {
+            &self.own.nvcsw
+        } else {
+            &self.own.nivcsw
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// Charge the time since `thread` was last charged to its process, as user
+/// time if `user`, else as kernel time.
+pub fn charge(thread: &Thread, user: bool) {
+    let now = timer::read();
+    let mut thread_inner = thread.lock_inner();
+    let ticks = now - thread_inner.charged_at;
+    thread_inner.charged_at = now;
+    drop(thread_inner);
+    let Some(process) = thread.process.upgrade() else {
+        return;
+    };
+    let counter = if user {
+        &process.cpu_times.own.utime
+    } else {
+        &process.cpu_times.own.stime
+    };
+    counter.fetch_add(ticks, Ordering::Relaxed);
+}
+
+/// Charge the time `thread` has been running in the kernel, as it is
+/// switched out, voluntarily or not.
+pub fn charge_switch(thread: &Thread, voluntary: bool) {
+    charge(thread, false);
+    if let Some(process) = thread.process.upgrade() {
+        process.cpu_times.count_switch(voluntary);
+    }
+}
+
+/// Charge the current thread's time in user space, on a trap from it.
+pub fn charge_user_time() {
+    charge(&current_thread(), true);
+}
+
+/// Charge the current thread's time in the kernel, as it returns to user
+/// space or exits.
+pub fn charge_kernel_time() {
+    charge(&current_thread(), false);
+}
@@ -0,0 +1,73 @@
+use alloc::vec::Vec;
+use lazy_static::lazy_static;
+
+use crate::sync::SpinLock;
+
+lazy_static! {
+    static ref PID_ALLOCATOR: SpinLock<IdAllocator> =
+        SpinLock::new("PID_ALLOCATOR", IdAllocator::new(1));
+}
+
+#[derive(Debug)]
+pub struct Pid(pub usize);
+
+impl Pid {
+    pub fn new() -> Self {
+        Self(PID_ALLOCATOR.lock().alloc())
+    }
+}
+
+impl Drop for Pid {
+    fn drop(&mut self) {
+        PID_ALLOCATOR.lock().dealloc(self.0);
+    }
+}
+
+/// Hands out the smallest ids not in use, starting from `first`
+pub struct IdAllocator {
+    first: usize,
+    /// every id below this has been handed out at some point
+    next: usize,
+    recycle: Vec<usize>,
+}
+
+impl IdAllocator {
+    pub fn new(first: usize) -> Self {
+        Self {
+            first,
+            next: first,
+            recycle: Vec::new(),
+        }
+    }
+
+    pub fn alloc(&mut self) -> usize {
+        if let Some(id) = self.recycle.pop() {
+            id
+        } else {
+            self.next += 1;
+            self.next - 1
+        }
+    }
+
+    pub fn dealloc(&mut self, id: usize) {
+        assert!(id >= self.first && id < self.next, "invalid id {}", id);
+        assert!(
+            self.recycle.iter().all(|&i| i != id),
+            "id {} is already deallocated",
+            id
+        );
+        self.recycle.push(id);
+    }
+}
+
+#[test_case]
+fn id_allocator_test() {
+    let mut allocator = IdAllocator::new(3);
+    assert_eq!(allocator.alloc(), 3);
+    assert_eq!(allocator.alloc(), 4);
+    assert_eq!(allocator.alloc(), 5);
+    allocator.dealloc(4);
+    assert_eq!(allocator.alloc(), 4);
+    assert_eq!(allocator.alloc(), 6);
+    println!("id_allocator_test passed!");
+}
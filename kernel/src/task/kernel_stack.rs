@@ -0,0 +1,71 @@
+use lazy_static::lazy_static;
+use log::trace;
+
+use crate::{
+    config::{KERNEL_STACK_SIZE, TRAMPOLINE},
+    memory::{address::PAGE_SIZE, MapPermission, KERNEL_SPACE},
+    sync::SpinLock,
+};
+
+use super::id::IdAllocator;
+
+lazy_static! {
+    /// Each thread has its own kernel stack, found by its id
+    static ref KSTACK_ALLOCATOR: SpinLock<IdAllocator> =
+        SpinLock::new("KSTACK_ALLOCATOR", IdAllocator::new(1));
+}
+
+pub struct KernelStack {
+    id: usize,
+}
+
+impl KernelStack {
+    /// Map a new kernel stack, failing if frames run out.
+    pub fn new() -> Result<Self, &'static str> {
+        let id = KSTACK_ALLOCATOR.lock().alloc();
+        let (stack_left, stack_right) = kernel_stack_position(id);
+        trace!(
+            "mapping kernel stack {} [{:x}, {:x})",
+            id,
+            stack_left,
+            stack_right
+        );
+        let result = KERNEL_SPACE.lock().insert_framed_area(
+            stack_left.into(),
+            stack_right.into(),
+            MapPermission::R | MapPermission::W,
+        );
+        if let Err(err) = result {
+            KSTACK_ALLOCATOR.lock().dealloc(id);
+            return Err(err);
+        }
+        Ok(Self { id })
+    }
+
+    pub fn top(&self) -> usize {
+        kernel_stack_position(self.id).1
+    }
+}
+
+impl Drop for KernelStack {
+    fn drop(&mut self) {
+        let (stack_left, stack_right) = kernel_stack_position(self.id);
+        trace!(
+            "unmapping kernel stack {} [{:x}, {:x})",
+            self.id,
+            stack_left,
+            stack_right
+        );
+        KERNEL_SPACE
+            .lock()
+            .remove_area(stack_left.into(), stack_right.into())
+            .unwrap();
+        KSTACK_ALLOCATOR.lock().dealloc(self.id);
+    }
+}
+
+/// return the kernel stack `id` as [{0}, {1}), each with a guard page above
+fn kernel_stack_position(id: usize) -> (usize, usize) {
+    let stack_bottom = TRAMPOLINE - id * (KERNEL_STACK_SIZE + PAGE_SIZE); // stack bottom
+    (stack_bottom - KERNEL_STACK_SIZE, stack_bottom)
+}
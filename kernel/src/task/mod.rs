@@ -1,133 +1,260 @@
 mod context;
+mod cputime;
+mod id;
+mod kernel_stack;
 mod process;
 mod processor;
+mod scheduler;
+mod signal;
 mod switch;
+mod thread;
 
-use alloc::collections::VecDeque;
+use alloc::collections::BTreeMap;
 use alloc::sync::Arc;
+use alloc::vec::Vec;
 use lazy_static::*;
 use log::info;
-use spin::Mutex;
 
+use self::cputime::{charge, charge_switch};
 use self::process::Process;
 use self::processor::{schedule, PROCESSOR};
-use crate::config::MAX_APP_NUM;
+use self::scheduler::{Scheduler, SchedulerImpl};
+use self::thread::ThreadStatus;
+use crate::{
+    fs::{open_file, OpenFlags},
+    sbi::shutdown,
+    sync::{futex_purge, SpinLock},
+    timer::remove_timer,
+};
 
 pub use context::TaskContext;
-pub use processor::{current_process, current_trap_cx, current_user_token, run_processes};
-
-pub struct AppManager {
-    num_app: usize,
-    app_start: [usize; MAX_APP_NUM + 1],
-}
-
-impl AppManager {
-    pub fn print_app_info(&self) {
-        info!("num_app = {}", self.num_app);
-        for i in 0..self.num_app {
-            info!(
-                "app_{} [{:#x}, {:#x})",
-                i,
-                self.app_start[i],
-                self.app_start[i + 1]
-            );
-        }
-    }
+pub use cputime::{charge_kernel_time, charge_user_time, Usage};
+pub use process::args_size;
+pub use processor::{
+    current_process, current_thread, current_trap_cx, current_user_token, run_threads,
+};
+pub use scheduler::MIN_PRIORITY;
+pub use signal::{
+    handle_signals, sanitize_mask, send_fault_signal, send_signal, signal_pending, SignalAction,
+    SignalFlags,
+};
+pub use thread::Thread;
 
-    pub fn load_app(&self, app_id: usize) -> &'static [u8] {
-        info!("Loading app_{}", app_id);
-        assert!(app_id < self.num_app);
-
-        let app_elf = unsafe {
-            core::slice::from_raw_parts(
-                self.app_start[app_id] as *const u8,
-                self.app_start[app_id + 1] - self.app_start[app_id],
-            )
-        };
-        app_elf
-    }
+lazy_static! {
+    /// The first user process, which adopts every orphan and reaps it
+    pub static ref INITPROC: Arc<Process> = {
+        let inode = open_file("init_proc", OpenFlags::RDONLY).expect("init_proc not found");
+        Process::new(&inode.read_all())
+    };
 }
 
 lazy_static! {
-    pub static ref APP_MANAGER: Mutex<AppManager> = unsafe {
-        Mutex::new({
-            extern "C" {
-                fn _num_app();
-            }
-
-            let num_app_ptr = _num_app as *const usize;
-            let num_app = num_app_ptr.read_volatile();
-            let mut app_start: [usize; MAX_APP_NUM + 1] = [0; MAX_APP_NUM + 1];
-            let app_start_raw: &[usize] =
-                core::slice::from_raw_parts(num_app_ptr.add(1), num_app + 1);
-            app_start[..=num_app].copy_from_slice(app_start_raw);
-            AppManager { num_app, app_start }
-        })
+    /// Every process that has not exited yet, by pid
+    static ref PID2PROCESS: SpinLock<BTreeMap<usize, Arc<Process>>> = {
+        let mut pid2process = BTreeMap::new();
+        pid2process.insert(INITPROC.pid.0, INITPROC.clone());
+        SpinLock::new("PID2PROCESS", pid2process)
     };
 }
 
 lazy_static! {
-    pub static ref PROCESS_MANAGER: Mutex<ProcessManager> = {
-        let mut task_manager = ProcessManager::new();
-        let app_manager = APP_MANAGER.lock();
-        for id in 0..app_manager.num_app {
-            let elf_data = app_manager.load_app(id);
-            task_manager.add(Arc::new(Process::new(elf_data)));
-        }
-
-        Mutex::new(task_manager)
+    pub static ref THREAD_MANAGER: SpinLock<ThreadManager> = {
+        let mut thread_manager = ThreadManager::new();
+        thread_manager.add(INITPROC.lock_inner().thread(0).unwrap());
+        SpinLock::new("THREAD_MANAGER", thread_manager)
     };
 }
 
-pub struct ProcessManager {
-    tasks: VecDeque<Arc<Process>>,
+/// The runnable threads, of which the scheduler picks the next to run
+pub struct ThreadManager {
+    scheduler: SchedulerImpl,
 }
 
-impl ProcessManager {
+impl ThreadManager {
     pub fn new() -> Self {
         Self {
-            tasks: VecDeque::new(),
+            scheduler: SchedulerImpl::new(),
         }
     }
-    pub fn num(&self) -> usize {
-        self.tasks.len()
+    pub fn add(&mut self, thread: Arc<Thread>) {
+        self.scheduler.add(thread);
     }
-    pub fn add(&mut self, task: Arc<Process>) {
-        self.tasks.push_back(task);
+    pub fn fetch(&mut self) -> Option<Arc<Thread>> {
+        self.scheduler.fetch()
     }
-    pub fn fetch(&mut self) -> Option<Arc<Process>> {
-        self.tasks.pop_front()
+    pub fn remove(&mut self, thread: &Arc<Thread>) {
+        self.scheduler.remove(thread);
     }
 }
 
+/// Make the new `process` and its threads runnable.
 pub fn add_process(process: Arc<Process>) {
-    PROCESS_MANAGER.lock().add(process);
+    PID2PROCESS.lock().insert(process.pid.0, process.clone());
+    for thread in process.lock_inner().threads.iter().flatten() {
+        add_thread(thread.clone());
+    }
+}
+
+/// Make the new `thread` runnable.
+pub fn add_thread(thread: Arc<Thread>) {
+    THREAD_MANAGER.lock().add(thread);
 }
 
+/// Return the process `pid`, unless it has exited.
+pub fn pid2process(pid: usize) -> Option<Arc<Process>> {
+    PID2PROCESS.lock().get(&pid).cloned()
+}
+
+/// Return the processes in the process group `pgid` that have not exited.
+pub fn process_group(pgid: usize) -> Vec<Arc<Process>> {
+    PID2PROCESS
+        .lock()
+        .values()
+        .filter(|process| process.lock_inner().pgid == pgid)
+        .cloned()
+        .collect()
+}
+
+/// Suspend the current thread, which stays runnable, and run another.
 pub fn suspend_current_and_run_next_task() {
+    switch_out_current(true);
+}
+
+/// Suspend the current thread at the end of its time slice, which stays
+/// runnable, and run another.
+pub fn preempt_current_and_run_next_task() {
+    switch_out_current(false);
+}
+
+fn switch_out_current(voluntary: bool) {
+    let current = PROCESSOR
+        .lock()
+        .current()
+        .take()
+        .expect("no current thread");
+    charge_switch(&current, voluntary);
+    let mut current_inner = current.lock_inner();
+    let current_cx_ptr = &mut current_inner.task_cx as *mut TaskContext;
+    current_inner.status = ThreadStatus::Ready;
+    drop(current_inner);
+    THREAD_MANAGER.lock().add(current);
+    schedule(current_cx_ptr);
+}
+
+/// Block the current thread, which is left off the ready queue until
+/// [`wakeup_thread`] is called on it by whoever it was parked with.
+pub fn block_current_and_run_next_task() {
     let current = PROCESSOR
         .lock()
         .current()
         .take()
-        .expect("no current process");
+        .expect("no current thread");
+    charge_switch(&current, true);
     let mut current_inner = current.lock_inner();
     let current_cx_ptr = &mut current_inner.task_cx as *mut TaskContext;
-    current_inner.status = process::ProcessStatus::Ready;
+    current_inner.status = ThreadStatus::Blocked;
     drop(current_inner);
-    PROCESS_MANAGER.lock().add(current);
+    drop(current);
     schedule(current_cx_ptr);
 }
 
-pub fn exit_current_and_run_next_task() {
+/// Make the blocked `thread` runnable again, returning false if it is not
+/// blocked, as its process has exited.
+pub fn wakeup_thread(thread: Arc<Thread>) -> bool {
+    let mut thread_inner = thread.lock_inner();
+    if thread_inner.status != ThreadStatus::Blocked {
+        return false;
+    }
+    thread_inner.status = ThreadStatus::Ready;
+    drop(thread_inner);
+    THREAD_MANAGER.lock().add(thread);
+    true
+}
+
+/// Exit the current thread with `exit_code`, and if it is the main thread or
+/// the last one running, the whole process.
+pub fn exit_current_and_run_next_task(exit_code: i32) {
+    let thread = current_thread();
+    let process = current_process();
+    let exit_process = thread.tid == 0
+        || process
+            .lock_inner()
+            .threads
+            .iter()
+            .flatten()
+            .all(|other| Arc::ptr_eq(other, &thread) || other.lock_inner().exit_code.is_some());
+    drop(process);
+    drop(thread);
+    exit_current(exit_code, exit_process);
+}
+
+/// Exit the current process, with all of its threads, with `exit_code`.
+pub fn exit_current_process_and_run_next_task(exit_code: i32) {
+    exit_current(exit_code, true);
+}
+
+fn exit_current(exit_code: i32, exit_process: bool) {
     let current = PROCESSOR
         .lock()
         .current()
         .take()
-        .expect("no current process");
+        .expect("no current thread");
+    charge(&current, false);
+    let process = current.process.upgrade().unwrap();
     let mut current_inner = current.lock_inner();
     let current_cx_ptr = &mut current_inner.task_cx as *mut TaskContext;
-    current_inner.status = process::ProcessStatus::Exited;
+    // the thread stays in the process, keeping the kernel stack we are on,
+    // until it is collected
+    current_inner.status = ThreadStatus::Exited;
+    current_inner.exit_code = Some(exit_code);
+    let res = current_inner.res.take();
     drop(current_inner);
+    drop(res);
+    if exit_process {
+        if process.pid.0 == INITPROC.pid.0 {
+            info!("Init process exited with code {}", exit_code);
+            shutdown(exit_code != 0);
+        }
+        PID2PROCESS.lock().remove(&process.pid.0);
+        let mut process_inner = process.lock_inner();
+        // stay as a zombie until the parent collects the exit code, which is
+        // also when the pid and the last kernel stack are released
+        process_inner.zombie = true;
+        process_inner.exit_code = exit_code;
+        // hand the children over to init, which reaps them once they exit
+        {
+            let mut initproc_inner = INITPROC.lock_inner();
+            for child in process_inner.children.drain(..) {
+                child.lock_inner().parent = Some(Arc::downgrade(&INITPROC));
+                initproc_inner.children.push(child);
+            }
+        }
+        // the other threads never run again
+        let mut threads = Vec::new();
+        for slot in process_inner.threads.iter_mut() {
+            if let Some(thread) = slot.take_if(|thread| !Arc::ptr_eq(thread, &current)) {
+                THREAD_MANAGER.lock().remove(&thread);
+                remove_timer(&thread);
+                futex_purge(&thread);
+                threads.push(thread);
+            }
+        }
+        // the user address space is no longer needed
+        process_inner.memory_set.recycle_data_pages();
+        // close files now, so pipe readers see EOF without waiting for the reap
+        process_inner.fd_table.clear();
+        drop(process_inner);
+        // their tids and kernel stacks go, which needs the process unlocked
+        for thread in threads {
+            let mut thread_inner = thread.lock_inner();
+            // whatever they are blocked on must not wake them up
+            thread_inner.status = ThreadStatus::Exited;
+            let res = thread_inner.res.take();
+            drop(thread_inner);
+            drop(res);
+        }
+    }
+    drop(process);
     drop(current);
     schedule(current_cx_ptr);
 }
@@ -0,0 +1,454 @@
+use alloc::{
+    string::String,
+    sync::{Arc, Weak},
+    vec,
+    vec::Vec,
+};
+use core::mem::size_of;
+
+use crate::{
+    fs::{normalize, File, Stdin, Stdout},
+    memory::{
+        translated_byte_buffer, translated_refmut, translated_str, MapPermission, MemorySet,
+        KERNEL_SPACE,
+    },
+    sync::{Condvar, Mutex, Semaphore, SpinLock, SpinLockGuard},
+    trap::{trap_handler, TrapContext},
+};
+
+use super::{
+    cputime::CpuTimes,
+    id::{IdAllocator, Pid},
+    scheduler::{SchedInfo, DEFAULT_PRIORITY},
+    signal::{SignalAction, SignalFlags, MAX_SIG, SIG_IGN},
+    thread::{Thread, ThreadUserRes},
+};
+
+pub struct Process {
+    pub pid: Pid,
+    pub sched: SchedInfo,
+    pub cpu_times: CpuTimes,
+    inner: SpinLock<ProcessInner>,
+}
+
+pub struct ProcessInner {
+    pub parent: Option<Weak<Process>>,
+    pub children: Vec<Arc<Process>>,
+    /// exited but not yet reaped by its parent through `sys_waitpid`
+    pub zombie: bool,
+    pub exit_code: i32,
+    pub memory_set: MemorySet,
+    /// threads indexed by tid, `None` marks a free slot
+    pub threads: Vec<Option<Arc<Thread>>>,
+    pub tid_allocator: IdAllocator,
+    /// the heap is `[heap_bottom, program_brk)`, moved by `sys_sbrk`
+    pub heap_bottom: usize,
+    pub program_brk: usize,
+    /// open files indexed by fd, `None` marks a free slot
+    pub fd_table: Vec<Option<Arc<dyn File>>>,
+    /// current working directory, absolute and normalized
+    pub cwd: String,
+    /// synchronization primitives of the threads, indexed by id; a forked
+    /// child starts without any
+    pub mutex_list: Vec<Arc<dyn Mutex>>,
+    pub semaphore_list: Vec<Arc<Semaphore>>,
+    pub condvar_list: Vec<Arc<Condvar>>,
+    /// signals sent but not yet acted on
+    pub signals: SignalFlags,
+    /// signals held back by `sys_sigprocmask`
+    pub signal_mask: SignalFlags,
+    pub signal_actions: [SignalAction; MAX_SIG + 1],
+    /// stopped by a signal until SIGCONT
+    pub stopped: bool,
+    /// the signal that stopped the process, until `sys_waitpid` has reported
+    /// the stop to the parent
+    pub unreported_stop: Option<usize>,
+    /// the process group, which the console signals as a whole
+    pub pgid: usize,
+}
+
+impl ProcessInner {
+    pub fn is_zombie(&self) -> bool {
+        self.zombie
+    }
+
+    /// Return the thread `tid`, if it has not been collected.
+    pub fn thread(&self, tid: usize) -> Option<Arc<Thread>> {
+        self.threads.get(tid).cloned().flatten()
+    }
+
+    /// Install `thread` in its slot of the thread table.
+    fn add_thread(&mut self, thread: Arc<Thread>) {
+        let tid = thread.tid;
+        if self.threads.len() <= tid {
+            self.threads.resize(tid + 1, None);
+        }
+        self.threads[tid] = Some(thread);
+    }
+
+    /// Return the signals held back from delivery while `handling_sig`, the
+    /// signal whose handler is running on the thread in question, if any:
+    /// the signal mask, and that signal and the mask of its action.
+    pub fn blocked_signals(&self, handling_sig: Option<usize>) -> SignalFlags {
+        match handling_sig {
+            Some(signum) => {
+                self.signal_mask
+                    | self.signal_actions[signum].mask
+                    | SignalFlags::from_signum(signum).unwrap()
+            }
+            None => self.signal_mask,
+        }
+    }
+
+    /// Return the file at `fd`, if it is open.
+    pub fn file(&self, fd: usize) -> Option<Arc<dyn File>> {
+        self.fd_table.get(fd).cloned().flatten()
+    }
+
+    /// Read the C string at `ptr` from user space, returning `None` if it is
+    /// not readable.
+    pub fn user_str(&mut self, ptr: *const u8) -> Option<String> {
+        if !self.memory_set.fault_in_str(ptr as usize) {
+            return None;
+        }
+        Some(translated_str(self.memory_set.satp_token(), ptr))
+    }
+
+    /// Read the path at `path` from user space and resolve it against the
+    /// cwd, returning `None` if it is not readable.
+    pub fn user_path(&mut self, path: *const u8) -> Option<String> {
+        let path = self.user_str(path)?;
+        Some(normalize(&self.cwd, &path))
+    }
+
+    /// Read a `T` from user space at `ptr`, returning `None` if it is not
+    /// readable.
+    pub fn read_user<T: Copy>(&mut self, ptr: *const T) -> Option<T> {
+        if !self
+            .memory_set
+            .fault_in(ptr as usize, size_of::<T>(), MapPermission::R)
+        {
+            return None;
+        }
+        let mut value = core::mem::MaybeUninit::<T>::uninit();
+        let dst = value.as_mut_ptr() as *mut u8;
+        let buffers = translated_byte_buffer(
+            self.memory_set.satp_token(),
+            ptr as *const u8,
+            size_of::<T>(),
+        );
+        for (i, src) in buffers.into_iter().flatten().enumerate() {
+            unsafe { dst.add(i).write(*src) };
+        }
+        Some(unsafe { value.assume_init() })
+    }
+
+    /// Write `value` to user space at `ptr`, returning false if it is not
+    /// writable.
+    pub fn write_user<T>(&mut self, ptr: *mut T, value: &T) -> bool {
+        if !self
+            .memory_set
+            .fault_in(ptr as usize, size_of::<T>(), MapPermission::W)
+        {
+            return false;
+        }
+        let src = value as *const T as *const u8;
+        let buffers = translated_byte_buffer(
+            self.memory_set.satp_token(),
+            ptr as *const u8,
+            size_of::<T>(),
+        );
+        for (i, dst) in buffers.into_iter().flatten().enumerate() {
+            *dst = unsafe { src.add(i).read() };
+        }
+        true
+    }
+
+    /// Allocate the lowest free fd.
+    pub fn alloc_fd(&mut self) -> usize {
+        if let Some(fd) = self.fd_table.iter().position(|file| file.is_none()) {
+            fd
+        } else {
+            self.fd_table.push(None);
+            self.fd_table.len() - 1
+        }
+    }
+}
+
+impl Process {
+    #[track_caller]
+    pub fn lock_inner(&self) -> SpinLockGuard<'_, ProcessInner> {
+        self.inner.lock()
+    }
+}
+
+impl Process {
+    /// Create a process running `elf_data`, with a main thread that is not
+    /// runnable yet.
+    pub fn new(elf_data: &[u8]) -> Arc<Self> {
+        let (memory_set, heap_bottom, entry_point) =
+            MemorySet::from_elf(elf_data).expect("invalid ELF");
+        let pid = Pid::new();
+        let pgid = pid.0;
+        let process = Arc::new(Self {
+            pid,
+            sched: SchedInfo::new(DEFAULT_PRIORITY, 0),
+            cpu_times: CpuTimes::default(),
+            inner: SpinLock::new(
+                "Process::inner",
+                ProcessInner {
+                    zombie: false,
+                    exit_code: 0,
+                    memory_set,
+                    threads: Vec::new(),
+                    tid_allocator: IdAllocator::new(0),
+                    heap_bottom,
+                    program_brk: heap_bottom,
+                    parent: None,
+                    children: Vec::new(),
+                    fd_table: vec![
+                        // 0 -> stdin
+                        Some(Arc::new(Stdin)),
+                        // 1 -> stdout
+                        Some(Arc::new(Stdout)),
+                        // 2 -> stderr
+                        Some(Arc::new(Stdout)),
+                    ],
+                    cwd: String::from("/"),
+                    mutex_list: Vec::new(),
+                    semaphore_list: Vec::new(),
+                    condvar_list: Vec::new(),
+                    signals: SignalFlags::empty(),
+                    signal_mask: SignalFlags::empty(),
+                    signal_actions: [SignalAction::default(); MAX_SIG + 1],
+                    stopped: false,
+                    unreported_stop: None,
+                    pgid,
+                },
+            ),
+        });
+        let mut inner = process.lock_inner();
+        let tid = inner.tid_allocator.alloc();
+        let thread = Arc::new(
+            Thread::new(&process, &mut inner, tid, true).expect("cannot map the main thread"),
+        );
+        *thread.lock_inner().trap_cx() = TrapContext::app_init_context(
+            entry_point,
+            thread.ustack_top(),
+            KERNEL_SPACE.lock().satp_token(),
+            thread.kernel_stack.top(),
+            trap_handler as usize,
+        );
+        inner.add_thread(thread);
+        drop(inner);
+        process
+    }
+
+    /// Create a child process with a copy of the address space, and a single
+    /// thread that continues from where `thread`, the calling thread of this
+    /// process, is, under the same tid. It is not runnable yet.
+    ///
+    /// Fails, leaving no child behind, if memory runs out.
+    pub fn fork(self: &Arc<Process>, thread: &Thread) -> Result<Arc<Process>, &'static str> {
+        let mut parent_inner = self.inner.lock();
+        let mut memory_set = MemorySet::from_existed(&mut parent_inner.memory_set)?;
+        // only the calling thread lives on in the child
+        for other in parent_inner.threads.iter().flatten() {
+            if other.tid != thread.tid {
+                ThreadUserRes::unmap(other.tid, &mut memory_set);
+            }
+        }
+        let process = Arc::new(Process {
+            pid: Pid::new(),
+            // the child starts level with the parent, rather than far ahead
+            // of every other process
+            sched: SchedInfo::new(self.sched.priority(), self.sched.pass()),
+            cpu_times: CpuTimes::default(),
+            inner: SpinLock::new(
+                "Process::inner",
+                ProcessInner {
+                    parent: Some(Arc::downgrade(self)),
+                    children: Vec::new(),
+                    zombie: false,
+                    exit_code: 0,
+                    memory_set,
+                    threads: Vec::new(),
+                    // the stacks of lower tids are gone with their threads
+                    tid_allocator: IdAllocator::new(thread.tid),
+                    heap_bottom: parent_inner.heap_bottom,
+                    program_brk: parent_inner.program_brk,
+                    fd_table: parent_inner.fd_table.clone(),
+                    cwd: parent_inner.cwd.clone(),
+                    mutex_list: Vec::new(),
+                    semaphore_list: Vec::new(),
+                    condvar_list: Vec::new(),
+                    signals: SignalFlags::empty(),
+                    signal_mask: parent_inner.signal_mask,
+                    signal_actions: parent_inner.signal_actions,
+                    stopped: false,
+                    unreported_stop: None,
+                    pgid: parent_inner.pgid,
+                },
+            ),
+        });
+        drop(parent_inner);
+        let mut inner = process.lock_inner();
+        let tid = inner.tid_allocator.alloc();
+        // the stack and TrapContext came with the address space
+        let child_thread = Arc::new(Thread::new(&process, &mut inner, tid, false)?);
+        let thread_inner = thread.lock_inner();
+        let mut child_thread_inner = child_thread.lock_inner();
+        child_thread_inner.handling_sig = thread_inner.handling_sig;
+        child_thread_inner.trap_cx_backup = thread_inner.trap_cx_backup;
+        child_thread_inner.trap_cx().kernel_sp = child_thread.kernel_stack.top();
+        drop(child_thread_inner);
+        inner.add_thread(child_thread);
+        drop(inner);
+        self.inner.lock().children.push(process.clone());
+        Ok(process)
+    }
+
+    /// Replace the program with `elf_data`, which may only be done while
+    /// `thread`, the calling thread, is the only one left.
+    ///
+    /// Fails, leaving the old program in place, if `elf_data` cannot be
+    /// loaded.
+    pub fn exec(
+        &self,
+        thread: &Thread,
+        elf_data: &[u8],
+        args: Vec<String>,
+        envs: Vec<String>,
+    ) -> Result<(), &'static str> {
+        let (mut memory_set, heap_bottom, entry_point) = MemorySet::from_elf(elf_data)?;
+        let mut inner = self.lock_inner();
+        let mut thread_inner = thread.lock_inner();
+        let res = thread_inner.res.as_ref().unwrap();
+        ThreadUserRes::map(thread.tid, &mut memory_set)?;
+        let trap_cx_ppn = res.trap_cx_ppn(&memory_set);
+        let (user_sp, argv_base, envp_base) =
+            push_args(&mut memory_set, thread.ustack_top(), &args, &envs)
+                .ok_or("cannot load the user stack")?;
+        thread_inner.trap_cx_ppn = trap_cx_ppn;
+        inner.memory_set = memory_set;
+        inner.heap_bottom = heap_bottom;
+        inner.program_brk = heap_bottom;
+        inner.mutex_list.clear();
+        inner.semaphore_list.clear();
+        inner.condvar_list.clear();
+        // handlers are gone with the old program, but ignored signals stay
+        // ignored
+        for action in inner.signal_actions.iter_mut() {
+            if action.handler != SIG_IGN {
+                *action = SignalAction::default();
+            }
+        }
+        thread_inner.handling_sig = None;
+        thread_inner.trap_cx_backup = None;
+
+        let trap_cx = thread_inner.trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry_point,
+            user_sp,
+            KERNEL_SPACE.lock().satp_token(),
+            thread.kernel_stack.top(),
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = args.len();
+        trap_cx.x[11] = argv_base;
+        trap_cx.x[12] = envp_base;
+        Ok(())
+    }
+
+    /// Create a thread that runs from `entry` with `arg` in `a0`, returning
+    /// it, not runnable yet. Fails if its user stack or TrapContext cannot be
+    /// mapped, say because the program has mapped something there.
+    pub fn create_thread(
+        self: &Arc<Process>,
+        entry: usize,
+        arg: usize,
+    ) -> Result<Arc<Thread>, &'static str> {
+        let mut inner = self.lock_inner();
+        let tid = inner.tid_allocator.alloc();
+        let thread = match Thread::new(self, &mut inner, tid, true) {
+            Ok(thread) => Arc::new(thread),
+            Err(err) => {
+                inner.tid_allocator.dealloc(tid);
+                return Err(err);
+            }
+        };
+        let trap_cx = thread.lock_inner().trap_cx();
+        *trap_cx = TrapContext::app_init_context(
+            entry,
+            thread.ustack_top(),
+            KERNEL_SPACE.lock().satp_token(),
+            thread.kernel_stack.top(),
+            trap_handler as usize,
+        );
+        trap_cx.x[10] = arg;
+        inner.add_thread(thread.clone());
+        Ok(thread)
+    }
+}
+
+/// Copy `args` and `envs` onto the user stack below `user_sp`, as C strings
+/// followed by NULL-terminated `argv` and `envp` pointer arrays:
+///
+/// ```text
+/// high  | env strings | arg strings | envp[] | argv[] |  low
+///                                                    ^ new user_sp
+/// ```
+///
+/// Returns the new (16-byte aligned) user_sp, `argv` and `envp`, or `None`
+/// if the stack cannot be loaded. The caller checks that [`args_size`] fits.
+fn push_args(
+    memory_set: &mut MemorySet,
+    mut user_sp: usize,
+    args: &[String],
+    envs: &[String],
+) -> Option<(usize, usize, usize)> {
+    let args_size = args_size(args, envs);
+    if !memory_set.fault_in(user_sp - args_size, args_size, MapPermission::W) {
+        return None;
+    }
+    let token = memory_set.satp_token();
+    let mut push_strs = |strs: &[String]| -> Vec<usize> {
+        strs.iter()
+            .map(|s| {
+                user_sp -= s.len() + 1;
+                let bytes = s.as_bytes().iter().chain(core::iter::once(&0));
+                let buffers = translated_byte_buffer(token, user_sp as *const u8, s.len() + 1);
+                for (dst, src) in buffers.into_iter().flatten().zip(bytes) {
+                    *dst = *src;
+                }
+                user_sp
+            })
+            .collect()
+    };
+    let env_ptrs = push_strs(envs);
+    let arg_ptrs = push_strs(args);
+    user_sp -= user_sp % size_of::<usize>();
+    let mut push_ptrs = |ptrs: &[usize]| -> usize {
+        user_sp -= (ptrs.len() + 1) * size_of::<usize>();
+        let base = user_sp;
+        for (i, &ptr) in ptrs.iter().chain(core::iter::once(&0)).enumerate() {
+            *translated_refmut(token, (base + i * size_of::<usize>()) as *mut usize) = ptr;
+        }
+        base
+    };
+    let envp_base = push_ptrs(&env_ptrs);
+    let argv_base = push_ptrs(&arg_ptrs);
+    user_sp -= user_sp % 16;
+    Some((user_sp, argv_base, envp_base))
+}
+
+/// Return how much of the user stack `push_args` takes for `args` and
+/// `envs` at most.
+pub fn args_size(args: &[String], envs: &[String]) -> usize {
+    args.iter()
+        .chain(envs)
+        .map(|s| s.len() + 1 + size_of::<usize>())
+        .sum::<usize>()
+        + 3 * size_of::<usize>()
+        + 16
+}
@@ -2,27 +2,28 @@ use alloc::sync::Arc;
 use lazy_static::lazy_static;
 use log::info;
 
-use crate::{sbi::shutdown, task::switch::__switch, trap::TrapContext};
+use crate::{sbi::shutdown, sync::SpinLock, task::switch::__switch, timer, trap::TrapContext};
 
 use super::{
-    process::{Process, ProcessStatus},
-    TaskContext, PROCESS_MANAGER,
+    process::Process,
+    thread::{Thread, ThreadStatus},
+    TaskContext, THREAD_MANAGER,
 };
 
 pub struct Processor {
-    current_process: Option<Arc<Process>>,
+    current_thread: Option<Arc<Thread>>,
     idle_task_cx: TaskContext,
 }
 
 impl Processor {
     pub fn new() -> Self {
         Self {
-            current_process: None,
+            current_thread: None,
             idle_task_cx: TaskContext::zero(),
         }
     }
-    pub fn current(&mut self) -> &mut Option<Arc<Process>> {
-        &mut self.current_process
+    pub fn current(&mut self) -> &mut Option<Arc<Thread>> {
+        &mut self.current_thread
     }
     fn idle_task_cx(&mut self) -> &mut TaskContext {
         &mut self.idle_task_cx
@@ -30,64 +31,58 @@ impl Processor {
 }
 
 lazy_static! {
-    pub static ref PROCESSOR: spin::Mutex<Processor> = spin::Mutex::new(Processor::new());
+    pub static ref PROCESSOR: SpinLock<Processor> = SpinLock::new("PROCESSOR", Processor::new());
 }
 
-pub fn run_processes() -> ! {
+pub fn run_threads() -> ! {
     loop {
-        let mut process_manager = PROCESS_MANAGER.lock();
-        if let Some(process) = process_manager.fetch() {
-            drop(process_manager);
-
-            let mut process_inner = process.lock_inner();
-            process_inner.status = ProcessStatus::Running;
-            let next_task_cx_ptr = &process_inner.task_cx as *const TaskContext;
-            drop(process_inner);
+        let thread = THREAD_MANAGER.lock().fetch();
+        if let Some(thread) = thread {
+            let mut thread_inner = thread.lock_inner();
+            thread_inner.status = ThreadStatus::Running;
+            // the time it waited to run is nobody's
+            thread_inner.charged_at = timer::read();
+            let next_task_cx_ptr = &thread_inner.task_cx as *const TaskContext;
+            drop(thread_inner);
 
             let mut processor = PROCESSOR.lock();
-            processor.current_process = Some(process);
+            processor.current_thread = Some(thread);
             let idle_task_cx_ptr = processor.idle_task_cx() as *mut TaskContext;
             drop(processor);
             unsafe {
                 __switch(idle_task_cx_ptr, next_task_cx_ptr);
             }
+        } else if timer::has_timers() {
+            // the hart idles until a sleep or a futex wait times out
+            timer::check_timers();
         } else {
-            info!("No process to run, shutdown");
+            info!("No thread to run, shutdown");
             shutdown(false);
         }
     }
 }
 
-pub fn current_process() -> Arc<Process> {
+pub fn current_thread() -> Arc<Thread> {
     PROCESSOR.lock().current().as_ref().unwrap().clone()
 }
 
+pub fn current_process() -> Arc<Process> {
+    current_thread().process.upgrade().unwrap()
+}
+
 pub fn current_trap_cx() -> &'static mut TrapContext {
-    PROCESSOR
-        .lock()
-        .current()
-        .as_ref()
-        .unwrap()
-        .lock_inner()
-        .trap_cx()
+    current_thread().lock_inner().trap_cx()
 }
 
 pub fn current_user_token() -> usize {
-    PROCESSOR
-        .lock()
-        .current()
-        .as_ref()
-        .unwrap()
-        .lock_inner()
-        .memory_set
-        .satp_token()
+    current_process().lock_inner().memory_set.satp_token()
 }
 
-pub fn schedule(switched_process_cx_ptr: *mut TaskContext) {
+pub fn schedule(switched_task_cx_ptr: *mut TaskContext) {
     let mut processor = PROCESSOR.lock();
-    let idle_process_cx_ptr = processor.idle_task_cx() as *const TaskContext;
+    let idle_task_cx_ptr = processor.idle_task_cx() as *const TaskContext;
     drop(processor);
     unsafe {
-        __switch(switched_process_cx_ptr, idle_process_cx_ptr);
+        __switch(switched_task_cx_ptr, idle_task_cx_ptr);
     }
 }
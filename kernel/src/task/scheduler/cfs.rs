@@ -0,0 +1,67 @@
+use alloc::{collections::BTreeMap, sync::Arc};
+
+use super::{Scheduler, DEFAULT_PRIORITY};
+use crate::{task::thread::Thread, timer};
+
+/// Completely-fair-style scheduling: each thread accrues virtual runtime as
+/// it runs, at a rate inversely proportional to the priority of its process,
+/// and the thread with the least runs next. Each process thus gets CPU time
+/// in proportion to its priority.
+///
+/// A thread that becomes runnable, new or woken up, starts level with the
+/// least virtual runtime among the others, so it can neither starve them by
+/// having slept nor wait behind them.
+pub struct CfsScheduler {
+    /// the runnable threads by virtual runtime, ties broken by arrival
+    threads: BTreeMap<(u64, u64), Arc<Thread>>,
+    /// counts arrivals, to break ties
+    arrivals: u64,
+    /// the least virtual runtime so far, which never goes backwards
+    min_vruntime: u64,
+    /// the thread fetched last, with its virtual runtime and when it started
+    /// to run, until it comes back or the next fetch
+    running: Option<(Arc<Thread>, u64, u64)>,
+}
+
+fn weight(thread: &Thread) -> u64 {
+    thread
+        .process
+        .upgrade()
+        .map_or(DEFAULT_PRIORITY, |process| process.sched.priority()) as u64
+}
+
+impl Scheduler for CfsScheduler {
+    fn new() -> Self {
+        Self {
+            threads: BTreeMap::new(),
+            arrivals: 0,
+            min_vruntime: 0,
+            running: None,
+        }
+    }
+
+    fn add(&mut self, thread: Arc<Thread>) {
+        let vruntime = match self.running.take() {
+            Some((running, vruntime, started)) if Arc::ptr_eq(&running, &thread) => {
+                let ran = timer::read() - started;
+                vruntime + ran * DEFAULT_PRIORITY as u64 / weight(&thread)
+            }
+            _ => self.min_vruntime,
+        };
+        self.arrivals += 1;
+        self.threads.insert((vruntime, self.arrivals), thread);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<Thread>> {
+        // a thread that did not come back before this has blocked
+        self.running = None;
+        let ((vruntime, _), thread) = self.threads.pop_first()?;
+        self.min_vruntime = self.min_vruntime.max(vruntime);
+        self.running = Some((thread.clone(), vruntime, timer::read()));
+        Some(thread)
+    }
+
+    fn remove(&mut self, thread: &Arc<Thread>) {
+        self.threads.retain(|_, other| !Arc::ptr_eq(other, thread));
+    }
+}
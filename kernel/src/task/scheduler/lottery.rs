@@ -0,0 +1,65 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::Scheduler;
+use crate::{task::thread::Thread, timer};
+
+/// Lottery scheduling: each runnable thread holds as many tickets as the
+/// priority of its process, and the thread to run is drawn at random by
+/// ticket. Each process thus gets time slices in proportion to its priority,
+/// on average.
+pub struct LotteryScheduler {
+    threads: VecDeque<Arc<Thread>>,
+    /// the state of a xorshift generator, never 0
+    seed: u64,
+}
+
+impl LotteryScheduler {
+    fn random(&mut self) -> u64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 7;
+        self.seed ^= self.seed << 17;
+        self.seed
+    }
+}
+
+fn tickets(thread: &Thread) -> u64 {
+    thread
+        .process
+        .upgrade()
+        .map_or(0, |process| process.sched.priority() as u64)
+}
+
+impl Scheduler for LotteryScheduler {
+    fn new() -> Self {
+        Self {
+            threads: VecDeque::new(),
+            seed: timer::read() | 1,
+        }
+    }
+
+    fn add(&mut self, thread: Arc<Thread>) {
+        self.threads.push_back(thread);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<Thread>> {
+        let total: u64 = self.threads.iter().map(|thread| tickets(thread)).sum();
+        if total == 0 {
+            return self.threads.pop_front();
+        }
+        let mut winner = self.random() % total;
+        let index = self.threads.iter().position(|thread| {
+            let tickets = tickets(thread);
+            if winner < tickets {
+                true
+            } else {
+                winner -= tickets;
+                false
+            }
+        })?;
+        self.threads.remove(index)
+    }
+
+    fn remove(&mut self, thread: &Arc<Thread>) {
+        self.threads.retain(|other| !Arc::ptr_eq(other, thread));
+    }
+}
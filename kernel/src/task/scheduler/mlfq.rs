@@ -0,0 +1,96 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::{Scheduler, TIME_SLICE};
+use crate::{task::thread::Thread, timer};
+
+const LEVELS: usize = 4;
+/// How long a thread may run at each level, over all its turns there, before
+/// it drops to the next
+const ALLOTMENTS: [u64; LEVELS] = [TIME_SLICE, 2 * TIME_SLICE, 4 * TIME_SLICE, u64::MAX];
+/// How often every thread is boosted back to the top level, so that none
+/// starves
+const BOOST_PERIOD: u64 = 100 * TIME_SLICE;
+
+struct Entry {
+    thread: Arc<Thread>,
+    level: usize,
+    /// how long the thread has run at its level
+    used: u64,
+}
+
+/// Multi-level feedback queue: threads run round-robin within the highest
+/// level that has any, start at the top level, and drop a level each time
+/// they use up the allotment of theirs, so that threads that give up the CPU
+/// early stay ahead of those that do not. Every `BOOST_PERIOD`, all threads
+/// go back to the top.
+///
+/// A thread that blocks starts at the top again when it is woken up.
+pub struct MlfqScheduler {
+    levels: [VecDeque<Entry>; LEVELS],
+    /// the thread fetched last, with when it started to run, until it comes
+    /// back or another is fetched
+    running: Option<(Entry, u64)>,
+    last_boost: u64,
+}
+
+impl MlfqScheduler {
+    fn boost(&mut self, now: u64) {
+        self.last_boost = now;
+        for level in 1..LEVELS {
+            while let Some(mut entry) = self.levels[level].pop_front() {
+                entry.level = 0;
+                entry.used = 0;
+                self.levels[0].push_back(entry);
+            }
+        }
+    }
+}
+
+impl Scheduler for MlfqScheduler {
+    fn new() -> Self {
+        Self {
+            levels: Default::default(),
+            running: None,
+            last_boost: timer::read(),
+        }
+    }
+
+    fn add(&mut self, thread: Arc<Thread>) {
+        let now = timer::read();
+        let entry = match self.running.take() {
+            Some((mut entry, started)) if Arc::ptr_eq(&entry.thread, &thread) => {
+                entry.used += now - started;
+                if entry.used >= ALLOTMENTS[entry.level] {
+                    entry.level += 1;
+                    entry.used = 0;
+                }
+                entry
+            }
+            _ => Entry {
+                thread,
+                level: 0,
+                used: 0,
+            },
+        };
+        self.levels[entry.level].push_back(entry);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<Thread>> {
+        let now = timer::read();
+        if now - self.last_boost >= BOOST_PERIOD {
+            self.boost(now);
+        }
+        // a thread that did not come back before this has blocked
+        self.running = None;
+        let entry = self.levels.iter_mut().find_map(|level| level.pop_front())?;
+        let thread = entry.thread.clone();
+        self.running = Some((entry, now));
+        Some(thread)
+    }
+
+    fn remove(&mut self, thread: &Arc<Thread>) {
+        for level in self.levels.iter_mut() {
+            level.retain(|entry| !Arc::ptr_eq(&entry.thread, thread));
+        }
+    }
+}
@@ -0,0 +1,119 @@
+//! Scheduling policies
+//!
+//! The ready queue, [`super::ThreadManager`], leaves the choice of which
+//! thread runs next to a [`Scheduler`]. Each thread runs for one time slice
+//! at most, until the next timer interrupt, before it is handed back.
+//!
+//! The policy is picked by a feature of the kernel crate: `sched-rr`,
+//! `sched-stride`, `sched-mlfq`, `sched-lottery` or `sched-cfs`, at most one
+//! of them. Without any, it is stride scheduling.
+
+#[cfg(feature = "sched-cfs")]
+mod cfs;
+#[cfg(feature = "sched-lottery")]
+mod lottery;
+#[cfg(feature = "sched-mlfq")]
+mod mlfq;
+#[cfg(feature = "sched-rr")]
+mod rr;
+#[cfg(not(any(
+    feature = "sched-rr",
+    feature = "sched-mlfq",
+    feature = "sched-lottery",
+    feature = "sched-cfs"
+)))]
+mod stride;
+
+use alloc::sync::Arc;
+use core::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+use super::thread::Thread;
+use crate::config::{CLOCK_FREQ, TICKS_PER_SEC};
+
+#[cfg(any(
+    all(feature = "sched-rr", feature = "sched-stride"),
+    all(feature = "sched-rr", feature = "sched-mlfq"),
+    all(feature = "sched-rr", feature = "sched-lottery"),
+    all(feature = "sched-rr", feature = "sched-cfs"),
+    all(feature = "sched-stride", feature = "sched-mlfq"),
+    all(feature = "sched-stride", feature = "sched-lottery"),
+    all(feature = "sched-stride", feature = "sched-cfs"),
+    all(feature = "sched-mlfq", feature = "sched-lottery"),
+    all(feature = "sched-mlfq", feature = "sched-cfs"),
+    all(feature = "sched-lottery", feature = "sched-cfs"),
+))]
+compile_error!("at most one of the sched-* features can be enabled");
+
+#[cfg(feature = "sched-cfs")]
+pub type SchedulerImpl = cfs::CfsScheduler;
+#[cfg(feature = "sched-lottery")]
+pub type SchedulerImpl = lottery::LotteryScheduler;
+#[cfg(feature = "sched-mlfq")]
+pub type SchedulerImpl = mlfq::MlfqScheduler;
+#[cfg(feature = "sched-rr")]
+pub type SchedulerImpl = rr::RoundRobinScheduler;
+#[cfg(not(any(
+    feature = "sched-rr",
+    feature = "sched-mlfq",
+    feature = "sched-lottery",
+    feature = "sched-cfs"
+)))]
+pub type SchedulerImpl = stride::StrideScheduler;
+
+pub trait Scheduler: Send {
+    fn new() -> Self;
+    /// Make `thread` runnable.
+    fn add(&mut self, thread: Arc<Thread>);
+    /// Take the thread to run next out of the runnable ones.
+    fn fetch(&mut self) -> Option<Arc<Thread>>;
+    /// Take `thread` out of the runnable ones, if it is there.
+    fn remove(&mut self, thread: &Arc<Thread>);
+}
+
+/// The priority a process starts with
+pub const DEFAULT_PRIORITY: usize = 16;
+/// The lowest priority `sys_set_priority` accepts
+pub const MIN_PRIORITY: usize = 2;
+/// The length of a time slice, in timer ticks, for the policies that
+/// measure how long threads run
+#[allow(dead_code)]
+pub const TIME_SLICE: u64 = CLOCK_FREQ / TICKS_PER_SEC;
+
+/// What the scheduler keeps for each process, shared by its threads
+///
+/// This is not in `ProcessInner`, since it is read and updated with the
+/// ready queue locked, while the process is locked before the ready queue.
+pub struct SchedInfo {
+    priority: AtomicUsize,
+    /// how far the process has advanced, by its stride each time one of its
+    /// threads is picked
+    pass: AtomicU64,
+}
+
+impl SchedInfo {
+    pub fn new(priority: usize, pass: u64) -> Self {
+        Self {
+            priority: AtomicUsize::new(priority),
+            pass: AtomicU64::new(pass),
+        }
+    }
+
+    pub fn priority(&self) -> usize {
+        self.priority.load(Ordering::Relaxed)
+    }
+
+    pub fn set_priority(&self, priority: usize) {
+        assert!(priority >= MIN_PRIORITY);
+        self.priority.store(priority, Ordering::Relaxed);
+    }
+
+    pub fn pass(&self) -> u64 {
+        self.pass.load(Ordering::Relaxed)
+    }
+
+    /// Only stride scheduling advances the pass; the others leave it at 0.
+    #[allow(dead_code)]
+    pub fn advance(&self, stride: u64) {
+        self.pass.fetch_add(stride, Ordering::Relaxed);
+    }
+}
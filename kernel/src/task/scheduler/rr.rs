@@ -0,0 +1,29 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::Scheduler;
+use crate::task::thread::Thread;
+
+/// Round-robin: threads run in the order they became runnable
+pub struct RoundRobinScheduler {
+    threads: VecDeque<Arc<Thread>>,
+}
+
+impl Scheduler for RoundRobinScheduler {
+    fn new() -> Self {
+        Self {
+            threads: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, thread: Arc<Thread>) {
+        self.threads.push_back(thread);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<Thread>> {
+        self.threads.pop_front()
+    }
+
+    fn remove(&mut self, thread: &Arc<Thread>) {
+        self.threads.retain(|other| !Arc::ptr_eq(other, thread));
+    }
+}
@@ -0,0 +1,44 @@
+use alloc::{collections::VecDeque, sync::Arc};
+
+use super::Scheduler;
+use crate::task::thread::Thread;
+
+/// The strides are this divided by the priority, so they stay far apart
+const BIG_STRIDE: u64 = 1 << 20;
+
+/// Stride scheduling: the thread to run is one of the process with the least
+/// pass, which then advances by its stride, inversely proportional to its
+/// priority. Each process thus gets time slices in proportion to its
+/// priority. Ties go to the thread that has waited longest.
+pub struct StrideScheduler {
+    threads: VecDeque<Arc<Thread>>,
+}
+
+impl Scheduler for StrideScheduler {
+    fn new() -> Self {
+        Self {
+            threads: VecDeque::new(),
+        }
+    }
+
+    fn add(&mut self, thread: Arc<Thread>) {
+        self.threads.push_back(thread);
+    }
+
+    fn fetch(&mut self) -> Option<Arc<Thread>> {
+        let (index, process) = self
+            .threads
+            .iter()
+            .enumerate()
+            .filter_map(|(index, thread)| Some((index, thread.process.upgrade()?)))
+            .min_by_key(|(_, process)| process.sched.pass())?;
+        process
+            .sched
+            .advance(BIG_STRIDE / process.sched.priority() as u64);
+        self.threads.remove(index)
+    }
+
+    fn remove(&mut self, thread: &Arc<Thread>) {
+        self.threads.retain(|other| !Arc::ptr_eq(other, thread));
+    }
+}
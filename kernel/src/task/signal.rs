@@ -0,0 +1,323 @@
+//! POSIX-style signals
+//!
+//! A signal sent to a process is only recorded as pending. The process acts
+//! on its pending signals in [`handle_signals`], each time it is about to
+//! return to user space, either by the default action or by running its own
+//! handler.
+//!
+//! A user handler runs on the interrupted user stack with the signal number
+//! in `a0`, and returns to the `restorer` of its [`SignalAction`], which must
+//! call `sys_sigreturn` to restore the trap context saved before the handler.
+//! Only one handler runs at a time on a thread: signals to be caught while it
+//! runs stay pending until it returns, or until another thread of the process
+//! returns to user space and takes them.
+
+use alloc::vec::Vec;
+use bitflags::bitflags;
+use log::warn;
+
+use super::{
+    current_process, current_thread, exit_current_process_and_run_next_task, process::Process,
+    suspend_current_and_run_next_task, wakeup_thread,
+};
+use crate::timer::remove_timer;
+
+pub const MAX_SIG: usize = 31;
+
+bitflags! {
+    /// A set of signals, where signal `n` is bit `n`
+    #[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+    pub struct SignalFlags: u32 {
+        const SIGHUP = 1 << 1;
+        const SIGINT = 1 << 2;
+        const SIGQUIT = 1 << 3;
+        const SIGILL = 1 << 4;
+        const SIGTRAP = 1 << 5;
+        const SIGABRT = 1 << 6;
+        const SIGBUS = 1 << 7;
+        const SIGFPE = 1 << 8;
+        const SIGKILL = 1 << 9;
+        const SIGUSR1 = 1 << 10;
+        const SIGSEGV = 1 << 11;
+        const SIGUSR2 = 1 << 12;
+        const SIGPIPE = 1 << 13;
+        const SIGALRM = 1 << 14;
+        const SIGTERM = 1 << 15;
+        const SIGSTKFLT = 1 << 16;
+        const SIGCHLD = 1 << 17;
+        const SIGCONT = 1 << 18;
+        const SIGSTOP = 1 << 19;
+        const SIGTSTP = 1 << 20;
+        const SIGTTIN = 1 << 21;
+        const SIGTTOU = 1 << 22;
+        const SIGURG = 1 << 23;
+        const SIGXCPU = 1 << 24;
+        const SIGXFSZ = 1 << 25;
+        const SIGVTALRM = 1 << 26;
+        const SIGPROF = 1 << 27;
+        const SIGWINCH = 1 << 28;
+        const SIGIO = 1 << 29;
+        const SIGPWR = 1 << 30;
+        const SIGSYS = 1 << 31;
+    }
+}
+
+impl SignalFlags {
+    /// Return the set of just signal `signum`, or `None` if there is no such
+    /// signal.
+    pub fn from_signum(signum: usize) -> Option<Self> {
+        (1..=MAX_SIG)
+            .contains(&signum)
+            .then(|| Self::from_bits_retain(1 << signum))
+    }
+
+    /// Return the number of the lowest signal in the set.
+    fn lowest_signum(&self) -> Option<usize> {
+        (!self.is_empty()).then(|| self.bits().trailing_zeros() as usize)
+    }
+}
+
+/// Signals that cannot be caught, ignored or blocked
+const UNBLOCKABLE: SignalFlags = SignalFlags::SIGKILL.union(SignalFlags::SIGSTOP);
+
+/// Signals that stop a process by default
+const STOP_SIGNALS: SignalFlags = SignalFlags::SIGSTOP
+    .union(SignalFlags::SIGTSTP)
+    .union(SignalFlags::SIGTTIN)
+    .union(SignalFlags::SIGTTOU);
+
+/// `handler` for the default action
+pub const SIG_DFL: usize = 0;
+/// `handler` to ignore the signal
+pub const SIG_IGN: usize = 1;
+
+/// How a process treats a signal, set through `sys_sigaction`
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SignalAction {
+    /// the handler's address, or `SIG_DFL` or `SIG_IGN`
+    pub handler: usize,
+    /// signals blocked while the handler runs, besides the signal itself
+    pub mask: SignalFlags,
+    /// where the handler returns to, which must call `sys_sigreturn`
+    pub restorer: usize,
+}
+
+impl SignalAction {
+    /// Return whether `signum` may have this action.
+    pub fn valid_for(&self, signum: usize) -> bool {
+        let signal = SignalFlags::from_bits_retain(1 << signum);
+        self.handler == SIG_DFL || !UNBLOCKABLE.contains(signal)
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum DefaultAction {
+    Terminate,
+    Ignore,
+    Stop,
+    Continue,
+}
+
+fn default_action(signal: SignalFlags) -> DefaultAction {
+    if signal.intersects(SignalFlags::SIGCHLD | SignalFlags::SIGURG | SignalFlags::SIGWINCH) {
+        DefaultAction::Ignore
+    } else if signal.intersects(STOP_SIGNALS) {
+        DefaultAction::Stop
+    } else if signal == SignalFlags::SIGCONT {
+        DefaultAction::Continue
+    } else {
+        DefaultAction::Terminate
+    }
+}
+
+/// Remove the signals that cannot be blocked from `mask`.
+pub fn sanitize_mask(mask: SignalFlags) -> SignalFlags {
+    mask - UNBLOCKABLE
+}
+
+/// Return whether `signal`, with the action `handler`, cuts short a blocking
+/// syscall: whether it is caught, or by default terminates or stops the
+/// process.
+fn interrupts(signal: SignalFlags, handler: usize) -> bool {
+    match handler {
+        SIG_DFL => matches!(
+            default_action(signal),
+            DefaultAction::Terminate | DefaultAction::Stop
+        ),
+        SIG_IGN => false,
+        _ => true,
+    }
+}
+
+/// Send `signal` to `process`, which acts on it once it returns to user
+/// space.
+///
+/// As on Linux, SIGCONT continues a stopped process at once and discards its
+/// pending stop signals, a stop signal discards a pending SIGCONT, and an
+/// ignored signal is discarded. Threads of the process that are sleeping are
+/// woken up, and so are those blocked on anything else if the signal
+/// interrupts blocking syscalls.
+pub fn send_signal(process: &Process, signal: SignalFlags) {
+    let signum = signal.lowest_signum().unwrap();
+    let mut inner = process.lock_inner();
+    if signal == SignalFlags::SIGCONT {
+        inner.signals -= STOP_SIGNALS;
+        inner.stopped = false;
+        inner.unreported_stop = None;
+    } else if STOP_SIGNALS.contains(signal) {
+        inner.signals -= SignalFlags::SIGCONT;
+    }
+    let handler = inner.signal_actions[signum].handler;
+    if handler == SIG_IGN {
+        return;
+    }
+    inner.signals |= signal;
+    // threads asleep in the timer queue wake up to see whether the signal
+    // cuts their sleep short; every wait queue leaves a thread woken this
+    // way by itself once it sees the signal pending
+    let interrupts = interrupts(signal, handler);
+    let threads: Vec<_> = inner.threads.iter().flatten().cloned().collect();
+    drop(inner);
+    for thread in threads {
+        if remove_timer(&thread) || interrupts {
+            wakeup_thread(thread);
+        }
+    }
+}
+
+/// Return whether the current process has a signal pending that a blocking
+/// syscall should give up waiting for: one that is not blocked, and is
+/// caught or by default terminates or stops the process.
+pub fn signal_pending() -> bool {
+    let handling_sig = current_thread().lock_inner().handling_sig;
+    let process = current_process();
+    let inner = process.lock_inner();
+    let pending = inner.signals - inner.blocked_signals(handling_sig);
+    (1..=MAX_SIG).any(|signum| {
+        let signal = SignalFlags::from_bits_retain(1 << signum);
+        pending.contains(signal) && interrupts(signal, inner.signal_actions[signum].handler)
+    })
+}
+
+/// Send `signal` to the current process for a fault it caused.
+///
+/// Returning to the faulting instruction without running a handler would
+/// only fault again, so if the signal is blocked or ignored, or a handler is
+/// running already, the process is killed at once.
+pub fn send_fault_signal(signal: SignalFlags) {
+    let signum = signal.lowest_signum().unwrap();
+    let handling_sig = current_thread().lock_inner().handling_sig;
+    let process = current_process();
+    let mut inner = process.lock_inner();
+    let handler = inner.signal_actions[signum].handler;
+    if handler == SIG_DFL
+        || handler == SIG_IGN
+        || handling_sig.is_some()
+        || inner.blocked_signals(handling_sig).contains(signal)
+    {
+        drop(inner);
+        drop(process);
+        warn!("{:?} in application, kernel killed it.", signal);
+        exit_current_process_and_run_next_task(-(signum as i32));
+    } else {
+        inner.signals |= signal;
+    }
+}
+
+/// Act on the pending signals of the current process, which is about to
+/// return to user space. This either sets the trap context up to run a
+/// handler, or returns to where the process was; it does not return if the
+/// process is killed, and waits here while it is stopped.
+pub fn handle_signals() {
+    loop {
+        let thread = current_thread();
+        let process = current_process();
+        let mut inner = process.lock_inner();
+        let mut thread_inner = thread.lock_inner();
+        let deliverable = if inner.stopped {
+            inner.signals & (SignalFlags::SIGKILL | SignalFlags::SIGCONT)
+        } else {
+            inner.signals - inner.blocked_signals(thread_inner.handling_sig)
+        };
+        let mut kill = None;
+        for signum in 1..=MAX_SIG {
+            let signal = SignalFlags::from_bits_retain(1 << signum);
+            if !deliverable.contains(signal) {
+                continue;
+            }
+            let action = inner.signal_actions[signum];
+            let caught = action.handler != SIG_DFL && action.handler != SIG_IGN;
+            if caught && thread_inner.handling_sig.is_some() {
+                continue;
+            }
+            inner.signals -= signal;
+            if signal == SignalFlags::SIGCONT {
+                inner.stopped = false;
+            }
+            match action.handler {
+                SIG_DFL => match default_action(signal) {
+                    DefaultAction::Terminate => {
+                        kill = Some(signum);
+                        break;
+                    }
+                    DefaultAction::Stop => {
+                        inner.stopped = true;
+                        inner.unreported_stop = Some(signum);
+                    }
+                    DefaultAction::Ignore | DefaultAction::Continue => {}
+                },
+                SIG_IGN => {}
+                handler => {
+                    let trap_cx = thread_inner.trap_cx();
+                    thread_inner.trap_cx_backup = Some(*trap_cx);
+                    thread_inner.handling_sig = Some(signum);
+                    trap_cx.sepc = handler;
+                    trap_cx.x[1] = action.restorer;
+                    trap_cx.x[10] = signum;
+                }
+            }
+        }
+        let stopped = inner.stopped;
+        drop(thread_inner);
+        drop(inner);
+        drop(process);
+        drop(thread);
+        if let Some(signum) = kill {
+            warn!("Killed by signal {}", signum);
+            exit_current_process_and_run_next_task(-(signum as i32));
+        }
+        if !stopped {
+            return;
+        }
+        suspend_current_and_run_next_task();
+    }
+}
+
+#[test_case]
+fn signal_test() {
+    assert_eq!(SignalFlags::from_signum(0), None);
+    assert_eq!(SignalFlags::from_signum(9), Some(SignalFlags::SIGKILL));
+    assert_eq!(SignalFlags::from_signum(MAX_SIG + 1), None);
+    assert_eq!(
+        (SignalFlags::SIGSEGV | SignalFlags::SIGTERM).lowest_signum(),
+        Some(11)
+    );
+    assert_eq!(default_action(SignalFlags::SIGCHLD), DefaultAction::Ignore);
+    assert_eq!(default_action(SignalFlags::SIGTSTP), DefaultAction::Stop);
+    assert_eq!(
+        default_action(SignalFlags::SIGINT),
+        DefaultAction::Terminate
+    );
+    assert_eq!(
+        sanitize_mask(SignalFlags::all()),
+        SignalFlags::all() - SignalFlags::SIGKILL - SignalFlags::SIGSTOP
+    );
+    let catch = SignalAction {
+        handler: 0x1000,
+        ..SignalAction::default()
+    };
+    assert!(catch.valid_for(10));
+    assert!(!catch.valid_for(9));
+    println!("signal_test passed!");
+}
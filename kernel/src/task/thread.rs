@@ -0,0 +1,194 @@
+//! Threads, the flows of control within a process
+//!
+//! All threads of a process share its address space, in which each has a
+//! user stack and a TrapContext of its own, placed by its tid, as well as a
+//! kernel stack of its own. Thread 0 is the main thread, created along with
+//! the process; when it exits, the whole process does, as it also does when
+//! its last thread exits.
+
+use alloc::sync::{Arc, Weak};
+
+use crate::{
+    config::{TRAP_CONTEXT_BASE, USER_STACK_SIZE, USER_STACK_TOP},
+    memory::{
+        address::{PhysPageNum, VirtAddr, PAGE_SIZE},
+        MapPermission, MemorySet,
+    },
+    sync::{SpinLock, SpinLockGuard},
+    trap::TrapContext,
+};
+
+use super::{
+    kernel_stack::KernelStack,
+    process::{Process, ProcessInner},
+    TaskContext,
+};
+
+pub struct Thread {
+    pub process: Weak<Process>,
+    pub tid: usize,
+    pub kernel_stack: KernelStack,
+    inner: SpinLock<ThreadInner>,
+}
+
+pub struct ThreadInner {
+    /// the user stack and TrapContext, released when the thread exits
+    pub res: Option<ThreadUserRes>,
+    pub trap_cx_ppn: PhysPageNum,
+    pub task_cx: TaskContext,
+    pub status: ThreadStatus,
+    /// set when the thread exits, for `sys_waittid`
+    pub exit_code: Option<i32>,
+    /// the signal whose handler is running on this thread
+    pub handling_sig: Option<usize>,
+    /// the trap context from before the running handler
+    pub trap_cx_backup: Option<TrapContext>,
+    /// when the thread's time was last charged to its process
+    pub charged_at: u64,
+}
+
+impl ThreadInner {
+    pub fn trap_cx(&self) -> &'static mut TrapContext {
+        unsafe { self.trap_cx_ppn.get_mut() }
+    }
+}
+
+impl Thread {
+    /// Create thread `tid`, already allocated from `process_inner`, of
+    /// `process`, mapping its user stack and TrapContext unless they are
+    /// mapped already. Its TrapContext is left for the caller to set up.
+    ///
+    /// Fails, leaving nothing mapped, if they or the kernel stack cannot be
+    /// mapped.
+    pub fn new(
+        process: &Arc<Process>,
+        process_inner: &mut ProcessInner,
+        tid: usize,
+        map_user_res: bool,
+    ) -> Result<Self, &'static str> {
+        let kernel_stack = KernelStack::new()?;
+        if map_user_res {
+            ThreadUserRes::map(tid, &mut process_inner.memory_set)?;
+        }
+        let res = ThreadUserRes {
+            tid,
+            process: Arc::downgrade(process),
+        };
+        let trap_cx_ppn = res.trap_cx_ppn(&process_inner.memory_set);
+        let kernel_stack_top = kernel_stack.top();
+        Ok(Self {
+            process: Arc::downgrade(process),
+            tid,
+            kernel_stack,
+            inner: SpinLock::new(
+                "Thread::inner",
+                ThreadInner {
+                    res: Some(res),
+                    trap_cx_ppn,
+                    task_cx: TaskContext::goto_trap_return(kernel_stack_top),
+                    status: ThreadStatus::Ready,
+                    exit_code: None,
+                    handling_sig: None,
+                    trap_cx_backup: None,
+                    charged_at: 0,
+                },
+            ),
+        })
+    }
+
+    #[track_caller]
+    pub fn lock_inner(&self) -> SpinLockGuard<'_, ThreadInner> {
+        self.inner.lock()
+    }
+
+    /// Return where the TrapContext of this thread is in user space.
+    pub fn trap_cx_va(&self) -> usize {
+        trap_cx_va(self.tid)
+    }
+
+    /// Return where the user stack of this thread ends.
+    pub fn ustack_top(&self) -> usize {
+        ustack_top(self.tid)
+    }
+}
+
+fn trap_cx_va(tid: usize) -> usize {
+    TRAP_CONTEXT_BASE - tid * PAGE_SIZE
+}
+
+fn ustack_top(tid: usize) -> usize {
+    USER_STACK_TOP - tid * (USER_STACK_SIZE + PAGE_SIZE)
+}
+
+/// The user stack and TrapContext mapped for a thread in its process, which
+/// are unmapped when this is dropped
+///
+/// This must not be dropped while the process is locked. The tid itself is
+/// only released when the thread is collected, so that it is not reused
+/// before its exit code is.
+pub struct ThreadUserRes {
+    tid: usize,
+    process: Weak<Process>,
+}
+
+impl ThreadUserRes {
+    /// Map the user stack and TrapContext of thread `tid` in `memory_set`,
+    /// failing, with neither mapped, if their place is taken or memory runs
+    /// out.
+    pub fn map(tid: usize, memory_set: &mut MemorySet) -> Result<(), &'static str> {
+        let ustack_top = ustack_top(tid);
+        memory_set.insert_framed_area(
+            (ustack_top - USER_STACK_SIZE).into(),
+            ustack_top.into(),
+            MapPermission::R | MapPermission::W | MapPermission::U,
+        )?;
+        let trap_cx_va = trap_cx_va(tid);
+        let result = memory_set.insert_framed_area(
+            trap_cx_va.into(),
+            (trap_cx_va + PAGE_SIZE).into(),
+            MapPermission::R | MapPermission::W,
+        );
+        if result.is_err() {
+            let _ =
+                memory_set.remove_area((ustack_top - USER_STACK_SIZE).into(), ustack_top.into());
+        }
+        result
+    }
+
+    /// Unmap the user stack and TrapContext of thread `tid` from
+    /// `memory_set`, where they may be gone already with the whole address
+    /// space.
+    pub fn unmap(tid: usize, memory_set: &mut MemorySet) {
+        let ustack_top = ustack_top(tid);
+        let _ = memory_set.remove_area((ustack_top - USER_STACK_SIZE).into(), ustack_top.into());
+        let trap_cx_va = trap_cx_va(tid);
+        let _ = memory_set.remove_area(trap_cx_va.into(), (trap_cx_va + PAGE_SIZE).into());
+    }
+
+    pub fn trap_cx_ppn(&self, memory_set: &MemorySet) -> PhysPageNum {
+        memory_set
+            .translate(VirtAddr::from(trap_cx_va(self.tid)).page_number_floor())
+            .unwrap()
+            .ppn()
+    }
+}
+
+impl Drop for ThreadUserRes {
+    fn drop(&mut self) {
+        let Some(process) = self.process.upgrade() else {
+            return;
+        };
+        Self::unmap(self.tid, &mut process.lock_inner().memory_set);
+    }
+}
+
+#[derive(Debug, PartialEq)]
+pub enum ThreadStatus {
+    Ready,
+    Running,
+    /// parked on a wait queue, off the ready queue
+    Blocked,
+    /// Exited but not yet collected through `sys_waittid`, or with the main
+    /// thread, through `sys_waitpid` on the process
+    Exited,
+}
@@ -2,13 +2,135 @@
 
 use crate::config::{CLOCK_FREQ, TICKS_PER_SEC};
 use crate::sbi::set_timer;
+use crate::sync::SpinLock;
+use crate::task::{wakeup_thread, Thread};
+use alloc::{collections::BinaryHeap, sync::Arc};
+use core::cmp::Ordering;
 use riscv::register::time;
 
 pub fn read() -> u64 {
     time::read() as u64
 }
 
+/// A span of time as user space passes it, like Linux's `struct timespec`
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+impl TimeSpec {
+    pub fn from_ticks(ticks: u64) -> Self {
+        Self {
+            sec: (ticks / CLOCK_FREQ) as usize,
+            nsec: (ticks % CLOCK_FREQ * 1_000_000_000 / CLOCK_FREQ) as usize,
+        }
+    }
+
+    /// Return the span in timer ticks, or `None` if `nsec` is out of range.
+    pub fn ticks(&self) -> Option<u64> {
+        if self.nsec >= 1_000_000_000 {
+            return None;
+        }
+        Some(
+            (self.sec as u64).saturating_mul(CLOCK_FREQ)
+                + self.nsec as u64 * CLOCK_FREQ / 1_000_000_000,
+        )
+    }
+}
+
+/// A span of time as user space is given it, like Linux's `struct timeval`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+impl TimeVal {
+    pub fn from_ticks(ticks: u64) -> Self {
+        Self {
+            sec: (ticks / CLOCK_FREQ) as usize,
+            usec: ticks_to_us(ticks % CLOCK_FREQ) as usize,
+        }
+    }
+}
+
+pub fn ticks_to_us(ticks: u64) -> u64 {
+    ticks * 1_000_000 / CLOCK_FREQ
+}
+
 /// set the next timer interrupt
 pub fn set_next_trigger() {
     set_timer((read() + CLOCK_FREQ / TICKS_PER_SEC) as u64);
 }
+
+/// A thread blocked until `deadline`, a `read()` value
+struct Timer {
+    deadline: u64,
+    thread: Arc<Thread>,
+}
+
+impl PartialEq for Timer {
+    fn eq(&self, other: &Self) -> bool {
+        self.deadline == other.deadline
+    }
+}
+
+impl Eq for Timer {}
+
+impl PartialOrd for Timer {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Timer {
+    /// The earliest deadline is the greatest, so that it is on top of the
+    /// heap.
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.deadline.cmp(&self.deadline)
+    }
+}
+
+/// The timer queue, a min-heap of the blocked threads with a deadline
+static TIMERS: SpinLock<BinaryHeap<Timer>> = SpinLock::new("TIMERS", BinaryHeap::new());
+
+/// Wake up the blocked `thread` once `deadline` has passed, unless the timer
+/// is removed first. The caller blocks the thread after this.
+pub fn add_timer(deadline: u64, thread: Arc<Thread>) {
+    TIMERS.lock().push(Timer { deadline, thread });
+}
+
+/// Remove the timers of `thread`, which is woken up otherwise or has exited,
+/// returning whether it had any.
+pub fn remove_timer(thread: &Arc<Thread>) -> bool {
+    let mut timers = TIMERS.lock();
+    let len = timers.len();
+    timers.retain(|timer| !Arc::ptr_eq(&timer.thread, thread));
+    timers.len() < len
+}
+
+/// Wake up the threads whose deadlines have passed. This is called on every
+/// timer interrupt, and while the hart has nothing else to run, so without
+/// the `time-sharing` feature, deadlines only pass while it idles.
+pub fn check_timers() {
+    let now = read();
+    loop {
+        let mut timers = TIMERS.lock();
+        if !timers.peek().is_some_and(|timer| timer.deadline <= now) {
+            break;
+        }
+        let timer = timers.pop().unwrap();
+        drop(timers);
+        // a thread whose process has exited since stays asleep
+        wakeup_thread(timer.thread);
+    }
+}
+
+/// Return whether any thread is blocked with a deadline, and so will become
+/// runnable again without another thread waking it.
+pub fn has_timers() -> bool {
+    !TIMERS.lock().is_empty()
+}
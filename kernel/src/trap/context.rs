@@ -6,6 +6,7 @@ use super::trap_handler;
 
 /// Trap Context
 #[repr(C)]
+#[derive(Clone, Copy)]
 pub struct TrapContext {
     /// general regs[0..31]
     pub x: [usize; 32],
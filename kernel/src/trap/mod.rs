@@ -15,17 +15,20 @@
 mod context;
 
 use crate::{
-    config::{TRAMPOLINE, TRAP_CONTEXT},
+    config::TRAMPOLINE,
+    fs::poll_console,
+    memory::{address::VirtAddr, MapPermission},
     syscall::syscall,
     task::{
-        current_trap_cx, current_user_token, exit_current_and_run_next_task,
-        suspend_current_and_run_next_task,
+        charge_kernel_time, charge_user_time, current_process, current_thread, current_trap_cx,
+        current_user_token, handle_signals, preempt_current_and_run_next_task, send_fault_signal,
+        SignalFlags,
     },
-    timer::set_next_trigger,
+    timer::{check_timers, set_next_trigger},
 };
 pub use context::TrapContext;
 use core::arch::{asm, global_asm};
-use log::{trace, warn};
+use log::trace;
 use riscv::register::{
     scause::{self, Exception, Interrupt, Trap},
     sie, stval,
@@ -75,32 +78,53 @@ fn trap_from_kernel() {
 /// handle an interrupt, exception, or system call from user space
 pub fn trap_handler() -> ! {
     set_kernel_trap_entry();
+    charge_user_time();
     let cx = current_trap_cx();
     let scause = scause::read(); // get trap cause
     let stval = stval::read(); // get extra value
     match scause.cause() {
         Trap::Exception(Exception::UserEnvCall) => {
             cx.sepc += 4;
-            let ret = syscall(cx.x[17], [cx.x[10], cx.x[11], cx.x[12]]) as usize;
+            let ret = syscall(
+                cx.x[17],
+                [cx.x[10], cx.x[11], cx.x[12], cx.x[13], cx.x[14], cx.x[15]],
+            ) as usize;
             // reacquire the TrapContext because syscall may change it
             let cx = current_trap_cx();
             cx.x[10] = ret;
         }
+        Trap::Exception(Exception::StorePageFault)
+        | Trap::Exception(Exception::LoadPageFault)
+        | Trap::Exception(Exception::InstructionPageFault) => {
+            let perm = match scause.cause() {
+                Trap::Exception(Exception::StorePageFault) => MapPermission::W,
+                Trap::Exception(Exception::LoadPageFault) => MapPermission::R,
+                _ => MapPermission::X,
+            };
+            let vpn = VirtAddr::from(stval).page_number_floor();
+            if !current_process()
+                .lock_inner()
+                .memory_set
+                .handle_page_fault(vpn, perm)
+            {
+                send_fault_signal(SignalFlags::SIGSEGV);
+            }
+        }
         Trap::Exception(Exception::StoreFault)
-        | Trap::Exception(Exception::StorePageFault)
         | Trap::Exception(Exception::LoadFault)
-        | Trap::Exception(Exception::LoadPageFault) => {
-            warn!("PageFault in application, kernel killed it.");
-            exit_current_and_run_next_task();
+        | Trap::Exception(Exception::InstructionFault) => {
+            send_fault_signal(SignalFlags::SIGSEGV);
         }
         Trap::Exception(Exception::IllegalInstruction) => {
-            warn!("IllegalInstruction in application, kernel killed it.");
-            exit_current_and_run_next_task();
+            send_fault_signal(SignalFlags::SIGILL);
         }
         Trap::Interrupt(Interrupt::SupervisorTimer) => {
             trace!("Supervisor timer triggered");
             set_next_trigger();
-            suspend_current_and_run_next_task();
+            // a job that never reads the console can still be interrupted
+            poll_console();
+            check_timers();
+            preempt_current_and_run_next_task();
         }
         _ => {
             panic!(
@@ -110,12 +134,14 @@ pub fn trap_handler() -> ! {
             );
         }
     }
+    handle_signals();
     trap_return();
 }
 
 pub fn trap_return() -> ! {
+    charge_kernel_time();
     set_user_trap_entry();
-    let trap_cx_ptr = TRAP_CONTEXT;
+    let trap_cx_ptr = current_thread().trap_cx_va();
     let user_satp = current_user_token();
     let restore_va = __restore as usize - __alltraps as usize + TRAMPOLINE;
     unsafe {
@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::syscall::{sys_close, sys_open, sys_read, sys_write, O_RDONLY};
+
+/// `cat <path>...`, such as `cat /dev/lockstat` for the kernel lock hold times
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("usage: cat <path>...");
+        return 1;
+    }
+    let mut exit_code = 0;
+    let mut buf = [0u8; 256];
+    for path in &argv[1..] {
+        let fd = sys_open(&format!("{}\0", path), O_RDONLY);
+        if fd < 0 {
+            println!("cat: cannot open {}", path);
+            exit_code = 1;
+            continue;
+        }
+        loop {
+            let len = sys_read(fd as usize, &mut buf);
+            if len <= 0 {
+                break;
+            }
+            sys_write(1, &buf[..len as usize]);
+        }
+        sys_close(fd as usize);
+    }
+    exit_code
+}
@@ -0,0 +1,109 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{
+    sys_close, sys_fstat, sys_getdents64, sys_linkat, sys_mkdirat, sys_open, sys_read,
+    sys_renameat2, sys_unlinkat, sys_write, Dirents, Stat, AT_FDCWD, AT_REMOVEDIR, DT_DIR, DT_REG,
+    O_CREATE, O_RDONLY, O_WRONLY, S_IFCHR, S_IFDIR, S_IFMT, S_IFREG,
+};
+
+fn fstat(fd: usize) -> Stat {
+    let mut stat = Stat::default();
+    assert_eq!(sys_fstat(fd, &mut stat), 0);
+    stat
+}
+
+/// Run the checks in the directory `dir`, which must not exist yet.
+fn test_in(dir: &str, file: &str, link: &str, moved: &str) {
+    assert_eq!(sys_mkdirat(AT_FDCWD, dir, 0o755), 0);
+    assert_eq!(sys_mkdirat(AT_FDCWD, dir, 0o755), -1);
+    let fd = sys_open(file, O_CREATE | O_WRONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_write(fd as usize, b"data"), 4);
+    let stat = fstat(fd as usize);
+    assert_eq!(stat.mode & S_IFMT, S_IFREG);
+    assert_eq!((stat.size, stat.nlink), (4, 1));
+    sys_close(fd as usize);
+
+    // a second name for the same file
+    assert_eq!(sys_linkat(AT_FDCWD, file, AT_FDCWD, link, 0), 0);
+    assert_eq!(sys_linkat(AT_FDCWD, file, AT_FDCWD, link, 0), -1);
+    assert_eq!(sys_linkat(AT_FDCWD, dir, AT_FDCWD, moved, 0), -1);
+    let fd = sys_open(link, O_RDONLY) as usize;
+    let linked = fstat(fd);
+    assert_eq!((linked.ino, linked.nlink), (stat.ino, 2));
+    sys_close(fd);
+
+    // the directory lists both, and cannot be read or removed yet
+    let fd = sys_open(dir, O_RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(fstat(fd).mode & S_IFMT, S_IFDIR);
+    assert_eq!(sys_read(fd, &mut [0u8; 8]), -1);
+    let mut buf = [0u8; 256];
+    let len = sys_getdents64(fd, &mut buf);
+    assert!(len > 0);
+    let mut count = 0;
+    for (ino, d_type, _) in Dirents::new(&buf[..len as usize]) {
+        assert_eq!((ino, d_type), (stat.ino, DT_REG));
+        count += 1;
+    }
+    assert_eq!(count, 2);
+    assert_eq!(sys_getdents64(fd, &mut buf), 0);
+    sys_close(fd);
+    assert_eq!(sys_unlinkat(AT_FDCWD, dir, AT_REMOVEDIR), -1);
+    assert_eq!(sys_unlinkat(AT_FDCWD, dir, 0), -1);
+    assert_eq!(sys_unlinkat(AT_FDCWD, file, AT_REMOVEDIR), -1);
+
+    // renaming over the other name leaves one link
+    assert_eq!(sys_renameat2(AT_FDCWD, file, AT_FDCWD, link, 0), 0);
+    assert_eq!(sys_open(file, O_RDONLY), -1);
+    assert_eq!(sys_renameat2(AT_FDCWD, link, AT_FDCWD, moved, 0), 0);
+    let fd = sys_open(moved, O_RDONLY) as usize;
+    assert_eq!(fstat(fd).nlink, 1);
+    assert_eq!(sys_read(fd, &mut buf), 4);
+    sys_close(fd);
+    assert_eq!(sys_unlinkat(AT_FDCWD, moved, 0), 0);
+    assert_eq!(sys_unlinkat(AT_FDCWD, moved, 0), -1);
+    assert_eq!(sys_unlinkat(AT_FDCWD, dir, AT_REMOVEDIR), 0);
+    assert_eq!(sys_open(dir, O_RDONLY), -1);
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    // on the root filesystem, and on the tmpfs init mounts at /tmp
+    test_in("dt_dir\0", "dt_dir/a\0", "dt_dir/b\0", "dt_moved\0");
+    test_in("/tmp/d\0", "/tmp/d/a\0", "/tmp/d/b\0", "/tmp/c\0");
+
+    // nothing moves across filesystems or onto mount points
+    let fd = sys_open("/tmp/x\0", O_CREATE | O_WRONLY);
+    sys_close(fd as usize);
+    assert_eq!(sys_linkat(AT_FDCWD, "/tmp/x\0", AT_FDCWD, "/x\0", 0), -1);
+    assert_eq!(sys_renameat2(AT_FDCWD, "/tmp/x\0", AT_FDCWD, "/x\0", 0), -1);
+    assert_eq!(sys_renameat2(AT_FDCWD, "/tmp\0", AT_FDCWD, "/t\0", 0), -1);
+    assert_eq!(sys_unlinkat(AT_FDCWD, "/tmp\0", AT_REMOVEDIR), -1);
+    assert_eq!(sys_unlinkat(AT_FDCWD, "/tmp/x\0", 0), 0);
+    // a directory cannot move below itself
+    assert_eq!(sys_mkdirat(AT_FDCWD, "/tmp/p\0", 0o755), 0);
+    assert_eq!(
+        sys_renameat2(AT_FDCWD, "/tmp/p\0", AT_FDCWD, "/tmp/p/q\0", 0),
+        -1
+    );
+    assert_eq!(sys_unlinkat(AT_FDCWD, "/tmp/p\0", AT_REMOVEDIR), 0);
+
+    // only AT_FDCWD is a dirfd for relative paths
+    assert_eq!(sys_mkdirat(3, "dt_dir\0", 0o755), -1);
+    assert_eq!(fstat(0).mode & S_IFMT, S_IFCHR);
+    let fd = sys_open("/\0", O_RDONLY) as usize;
+    let mut buf = [0u8; 64];
+    let len = sys_getdents64(fd, &mut buf);
+    assert!(len > 0);
+    assert!(Dirents::new(&buf[..len as usize])
+        .all(|(_, d_type, _)| d_type == DT_REG || d_type == DT_DIR));
+    sys_close(fd);
+    println!("dir_test passed!");
+    0
+}
@@ -0,0 +1,49 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::vec;
+use core::ptr::null;
+use user_lib::syscall::{
+    sys_close, sys_exec, sys_open, sys_read, sys_write, O_CREATE, O_RDONLY, O_TRUNC, O_WRONLY,
+};
+
+/// Write `data` to `/tmp/exec_test` and try to exec it.
+fn exec_bytes(data: &[u8]) -> isize {
+    let fd = sys_open("/tmp/exec_test\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_write(fd as usize, data), data.len() as isize);
+    sys_close(fd as usize);
+    sys_exec("/tmp/exec_test\0", &[null()], &[null()])
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut elf = [0u8; 4096];
+    let fd = sys_open("/hello\0", O_RDONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_read(fd as usize, &mut elf), elf.len() as isize);
+    sys_close(fd as usize);
+
+    // each of these is refused, and this program goes on running
+    assert_eq!(exec_bytes(b"\x7fELF"), -1);
+    assert_eq!(exec_bytes(&elf[..64]), -1);
+    assert_eq!(exec_bytes(&elf[..200]), -1);
+    // program headers claimed far past the end of the file
+    let mut bad_ph = elf;
+    bad_ph[32..40].copy_from_slice(&u64::MAX.to_le_bytes());
+    assert_eq!(exec_bytes(&bad_ph), -1);
+
+    // arguments that would not leave the program enough stack
+    let mut long_arg = vec![b'a'; 8192];
+    *long_arg.last_mut().unwrap() = 0;
+    assert_eq!(
+        sys_exec("/hello\0", &[long_arg.as_ptr(), null()], &[null()]),
+        -1
+    );
+    println!("exec_test passed!");
+    0
+}
@@ -0,0 +1,24 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{sys_read, sys_write};
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut buf = [0u8; 4];
+    // stderr goes to the console too
+    assert_eq!(sys_write(2, b"fd_test: stderr\n"), 16);
+    // unopened fds and the wrong direction fail instead of panicking
+    assert_eq!(sys_write(3, b"x"), -1);
+    assert_eq!(sys_write(usize::MAX, b"x"), -1);
+    assert_eq!(sys_read(42, &mut buf), -1);
+    assert_eq!(sys_read(1, &mut buf), -1);
+    assert_eq!(sys_write(0, b"x"), -1);
+    // reading nothing returns at once
+    assert_eq!(sys_read(0, &mut buf[..0]), 0);
+    println!("fd_test passed!");
+    0
+}
@@ -0,0 +1,54 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{
+    sys_close, sys_open, sys_read, sys_write, O_CREATE, O_RDONLY, O_TRUNC, O_WRONLY,
+};
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let test_str = b"Hello, world!";
+    let fd = sys_open("file_test_a\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(sys_read(fd, &mut [0u8; 4]), -1);
+    // more than a block, written in pieces
+    for _ in 0..100 {
+        assert_eq!(sys_write(fd, test_str), test_str.len() as isize);
+    }
+    assert_eq!(sys_close(fd), 0);
+
+    let fd = sys_open("file_test_a\0", O_RDONLY);
+    assert!(fd > 0);
+    let fd = fd as usize;
+    assert_eq!(sys_write(fd, test_str), -1);
+    let mut buffer = [0u8; 100];
+    let mut total = 0;
+    loop {
+        let read = sys_read(fd, &mut buffer[..test_str.len()]);
+        assert!(read >= 0);
+        if read == 0 {
+            break;
+        }
+        assert_eq!(&buffer[..read as usize], test_str);
+        total += read as usize;
+    }
+    assert_eq!(total, 100 * test_str.len());
+    sys_close(fd);
+
+    // truncating empties the file
+    let fd = sys_open("file_test_a\0", O_WRONLY | O_TRUNC);
+    assert!(fd > 0);
+    sys_close(fd as usize);
+    let fd = sys_open("file_test_a\0", O_RDONLY) as usize;
+    assert_eq!(sys_read(fd, &mut buffer), 0);
+    sys_close(fd);
+
+    // missing files are only created on request
+    assert_eq!(sys_open("file_test_missing\0", O_RDONLY), -1);
+    println!("file_test passed!");
+    0
+}
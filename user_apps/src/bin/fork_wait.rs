@@ -0,0 +1,37 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{sys_exit, sys_fork, sys_yield},
+    wait, waitpid,
+};
+
+const CHILDREN: usize = 5;
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut pids = [0isize; CHILDREN];
+    for (i, pid) in pids.iter_mut().enumerate() {
+        *pid = sys_fork();
+        if *pid == 0 {
+            // let later children finish first, so reaping in order blocks
+            for _ in 0..(CHILDREN - i) * 2 {
+                sys_yield();
+            }
+            println!("child {} exits", i);
+            sys_exit(100 + i as i32);
+        }
+    }
+    let mut exit_code = 0;
+    for (i, &pid) in pids.iter().enumerate() {
+        assert_eq!(waitpid(pid, &mut exit_code), pid);
+        assert_eq!(exit_code, 100 + i as i32);
+        println!("reaped child {} (pid {}), exit code {}", i, pid, exit_code);
+    }
+    assert_eq!(wait(&mut exit_code), -1);
+    println!("fork_wait passed!");
+    0
+}
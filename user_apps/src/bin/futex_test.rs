@@ -0,0 +1,117 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+use user_lib::{
+    sync::FutexMutex,
+    syscall::{
+        sys_exit, sys_fork, sys_futex, sys_get_time, sys_kill, sys_sleep_ms, sys_thread_create,
+        sys_yield, TimeSpec, FUTEX_WAIT, FUTEX_WAKE, SIGKILL,
+    },
+    waitpid, waittid,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 50;
+
+static LOCK: FutexMutex = FutexMutex::new();
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static FLAG: AtomicU32 = AtomicU32::new(0);
+
+/// Add one to the counter `ROUNDS` times under `LOCK`, with a yield between
+/// reading and writing that loses updates without it.
+fn add(_arg: usize) -> ! {
+    for _ in 0..ROUNDS {
+        LOCK.lock();
+        let value = COUNTER.load(Ordering::Relaxed);
+        sys_yield();
+        COUNTER.store(value + 1, Ordering::Relaxed);
+        LOCK.unlock();
+    }
+    sys_exit(0);
+    unreachable!()
+}
+
+/// Sleep until `FLAG` is set.
+fn wait_flag(_arg: usize) -> ! {
+    while FLAG.load(Ordering::Acquire) == 0 {
+        sys_futex(&FLAG, FUTEX_WAIT, 0, None);
+    }
+    sys_exit(0);
+    unreachable!()
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    // a word that has changed already is not waited on
+    let word = AtomicU32::new(1);
+    assert_eq!(sys_futex(&word, FUTEX_WAIT, 0, None), -1);
+    assert_eq!(sys_futex(&word, FUTEX_WAKE, 1, None), 0);
+
+    // nobody wakes this one, so it times out
+    let timeout = TimeSpec {
+        sec: 0,
+        nsec: 20_000_000,
+    };
+    let start = sys_get_time();
+    assert_eq!(sys_futex(&word, FUTEX_WAIT, 1, Some(&timeout)), -1);
+    assert!(sys_get_time() > start);
+    let invalid = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(sys_futex(&word, FUTEX_WAIT, 1, Some(&invalid)), -1);
+
+    // a sleeper is woken once the flag is set
+    let tid = sys_thread_create(wait_flag, 0) as usize;
+    for _ in 0..10 {
+        sys_yield();
+    }
+    FLAG.store(1, Ordering::Release);
+    assert_eq!(sys_futex(&FLAG, FUTEX_WAKE, u32::MAX, None), 1);
+    let mut exit_code = 0;
+    assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+
+    // a fork makes the page of the flag copy-on-write, and setting it then
+    // moves it to a new frame, which must not lose the sleeper
+    FLAG.store(0, Ordering::Release);
+    let tid = sys_thread_create(wait_flag, 0) as usize;
+    for _ in 0..10 {
+        sys_yield();
+    }
+    let pid = sys_fork();
+    if pid == 0 {
+        sys_exit(0);
+    }
+    FLAG.store(1, Ordering::Release);
+    assert_eq!(sys_futex(&FLAG, FUTEX_WAKE, u32::MAX, None), 1);
+    assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+
+    // a process waiting on a futex with no timeout can still be killed
+    let pid = sys_fork();
+    if pid == 0 {
+        let word = AtomicU32::new(0);
+        sys_futex(&word, FUTEX_WAIT, 0, None);
+        sys_exit(1);
+    }
+    sys_sleep_ms(50);
+    assert_eq!(sys_kill(pid, SIGKILL), 0);
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+
+    // the lock keeps the counter exact under contention
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = sys_thread_create(add, 0) as usize;
+    }
+    for &tid in tids.iter() {
+        assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+    }
+    assert_eq!(COUNTER.load(Ordering::Relaxed), THREADS * ROUNDS);
+    println!("futex_test passed!");
+    0
+}
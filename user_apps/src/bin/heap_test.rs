@@ -0,0 +1,47 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{alloc::alloc, string::String, vec::Vec};
+use core::alloc::Layout;
+use user_lib::syscall::sys_sbrk;
+
+const PAGE_SIZE: usize = 0x1000;
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    // the heap is growable and shrinkable by hand
+    let brk = sys_sbrk(0);
+    assert!(brk > 0);
+    assert_eq!(sys_sbrk(PAGE_SIZE as i32 * 2), brk);
+    let page = unsafe { core::slice::from_raw_parts_mut(brk as *mut u8, PAGE_SIZE * 2) };
+    page.fill(0xaa);
+    assert_eq!(sys_sbrk(-(PAGE_SIZE as i32)), brk + PAGE_SIZE as isize * 2);
+    assert_eq!(sys_sbrk(-(PAGE_SIZE as i32)), brk + PAGE_SIZE as isize);
+    assert_eq!(sys_sbrk(-1), -1);
+    assert_eq!(sys_sbrk(0), brk);
+
+    // and backs the global allocator
+    let mut v: Vec<usize> = Vec::new();
+    for i in 0..10_000 {
+        v.push(i);
+    }
+    assert!(v.iter().enumerate().all(|(i, &x)| i == x));
+    let mut s = String::new();
+    for word in ["heap", "grows", "through", "sbrk"] {
+        s.push_str(word);
+        s.push(' ');
+    }
+    println!("{}", s.trim_end());
+
+    // a block the break cannot move by fails without touching it
+    let brk = sys_sbrk(0);
+    let layout = Layout::from_size_align(1 << 32, PAGE_SIZE).unwrap();
+    assert!(unsafe { alloc(layout) }.is_null());
+    assert_eq!(sys_sbrk(0), brk);
+    println!("heap_test passed!");
+    0
+}
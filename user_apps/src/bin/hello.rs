@@ -4,8 +4,16 @@
 #[macro_use]
 extern crate user_lib;
 
+use user_lib::getenv;
+
 #[no_mangle]
-fn main(_argc: usize, _argv: &[&str]) -> i32 {
+fn main(argc: usize, argv: &[&str]) -> i32 {
     println!("Hello, world!");
+    for (i, arg) in argv.iter().enumerate().take(argc) {
+        println!("argv[{}] = {}", i, arg);
+    }
+    if let Some(name) = getenv("NAME") {
+        println!("Hello, {}!", name);
+    }
     0
 }
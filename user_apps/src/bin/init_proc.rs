@@ -1,18 +1,48 @@
 #![no_std]
 #![no_main]
 
-use user_lib::syscall::{sys_exec, sys_fork};
+use core::ptr::null;
+
+use user_lib::{
+    syscall::{sys_exec, sys_fork, sys_mount, sys_yield},
+    wait,
+};
 
 #[macro_use]
 extern crate user_lib;
 
 #[no_mangle]
 fn main(_argc: usize, _argv: &[&str]) -> i32 {
-    if sys_fork() == 0 {
-        println!("I am child");
-        sys_exec("1\0".as_ptr());
-    } else {
-        println!("I am parent");
+    if sys_mount("none\0", "/dev\0", "devfs\0") != 0 {
+        println!("[initproc] failed to mount /dev");
+    }
+    if sys_mount("none\0", "/tmp\0", "tmpfs\0") != 0 {
+        println!("[initproc] failed to mount /tmp");
+    }
+    let shell = sys_fork();
+    if shell == 0 {
+        sys_exec(
+            "user_shell\0",
+            &["user_shell\0".as_ptr(), null()],
+            &[null()],
+        );
+        println!("[initproc] failed to start the shell");
+        return -1;
+    }
+    loop {
+        let mut exit_code: i32 = 0;
+        let pid = wait(&mut exit_code);
+        if pid == -1 {
+            sys_yield();
+            continue;
+        }
+        // the system shuts down once the shell is gone
+        if pid == shell {
+            return exit_code;
+        }
+        println!(
+            "[init_proc] Released a zombie process, pid={}, exit_code={}",
+            pid, exit_code
+        );
     }
-    0
 }
@@ -0,0 +1,69 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{
+        stop_signal, sys_fork, sys_getpgid, sys_kill, sys_setpgid, sys_waitpid, sys_yield, SIGCONT,
+        SIGINT, SIGTSTP, WUNTRACED,
+    },
+    tcgetpgrp, tcsetpgrp, waitpid,
+};
+
+/// Wait for the child `pid` to exit or stop, returning its status.
+fn wait_untraced(pid: isize) -> i32 {
+    let mut status = 0;
+    loop {
+        match sys_waitpid(pid, &mut status, WUNTRACED) {
+            -2 => {
+                sys_yield();
+            }
+            found => {
+                assert_eq!(found, pid);
+                return status;
+            }
+        }
+    }
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let pgid = sys_getpgid(0);
+    assert!(pgid > 0);
+
+    // a child starts in its parent's group and can get one of its own
+    let child = sys_fork();
+    if child == 0 {
+        loop {
+            sys_yield();
+        }
+    }
+    assert_eq!(sys_getpgid(child as usize), pgid);
+    assert_eq!(sys_setpgid(child as usize, 0), 0);
+    assert_eq!(sys_getpgid(child as usize), child);
+    // groups that do not exist cannot be joined
+    assert_eq!(sys_setpgid(0, 12345), -1);
+
+    // the whole group is signalled, and a stop is reported once
+    assert_eq!(sys_kill(-child, SIGTSTP), 0);
+    assert_eq!(stop_signal(wait_untraced(child)), Some(SIGTSTP));
+    let mut status = 0;
+    assert_eq!(sys_waitpid(child, &mut status, WUNTRACED), -2);
+    assert_eq!(sys_kill(-child, SIGCONT), 0);
+    assert_eq!(sys_kill(-child, SIGINT), 0);
+    assert_eq!(waitpid(child, &mut status), child);
+    assert_eq!(status, -(SIGINT as i32));
+    assert_eq!(sys_kill(-child, 0), -1);
+
+    // the console can be handed to a group and back
+    let foreground = tcgetpgrp(0);
+    assert_eq!(tcsetpgrp(0, pgid as usize), 0);
+    assert_eq!(tcgetpgrp(0), pgid);
+    assert_eq!(tcsetpgrp(0, foreground as usize), 0);
+    // only the console is a terminal
+    assert_eq!(tcgetpgrp(3), -1);
+    println!("job_test passed!");
+    0
+}
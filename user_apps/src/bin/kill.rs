@@ -0,0 +1,32 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{sys_kill, SIGTERM};
+
+/// `kill [-<signum>] <pid>...`, sending SIGTERM by default. A negative pid
+/// names a process group.
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    let (signum, pids) = match argv.get(1).and_then(|arg| arg.strip_prefix('-')) {
+        Some(signum) => (signum.parse().ok(), &argv[2..]),
+        None => (Some(SIGTERM), &argv[1.min(argc)..]),
+    };
+    let Some(signum) = signum.filter(|_| !pids.is_empty()) else {
+        println!("usage: kill [-<signum>] <pid>...");
+        return 1;
+    };
+    let mut exit_code = 0;
+    for pid in pids {
+        let sent = pid
+            .parse()
+            .is_ok_and(|pid: isize| sys_kill(pid, signum) == 0);
+        if !sent {
+            println!("kill: no process {}", pid);
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
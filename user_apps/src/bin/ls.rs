@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::syscall::{
+    sys_close, sys_fstat, sys_getdents64, sys_open, Dirents, Stat, DT_DIR, O_RDONLY, S_IFDIR,
+    S_IFMT,
+};
+
+/// List the directory `path`, or print `path` itself if it is a file.
+fn ls(path: &str) -> bool {
+    let fd = sys_open(&format!("{}\0", path), O_RDONLY);
+    if fd < 0 {
+        println!("ls: cannot open {}", path);
+        return false;
+    }
+    let fd = fd as usize;
+    let mut stat = Stat::default();
+    sys_fstat(fd, &mut stat);
+    if stat.mode & S_IFMT != S_IFDIR {
+        println!("{}", path);
+        sys_close(fd);
+        return true;
+    }
+    let mut buf = [0u8; 512];
+    loop {
+        let len = sys_getdents64(fd, &mut buf);
+        if len <= 0 {
+            sys_close(fd);
+            return len == 0;
+        }
+        for (_, d_type, name) in Dirents::new(&buf[..len as usize]) {
+            if d_type == DT_DIR {
+                println!("{}/", name);
+            } else {
+                println!("{}", name);
+            }
+        }
+    }
+}
+
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    let ok = if argc < 2 {
+        ls(".")
+    } else {
+        argv[1..].iter().fold(true, |ok, path| ls(path) && ok)
+    };
+    if ok {
+        0
+    } else {
+        1
+    }
+}
@@ -0,0 +1,25 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::syscall::{sys_mkdirat, AT_FDCWD};
+
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    if argc < 2 {
+        println!("usage: mkdir <dir>...");
+        return 1;
+    }
+    let mut exit_code = 0;
+    for path in &argv[1..] {
+        if sys_mkdirat(AT_FDCWD, &format!("{}\0", path), 0o755) != 0 {
+            println!("mkdir: cannot create {}", path);
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
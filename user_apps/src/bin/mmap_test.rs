@@ -0,0 +1,88 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{sys_exit, sys_fork, sys_mmap, sys_munmap, PROT_READ, PROT_WRITE},
+    waitpid,
+};
+
+const PAGE_SIZE: usize = 0x1000;
+const START: usize = 0x1000_0000;
+
+/// Touch `addr` in a child process and return the child's exit code.
+fn probe(addr: usize) -> i32 {
+    let pid = sys_fork();
+    if pid == 0 {
+        unsafe {
+            (addr as *mut u8).write_volatile(1);
+        }
+        sys_exit(0);
+    }
+    let mut exit_code = 0;
+    waitpid(pid, &mut exit_code);
+    exit_code
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let len = 4 * PAGE_SIZE;
+    assert_eq!(sys_mmap(START, len, PROT_READ | PROT_WRITE), 0);
+    let buf = unsafe { core::slice::from_raw_parts_mut(START as *mut u8, len) };
+    assert!(buf.iter().all(|&b| b == 0));
+    for (i, b) in buf.iter_mut().enumerate() {
+        *b = i as u8;
+    }
+    assert!(buf.iter().enumerate().all(|(i, &b)| b == i as u8));
+
+    // overlapping, unaligned, empty, bad prot and kernel-range requests fail
+    assert_eq!(sys_mmap(START + PAGE_SIZE, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(sys_mmap(START + len + 1, PAGE_SIZE, PROT_READ), -1);
+    assert_eq!(sys_mmap(START + len, 0, PROT_READ), -1);
+    assert_eq!(sys_mmap(START + len, PAGE_SIZE, 0), -1);
+    assert_eq!(sys_mmap(START + len, PAGE_SIZE, 0x8), -1);
+    assert_eq!(
+        sys_mmap(usize::MAX - PAGE_SIZE * 2 + 1, PAGE_SIZE, PROT_READ),
+        -1
+    );
+
+    // punch a hole in the middle, the pages around it stay usable
+    assert_eq!(sys_munmap(START + PAGE_SIZE, 2 * PAGE_SIZE), 0);
+    assert_eq!(sys_munmap(START + PAGE_SIZE, PAGE_SIZE), -1);
+    assert_eq!(buf[0], 0);
+    assert_eq!(buf[len - 1], (len - 1) as u8);
+    assert_eq!(probe(START), 0);
+    assert_ne!(probe(START + PAGE_SIZE), 0);
+
+    // the hole can be mapped again, then everything unmapped at once
+    assert_eq!(
+        sys_mmap(START + PAGE_SIZE, 2 * PAGE_SIZE, PROT_READ | PROT_WRITE),
+        0
+    );
+    assert_eq!(buf[PAGE_SIZE], 0);
+    assert_eq!(sys_munmap(START, len), 0);
+    assert_ne!(probe(START), 0);
+
+    // with every frame taken, fork fails rather than the kernel; pages are
+    // only taken up front without demand paging, so more than the machine's
+    // 8 MiB mapped means nothing was taken
+    let mut end = START;
+    while end - START <= 16 << 20 && sys_mmap(end, PAGE_SIZE, PROT_READ | PROT_WRITE) == 0 {
+        end += PAGE_SIZE;
+    }
+    if end - START <= 16 << 20 {
+        assert_eq!(sys_fork(), -1);
+    }
+    assert_eq!(sys_munmap(START, end - START), 0);
+    let pid = sys_fork();
+    if pid == 0 {
+        sys_exit(0);
+    }
+    assert!(pid > 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    println!("mmap_test passed!");
+    0
+}
@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{sys_close, sys_dup, sys_exit, sys_fork, sys_pipe, sys_read, sys_write},
+    waitpid,
+};
+
+/// Longer than the kernel's pipe buffer, so the writer has to block.
+const LEN: usize = 200;
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut pipe_fd = [0usize; 2];
+    assert_eq!(sys_pipe(&mut pipe_fd), 0);
+    let [read_fd, write_fd] = pipe_fd;
+    let pid = sys_fork();
+    if pid == 0 {
+        // child: write through a dup of the write end
+        sys_close(read_fd);
+        let fd = sys_dup(write_fd);
+        assert!(fd >= 0);
+        sys_close(write_fd);
+        let mut data = [0u8; LEN];
+        for (i, b) in data.iter_mut().enumerate() {
+            *b = i as u8;
+        }
+        assert_eq!(sys_write(fd as usize, &data), LEN as isize);
+        sys_exit(0);
+    }
+    // parent: read until EOF
+    sys_close(write_fd);
+    assert_eq!(sys_write(read_fd, b"x"), -1);
+    let mut data = [0u8; LEN + 1];
+    let mut total = 0;
+    loop {
+        let read = sys_read(read_fd, &mut data[total..]);
+        assert!(read >= 0);
+        if read == 0 {
+            break;
+        }
+        total += read as usize;
+    }
+    assert_eq!(total, LEN);
+    assert!(data[..LEN].iter().enumerate().all(|(i, &b)| b == i as u8));
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, 0);
+
+    // closed and never-opened fds are rejected
+    assert_eq!(sys_close(read_fd), 0);
+    assert_eq!(sys_close(read_fd), -1);
+    assert_eq!(sys_dup(read_fd), -1);
+    println!("pipe_test passed!");
+    0
+}
@@ -0,0 +1,30 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::format;
+use user_lib::syscall::{sys_unlinkat, AT_FDCWD, AT_REMOVEDIR};
+
+/// `rm [-d] <path>...`, where `-d` removes empty directories instead of files
+#[no_mangle]
+fn main(argc: usize, argv: &[&str]) -> i32 {
+    let (flags, paths) = match argv.get(1) {
+        Some(&"-d") => (AT_REMOVEDIR, &argv[2..]),
+        _ => (0, &argv[1..]),
+    };
+    if argc < 2 || paths.is_empty() {
+        println!("usage: rm [-d] <path>...");
+        return 1;
+    }
+    let mut exit_code = 0;
+    for path in paths {
+        if sys_unlinkat(AT_FDCWD, &format!("{}\0", path), flags) != 0 {
+            println!("rm: cannot remove {}", path);
+            exit_code = 1;
+        }
+    }
+    exit_code
+}
@@ -0,0 +1,78 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{sys_fork, sys_get_time, sys_set_priority, sys_yield},
+    waitpid,
+};
+
+/// timer ticks per second on QEMU's virt machine
+const CLOCK_FREQ: isize = 12_500_000;
+const HOGS: usize = 4;
+
+/// Count loop iterations from `start` to `end`.
+fn spin(start: isize, end: isize) -> i32 {
+    while sys_get_time() < start {}
+    let mut count = 0;
+    while sys_get_time() < end {
+        count += 1;
+    }
+    count
+}
+
+/// Yield from `start` to `end`, and return the longest wait for a turn, in
+/// milliseconds.
+fn yield_until(start: isize, end: isize) -> i32 {
+    while sys_get_time() < start {}
+    let mut last = sys_get_time();
+    let mut max_gap = 0;
+    while last < end {
+        sys_yield();
+        let now = sys_get_time();
+        max_gap = max_gap.max(now - last);
+        last = now;
+    }
+    (max_gap * 1000 / CLOCK_FREQ) as i32
+}
+
+/// Checks that hold under every scheduling policy; `stride_test` checks
+/// priority weighting, which only some of them give
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    assert_eq!(sys_set_priority(1), -1);
+    assert_eq!(sys_set_priority(2), 2);
+    assert_eq!(sys_set_priority(16), 16);
+
+    // hogs of the same priority spin over the same second, next to a process
+    // that keeps yielding, and none of them is starved
+    let start = sys_get_time() + CLOCK_FREQ / 10;
+    let end = start + CLOCK_FREQ;
+    let mut pids = [0; HOGS];
+    for pid in pids.iter_mut() {
+        *pid = sys_fork();
+        if *pid == 0 {
+            return spin(start, end);
+        }
+    }
+    let yielder = sys_fork();
+    if yielder == 0 {
+        return yield_until(start, end);
+    }
+    let mut counts = [0; HOGS];
+    for (&pid, count) in pids.iter().zip(counts.iter_mut()) {
+        assert_eq!(waitpid(pid, count), pid);
+    }
+    let mut max_gap = 0;
+    assert_eq!(waitpid(yielder, &mut max_gap), yielder);
+    println!("loops {:?}, longest wait to yield {}ms", counts, max_gap);
+    let min = *counts.iter().min().unwrap();
+    let max = *counts.iter().max().unwrap();
+    assert!(min > 0);
+    assert!(max < min * 2, "loops {:?}", counts);
+    assert!(max_gap < 500, "waited {}ms to yield", max_gap);
+    println!("sched_test passed!");
+    0
+}
@@ -0,0 +1,113 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::{
+    ptr::null_mut,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use user_lib::{
+    signal,
+    syscall::{
+        sys_exit, sys_fork, sys_getpid, sys_kill, sys_sigaction, sys_sigprocmask, sys_yield,
+        SignalAction, SIGCONT, SIGKILL, SIGSEGV, SIGSTOP, SIGTERM, SIGUSR1, SIGUSR2, SIG_BLOCK,
+        SIG_IGN, SIG_SETMASK,
+    },
+    waitpid,
+};
+
+/// The signals caught so far, signal `n` as bit `n`
+static CAUGHT: AtomicUsize = AtomicUsize::new(0);
+
+extern "C" fn catch(signum: usize) {
+    CAUGHT.fetch_or(1 << signum, Ordering::SeqCst);
+}
+
+extern "C" fn exit_on_segv(signum: usize) {
+    sys_exit(signum as i32 * 10);
+}
+
+fn self_kill(signum: usize) {
+    assert_eq!(sys_kill(sys_getpid(), signum), 0);
+    // the signal is acted on when the syscall returns
+}
+
+/// Return the exit code of a child running `f`.
+fn in_child(f: fn()) -> i32 {
+    let pid = sys_fork();
+    if pid == 0 {
+        f();
+        sys_exit(0);
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    exit_code
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    // a handler runs, and the interrupted code carries on
+    assert_eq!(signal(SIGUSR1, catch), 0);
+    self_kill(SIGUSR1);
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), 1 << SIGUSR1);
+
+    // a blocked signal waits until it is unblocked
+    let mut old_mask = 0;
+    assert_eq!(
+        sys_sigprocmask(SIG_BLOCK, Some(&(1 << SIGUSR1)), Some(&mut old_mask)),
+        0
+    );
+    assert_eq!(old_mask, 0);
+    CAUGHT.store(0, Ordering::SeqCst);
+    self_kill(SIGUSR1);
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), 0);
+    assert_eq!(sys_sigprocmask(SIG_SETMASK, Some(&0), None), 0);
+    assert_eq!(CAUGHT.load(Ordering::SeqCst), 1 << SIGUSR1);
+
+    // ignored signals do nothing, and SIGKILL cannot be caught
+    let ignore = SignalAction {
+        handler: SIG_IGN,
+        ..SignalAction::default()
+    };
+    assert_eq!(sys_sigaction(SIGUSR2, Some(&ignore), None), 0);
+    self_kill(SIGUSR2);
+    assert_eq!(sys_sigaction(SIGKILL, Some(&ignore), None), -1);
+    assert_eq!(sys_sigaction(SIGSTOP, Some(&ignore), None), -1);
+    let mut old = SignalAction::default();
+    assert_eq!(sys_sigaction(SIGUSR1, None, Some(&mut old)), 0);
+    assert_eq!(old.handler, catch as *const () as usize);
+
+    // uncaught signals kill
+    assert_eq!(in_child(|| self_kill(SIGTERM)), -(SIGTERM as i32));
+    assert_eq!(
+        in_child(|| unsafe { null_mut::<u8>().write_volatile(0) }),
+        -(SIGSEGV as i32)
+    );
+    // a fault can be caught
+    assert_eq!(
+        in_child(|| {
+            signal(SIGSEGV, exit_on_segv);
+            unsafe { null_mut::<u8>().write_volatile(0) };
+        }),
+        SIGSEGV as i32 * 10
+    );
+
+    // another process can be stopped, continued and killed
+    let pid = sys_fork();
+    if pid == 0 {
+        loop {
+            sys_yield();
+        }
+    }
+    assert_eq!(sys_kill(pid, SIGSTOP), 0);
+    assert_eq!(sys_kill(pid, SIGCONT), 0);
+    assert_eq!(sys_kill(pid, SIGKILL), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    assert_eq!(sys_kill(pid, 0), -1);
+    println!("sig_test passed!");
+    0
+}
@@ -0,0 +1,110 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    signal,
+    syscall::{
+        sys_exit, sys_fork, sys_get_time, sys_kill, sys_nanosleep, sys_sleep_ms, sys_thread_create,
+        sys_times, TimeSpec, Tms, SIGKILL, SIGUSR1,
+    },
+    waitpid, waittid,
+};
+
+/// timer ticks per millisecond on QEMU's virt machine
+const TICKS_PER_MS: isize = 12_500;
+
+/// The order the sleepers woke up in
+static WOKEN: [AtomicUsize; 3] = [
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+    AtomicUsize::new(0),
+];
+static NEXT: AtomicUsize = AtomicUsize::new(0);
+
+/// Sleep for `ms` milliseconds and record the wake-up.
+fn sleeper(ms: usize) -> ! {
+    sys_sleep_ms(ms);
+    WOKEN[NEXT.fetch_add(1, Ordering::Relaxed)].store(ms, Ordering::Relaxed);
+    sys_exit(0);
+    unreachable!()
+}
+
+extern "C" fn ignore(_signum: usize) {}
+
+/// Sleep for a second, until the parent sends SIGUSR1, and check the time
+/// left.
+fn interrupted_sleep() -> i32 {
+    assert_eq!(signal(SIGUSR1, ignore), 0);
+    let req = TimeSpec { sec: 1, nsec: 0 };
+    let mut rem = TimeSpec::default();
+    assert_eq!(sys_nanosleep(&req, Some(&mut rem)), -1);
+    assert_eq!(rem.sec, 0);
+    assert!(rem.nsec > 500_000_000);
+    0
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let invalid = TimeSpec {
+        sec: 0,
+        nsec: 1_000_000_000,
+    };
+    assert_eq!(sys_nanosleep(&invalid, None), -1);
+    assert_eq!(sys_sleep_ms(0), 0);
+
+    // a sleeper takes no CPU time, and wakes up on time even while a hog
+    // keeps the CPU busy
+    let hog = sys_fork();
+    if hog == 0 {
+        loop {}
+    }
+    let mut before = Tms::default();
+    let start = sys_times(&mut before);
+    assert_eq!(sys_sleep_ms(100), 0);
+    let mut after = Tms::default();
+    let end = sys_times(&mut after);
+    let slept = (end - start) / TICKS_PER_MS;
+    println!("slept {}ms", slept);
+    assert!(slept >= 100);
+    assert!(slept < 150);
+    assert!(after.utime + after.stime - before.utime - before.stime < 10 * TICKS_PER_MS as usize);
+    assert_eq!(sys_kill(hog, SIGKILL), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(hog, &mut exit_code), hog);
+
+    // sleepers wake up by deadline, not by when they went to sleep
+    let tids = [60, 20, 40].map(|ms| sys_thread_create(sleeper, ms) as usize);
+    for tid in tids {
+        assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+    }
+    let woken = WOKEN.each_ref().map(|ms| ms.load(Ordering::Relaxed));
+    assert_eq!(woken, [20, 40, 60]);
+    assert!(sys_get_time() > end);
+
+    // a signal cuts a sleep short
+    let start = sys_get_time();
+    let sleeper = sys_fork();
+    if sleeper == 0 {
+        sys_sleep_ms(10_000);
+        return 1;
+    }
+    sys_sleep_ms(50);
+    assert_eq!(sys_kill(sleeper, SIGKILL), 0);
+    assert_eq!(waitpid(sleeper, &mut exit_code), sleeper);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+    assert!(sys_get_time() - start < 1000 * TICKS_PER_MS);
+    let sleeper = sys_fork();
+    if sleeper == 0 {
+        return interrupted_sleep();
+    }
+    sys_sleep_ms(50);
+    assert_eq!(sys_kill(sleeper, SIGUSR1), 0);
+    assert_eq!(waitpid(sleeper, &mut exit_code), sleeper);
+    assert_eq!(exit_code, 0);
+    println!("sleep_test passed!");
+    0
+}
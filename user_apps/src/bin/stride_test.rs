@@ -0,0 +1,58 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{sys_fork, sys_get_time, sys_set_priority},
+    waitpid,
+};
+
+/// timer ticks per second on QEMU's virt machine
+const CLOCK_FREQ: isize = 12_500_000;
+const PRIORITIES: [isize; 4] = [4, 6, 8, 12];
+
+/// Count loop iterations from `start` to `end`.
+fn spin(start: isize, end: isize) -> i32 {
+    while sys_get_time() < start {}
+    let mut count = 0;
+    while sys_get_time() < end {
+        count += 1;
+    }
+    count
+}
+
+/// Checks that each process runs in proportion to its priority, which stride
+/// and CFS scheduling give, but not the other policies
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    assert_eq!(sys_set_priority(1), -1);
+    assert_eq!(sys_set_priority(16), 16);
+
+    // the children all spin over the same second, by when each has run in
+    // proportion to its priority
+    let start = sys_get_time() + CLOCK_FREQ / 10;
+    let end = start + CLOCK_FREQ;
+    let mut pids = [0; PRIORITIES.len()];
+    for (pid, &priority) in pids.iter_mut().zip(PRIORITIES.iter()) {
+        *pid = sys_fork();
+        if *pid == 0 {
+            sys_set_priority(priority);
+            return spin(start, end);
+        }
+    }
+    let mut per_priority = [0; PRIORITIES.len()];
+    for ((&pid, &priority), share) in pids.iter().zip(PRIORITIES.iter()).zip(&mut per_priority) {
+        let mut count = 0;
+        assert_eq!(waitpid(pid, &mut count), pid);
+        println!("priority {}: {} loops", priority, count);
+        *share = count as isize / priority;
+    }
+    let min = *per_priority.iter().min().unwrap();
+    let max = *per_priority.iter().max().unwrap();
+    assert!(min > 0);
+    assert!(max * 10 < min * 13, "loops per priority {:?}", per_priority);
+    println!("stride_test passed!");
+    0
+}
@@ -0,0 +1,141 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use user_lib::{
+    syscall::{
+        sys_condvar_create, sys_condvar_signal, sys_condvar_wait, sys_exit, sys_fork, sys_kill,
+        sys_mutex_create, sys_mutex_lock, sys_mutex_unlock, sys_semaphore_create,
+        sys_semaphore_down, sys_semaphore_up, sys_sleep_ms, sys_thread_create, sys_yield, SIGKILL,
+    },
+    waitpid, waittid,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 50;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+static READY: AtomicBool = AtomicBool::new(false);
+
+/// Add one to the counter `ROUNDS` times under the mutex `mutex_id`, with a
+/// yield between reading and writing that loses updates without it.
+fn add(mutex_id: usize) -> ! {
+    for _ in 0..ROUNDS {
+        assert_eq!(sys_mutex_lock(mutex_id), 0);
+        let value = COUNTER.load(Ordering::Relaxed);
+        sys_yield();
+        COUNTER.store(value + 1, Ordering::Relaxed);
+        assert_eq!(sys_mutex_unlock(mutex_id), 0);
+    }
+    sys_exit(0);
+    unreachable!()
+}
+
+fn run_threads(entry: fn(usize) -> !, arg: usize) {
+    let mut tids = [0; THREADS];
+    for tid in tids.iter_mut() {
+        *tid = sys_thread_create(entry, arg) as usize;
+    }
+    let mut exit_code = 0;
+    for &tid in tids.iter() {
+        assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+        assert_eq!(exit_code, 0);
+    }
+}
+
+fn mutex_test(blocking: bool) {
+    let mutex_id = sys_mutex_create(blocking);
+    assert!(mutex_id >= 0);
+    COUNTER.store(0, Ordering::Relaxed);
+    run_threads(add, mutex_id as usize);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), THREADS * ROUNDS);
+    // a mutex that is not locked cannot be unlocked
+    assert_eq!(sys_mutex_unlock(mutex_id as usize), -1);
+}
+
+/// Count this thread in and up the semaphore `sem_id`.
+fn post(sem_id: usize) -> ! {
+    COUNTER.fetch_add(1, Ordering::Relaxed);
+    sys_semaphore_up(sem_id);
+    sys_exit(0);
+    unreachable!()
+}
+
+fn semaphore_test() {
+    let sem_id = sys_semaphore_create(0) as usize;
+    COUNTER.store(0, Ordering::Relaxed);
+    let tid = sys_thread_create(post, sem_id) as usize;
+    // this waits for the thread to up it
+    assert_eq!(sys_semaphore_down(sem_id), 0);
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 1);
+    let mut exit_code = 0;
+    assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+    // ups are counted while nobody waits
+    run_threads(post, sem_id);
+    for _ in 0..THREADS {
+        assert_eq!(sys_semaphore_down(sem_id), 0);
+    }
+    assert_eq!(COUNTER.load(Ordering::Relaxed), 1 + THREADS);
+
+    // a process blocked on a semaphore can still be killed
+    let pid = sys_fork();
+    if pid == 0 {
+        let sem_id = sys_semaphore_create(0) as usize;
+        sys_semaphore_down(sem_id);
+        sys_exit(1);
+    }
+    sys_sleep_ms(50);
+    assert_eq!(sys_kill(pid, SIGKILL), 0);
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert_eq!(exit_code, -(SIGKILL as i32));
+}
+
+/// The condition variable and mutex used by `notify`
+static CONDVAR_ID: AtomicUsize = AtomicUsize::new(0);
+static MUTEX_ID: AtomicUsize = AtomicUsize::new(0);
+
+fn notify(_arg: usize) -> ! {
+    let mutex_id = MUTEX_ID.load(Ordering::Relaxed);
+    sys_mutex_lock(mutex_id);
+    READY.store(true, Ordering::Relaxed);
+    sys_condvar_signal(CONDVAR_ID.load(Ordering::Relaxed));
+    sys_mutex_unlock(mutex_id);
+    sys_exit(0);
+    unreachable!()
+}
+
+fn condvar_test() {
+    let condvar_id = sys_condvar_create() as usize;
+    let mutex_id = sys_mutex_create(true) as usize;
+    CONDVAR_ID.store(condvar_id, Ordering::Relaxed);
+    MUTEX_ID.store(mutex_id, Ordering::Relaxed);
+    // waiting needs the mutex held
+    assert_eq!(sys_condvar_wait(condvar_id, mutex_id), -1);
+
+    assert_eq!(sys_mutex_lock(mutex_id), 0);
+    let tid = sys_thread_create(notify, 0) as usize;
+    while !READY.load(Ordering::Relaxed) {
+        assert_eq!(sys_condvar_wait(condvar_id, mutex_id), 0);
+    }
+    assert_eq!(sys_mutex_unlock(mutex_id), 0);
+    let mut exit_code = 0;
+    assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    mutex_test(false);
+    mutex_test(true);
+    semaphore_test();
+    condvar_test();
+    // ids that were never handed out are rejected
+    assert_eq!(sys_mutex_lock(1234), -1);
+    assert_eq!(sys_semaphore_up(1234), -1);
+    assert_eq!(sys_condvar_signal(1234), -1);
+    println!("sync_test passed!");
+    0
+}
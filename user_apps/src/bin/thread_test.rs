@@ -0,0 +1,81 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use core::sync::atomic::{AtomicUsize, Ordering};
+use user_lib::{
+    syscall::{
+        sys_exit, sys_gettid, sys_mmap, sys_munmap, sys_thread_create, sys_waittid, sys_yield,
+        PROT_READ, PROT_WRITE,
+    },
+    waittid,
+};
+
+const THREADS: usize = 4;
+const ROUNDS: usize = 100;
+
+static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Where the kernel puts the user stack of thread `tid`, as its bottom
+fn ustack_bottom(tid: usize) -> usize {
+    const USER_STACK_TOP: usize = 0x1_0000_0000;
+    const USER_STACK_SIZE: usize = 0x4000;
+    const PAGE_SIZE: usize = 0x1000;
+    USER_STACK_TOP - tid * (USER_STACK_SIZE + PAGE_SIZE) - USER_STACK_SIZE
+}
+
+fn worker(arg: usize) -> ! {
+    for _ in 0..ROUNDS {
+        COUNTER.fetch_add(arg, Ordering::Relaxed);
+        sys_yield();
+    }
+    sys_exit(sys_gettid() as i32 * 10);
+    unreachable!("thread {} went on after sys_exit", sys_gettid())
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    assert_eq!(sys_gettid(), 0);
+    let mut tids = [0; THREADS];
+    for (i, tid) in tids.iter_mut().enumerate() {
+        let created = sys_thread_create(worker, i + 1);
+        assert!(created > 0);
+        *tid = created as usize;
+    }
+    // neither the caller nor the main thread can be waited for
+    let mut exit_code = 0;
+    assert_eq!(sys_waittid(0, &mut exit_code), -1);
+    assert_eq!(sys_waittid(1234, &mut exit_code), -1);
+    for &tid in tids.iter() {
+        assert_eq!(waittid(tid, &mut exit_code), tid as isize);
+        assert_eq!(exit_code, tid as i32 * 10);
+        // a thread is collected only once
+        assert_eq!(sys_waittid(tid, &mut exit_code), -1);
+    }
+    // the threads share the address space of the process
+    let expected = ROUNDS * (1..=THREADS).sum::<usize>();
+    assert_eq!(COUNTER.load(Ordering::Relaxed), expected);
+    // tids are handed out again once collected
+    let tid = sys_thread_create(worker, 0);
+    assert!(tids.contains(&(tid as usize)));
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    // a thread whose stack would land on memory mapped already is refused,
+    // and its tid handed out again
+    for tid in 1..=THREADS {
+        assert_eq!(
+            sys_mmap(ustack_bottom(tid), 0x4000, PROT_READ | PROT_WRITE),
+            0
+        );
+    }
+    assert_eq!(sys_thread_create(worker, 0), -1);
+    for tid in 1..=THREADS {
+        assert_eq!(sys_munmap(ustack_bottom(tid), 0x4000), 0);
+    }
+    let tid = sys_thread_create(worker, 0);
+    assert!(tids.contains(&(tid as usize)));
+    assert_eq!(waittid(tid as usize, &mut exit_code), tid);
+    println!("thread_test passed!");
+    0
+}
@@ -0,0 +1,74 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::{
+    syscall::{
+        sys_fork, sys_get_time, sys_getpid, sys_getrusage, sys_times, sys_yield, RUsage, Tms,
+        RUSAGE_CHILDREN, RUSAGE_SELF,
+    },
+    waitpid,
+};
+
+/// timer ticks per second on QEMU's virt machine
+const CLOCK_FREQ: isize = 12_500_000;
+
+/// Spin in user space for `ticks`.
+fn spin(ticks: isize) {
+    let end = sys_get_time() + ticks;
+    while sys_get_time() < end {}
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut before = Tms::default();
+    let start = sys_times(&mut before);
+    assert!(start > 0);
+
+    // spinning is mostly user time, syscalls are kernel time
+    spin(CLOCK_FREQ / 5);
+    for _ in 0..1000 {
+        sys_getpid();
+    }
+    let mut after = Tms::default();
+    let now = sys_times(&mut after);
+    assert!(now >= start);
+    assert!(after.utime > before.utime);
+    assert!(after.stime > before.stime);
+
+    // yielding counts as voluntary, running out a time slice as involuntary
+    let mut usage = RUsage::default();
+    assert_eq!(sys_getrusage(RUSAGE_SELF, &mut usage), 0);
+    let voluntary = usage.nvcsw;
+    for _ in 0..10 {
+        sys_yield();
+    }
+    assert_eq!(sys_getrusage(RUSAGE_SELF, &mut usage), 0);
+    assert!(usage.nvcsw >= voluntary + 10);
+    assert!(usage.nivcsw > 0);
+    // laid out like Linux's, with what is not kept left zero
+    assert_eq!(core::mem::size_of::<RUsage>(), 144);
+    assert_eq!(usage.maxrss, 0);
+    assert_eq!(sys_getrusage(1, &mut usage), -1);
+
+    // a child's time goes to its parent once reaped
+    let pid = sys_fork();
+    if pid == 0 {
+        spin(CLOCK_FREQ / 10);
+        return 0;
+    }
+    let mut exit_code = 0;
+    assert_eq!(waitpid(pid, &mut exit_code), pid);
+    assert!(sys_times(&mut after) >= now);
+    assert!(after.cutime >= (CLOCK_FREQ / 20) as usize);
+    assert_eq!(sys_getrusage(RUSAGE_CHILDREN, &mut usage), 0);
+    assert!(usage.utime.sec * 1_000_000 + usage.utime.usec >= 50_000);
+    println!(
+        "user {}, kernel {}, children {} ticks",
+        after.utime, after.stime, after.cutime
+    );
+    println!("times_test passed!");
+    0
+}
@@ -0,0 +1,34 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{
+    sys_close, sys_open, sys_read, sys_write, O_APPEND, O_CREATE, O_RDONLY, O_TRUNC, O_WRONLY,
+};
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    // init mounts a tmpfs at /tmp
+    let fd = sys_open("/tmp/tmp_test\0", O_CREATE | O_TRUNC | O_WRONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_write(fd as usize, b"head"), 4);
+    sys_close(fd as usize);
+
+    // every write goes to the end, whatever the offset
+    let fd = sys_open("/tmp/tmp_test\0", O_WRONLY | O_APPEND);
+    assert!(fd > 0);
+    assert_eq!(sys_write(fd as usize, b"-"), 1);
+    assert_eq!(sys_write(fd as usize, b"tail"), 4);
+    sys_close(fd as usize);
+
+    let fd = sys_open("/tmp/tmp_test\0", O_RDONLY);
+    assert!(fd > 0);
+    let mut buffer = [0u8; 32];
+    let read = sys_read(fd as usize, &mut buffer);
+    assert_eq!(&buffer[..read as usize], b"head-tail");
+    sys_close(fd as usize);
+    println!("tmp_test passed!");
+    0
+}
@@ -0,0 +1,398 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+extern crate alloc;
+
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::ptr::null;
+use user_lib::{
+    console::getchar,
+    syscall::{
+        stop_signal, sys_chdir, sys_close, sys_dup, sys_exec, sys_exit, sys_fork, sys_getcwd,
+        sys_getpid, sys_kill, sys_open, sys_pipe, sys_setpgid, sys_sigaction, sys_waitpid,
+        sys_yield, SignalAction, O_CREATE, O_RDONLY, O_TRUNC, O_WRONLY, SIGCONT, SIGINT, SIGTSTP,
+        SIG_DFL, SIG_IGN, WUNTRACED,
+    },
+    tcsetpgrp,
+};
+
+const LF: u8 = b'\n';
+const CR: u8 = b'\r';
+const BS: u8 = 0x08;
+const DEL: u8 = 0x7f;
+/// Ctrl-D
+const EOT: u8 = 0x04;
+
+/// The console
+const STDIN: usize = 0;
+
+/// One command of a pipeline, with its redirections
+#[derive(Default)]
+struct Command {
+    args: Vec<String>,
+    input: Option<String>,
+    output: Option<String>,
+}
+
+/// Split `line` into words, with `|`, `<`, `>` and `&` always on their own.
+fn tokenize(line: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+    for c in line.chars() {
+        if c.is_whitespace() || "|<>&".contains(c) {
+            if !word.is_empty() {
+                tokens.push(core::mem::take(&mut word));
+            }
+            if !c.is_whitespace() {
+                tokens.push(c.to_string());
+            }
+        } else {
+            word.push(c);
+        }
+    }
+    if !word.is_empty() {
+        tokens.push(word);
+    }
+    tokens
+}
+
+/// Parse `line` into a pipeline and whether it runs in the background.
+fn parse(line: &str) -> Result<(Vec<Command>, bool), &'static str> {
+    let mut tokens = tokenize(line);
+    let background = tokens.last().is_some_and(|token| token == "&");
+    if background {
+        tokens.pop();
+    }
+    let mut pipeline = Vec::new();
+    let mut command = Command::default();
+    let mut tokens = tokens.into_iter();
+    while let Some(token) = tokens.next() {
+        match token.as_str() {
+            "|" => {
+                if command.args.is_empty() {
+                    return Err("empty command in pipeline");
+                }
+                pipeline.push(core::mem::take(&mut command));
+            }
+            "<" | ">" => {
+                let Some(path) = tokens.next().filter(|path| !"|<>&".contains(path.as_str()))
+                else {
+                    return Err("missing file to redirect");
+                };
+                if token == "<" {
+                    command.input = Some(path);
+                } else {
+                    command.output = Some(path);
+                }
+            }
+            "&" => return Err("`&` must come last"),
+            _ => command.args.push(token),
+        }
+    }
+    if command.args.is_empty() {
+        if pipeline.is_empty() && !background {
+            return Ok((pipeline, false));
+        }
+        return Err("empty command in pipeline");
+    }
+    pipeline.push(command);
+    Ok((pipeline, background))
+}
+
+/// Make `fd` the file at `target` in place of whatever was there.
+fn redirect(fd: usize, target: usize) {
+    sys_close(target);
+    assert_eq!(sys_dup(fd), target as isize);
+    sys_close(fd);
+}
+
+/// A pipeline started from one line, which forms a process group
+struct Job {
+    pgid: usize,
+    /// the commands that have not been reaped yet
+    pids: Vec<isize>,
+    /// the last command, whose exit code is the job's
+    last: isize,
+    exit_code: i32,
+}
+
+/// Set how Ctrl-C and Ctrl-Z are treated to `handler`.
+fn set_console_signals(handler: usize) {
+    let action = SignalAction {
+        handler,
+        ..SignalAction::default()
+    };
+    for signum in [SIGINT, SIGTSTP] {
+        sys_sigaction(signum, Some(&action), None);
+    }
+}
+
+/// Run `command` in the forked child, reading from `input` and writing to
+/// `output`. Never returns.
+fn run_child(command: &Command, input: Option<usize>, output: Option<usize>, pipes: &[usize]) -> ! {
+    // the shell ignores them, which would outlive the exec
+    set_console_signals(SIG_DFL);
+    if let Some(fd) = input {
+        redirect(fd, 0);
+    }
+    if let Some(fd) = output {
+        redirect(fd, 1);
+    }
+    for &fd in pipes {
+        sys_close(fd);
+    }
+    if let Some(path) = &command.input {
+        let fd = sys_open(&format!("{}\0", path), O_RDONLY);
+        if fd < 0 {
+            println!("{}: cannot open", path);
+            sys_exit(1);
+        }
+        redirect(fd as usize, 0);
+    }
+    if let Some(path) = &command.output {
+        let fd = sys_open(&format!("{}\0", path), O_CREATE | O_TRUNC | O_WRONLY);
+        if fd < 0 {
+            println!("{}: cannot open", path);
+            sys_exit(1);
+        }
+        redirect(fd as usize, 1);
+    }
+    // apps live in the root directory
+    let name = &command.args[0];
+    let path = if name.contains('/') {
+        format!("{}\0", name)
+    } else {
+        format!("/{}\0", name)
+    };
+    let args: Vec<String> = command
+        .args
+        .iter()
+        .map(|arg| format!("{}\0", arg))
+        .collect();
+    let mut argv: Vec<*const u8> = args.iter().map(|arg| arg.as_ptr()).collect();
+    argv.push(null());
+    sys_exec(&path, &argv, &[null()]);
+    println!("{}: command not found", name);
+    sys_exit(127);
+    unreachable!()
+}
+
+/// Start every command of `pipeline` in a new process group, named after
+/// the pid of the first command.
+fn spawn(pipeline: &[Command]) -> Job {
+    // pipes[i] connects command i to command i + 1
+    let mut pipes = Vec::new();
+    for _ in 1..pipeline.len() {
+        let mut pipe = [0usize; 2];
+        assert_eq!(sys_pipe(&mut pipe), 0);
+        pipes.push(pipe);
+    }
+    let all_fds: Vec<usize> = pipes.iter().flatten().copied().collect();
+    let mut pids = Vec::new();
+    let mut pgid = 0;
+    for (i, command) in pipeline.iter().enumerate() {
+        let pid = sys_fork();
+        if pid == 0 {
+            sys_setpgid(0, pgid);
+            let input = i.checked_sub(1).map(|prev| pipes[prev][0]);
+            let output = pipes.get(i).map(|pipe| pipe[1]);
+            run_child(command, input, output, &all_fds);
+        }
+        // set here too, whichever of the shell and the child runs first
+        sys_setpgid(pid as usize, pgid);
+        if pgid == 0 {
+            pgid = pid as usize;
+        }
+        pids.push(pid);
+    }
+    for fd in all_fds {
+        sys_close(fd);
+    }
+    Job {
+        pgid,
+        last: *pids.last().unwrap(),
+        pids,
+        exit_code: 0,
+    }
+}
+
+/// Note that `pid` of `job` has exited with `exit_code`, returning whether
+/// the whole job has.
+fn reap(job: &mut Job, pid: isize, exit_code: i32) -> bool {
+    job.pids.retain(|&job_pid| job_pid != pid);
+    if pid == job.last {
+        job.exit_code = exit_code;
+    }
+    job.pids.is_empty()
+}
+
+/// Hand the console to `job` and wait until it exits, returning it if it is
+/// stopped instead.
+fn run_foreground(mut job: Job) -> Option<Job> {
+    tcsetpgrp(STDIN, job.pgid);
+    let mut stopped = false;
+    while let Some(&pid) = job.pids.first() {
+        let mut status = 0;
+        let found = loop {
+            match sys_waitpid(pid, &mut status, WUNTRACED) {
+                -2 => {
+                    sys_yield();
+                }
+                found => break found,
+            }
+        };
+        if found > 0 && stop_signal(status).is_some() {
+            stopped = true;
+            break;
+        }
+        reap(&mut job, pid, status);
+    }
+    tcsetpgrp(STDIN, sys_getpid() as usize);
+    if stopped {
+        println!("");
+        println!("[{}] stopped", job.pgid);
+        return Some(job);
+    }
+    if job.exit_code != 0 {
+        println!("exit code {}", job.exit_code);
+    }
+    None
+}
+
+/// Reap the background jobs that have finished.
+fn reap_jobs(jobs: &mut Vec<Job>) {
+    let mut exit_code = 0;
+    loop {
+        let pid = sys_waitpid(-1, &mut exit_code, 0);
+        if pid < 0 {
+            break;
+        }
+        let Some(idx) = jobs.iter().position(|job| job.pids.contains(&pid)) else {
+            continue;
+        };
+        if reap(&mut jobs[idx], pid, exit_code) {
+            let job = jobs.remove(idx);
+            println!("[{}] done, exit code {}", job.pgid, job.exit_code);
+        }
+    }
+}
+
+/// Take the job `arg` names, or the latest one, out of `jobs` and continue
+/// it.
+fn continue_job(jobs: &mut Vec<Job>, arg: Option<&String>) -> Option<Job> {
+    let idx = match arg {
+        Some(arg) => jobs.iter().position(|job| arg.parse() == Ok(job.pgid))?,
+        None => jobs.len().checked_sub(1)?,
+    };
+    let job = jobs.remove(idx);
+    sys_kill(-(job.pgid as isize), SIGCONT);
+    Some(job)
+}
+
+fn print_prompt() {
+    let mut buf = [0u8; 128];
+    let len = sys_getcwd(&mut buf);
+    let cwd = if len > 0 {
+        core::str::from_utf8(&buf[..len as usize - 1]).unwrap_or("?")
+    } else {
+        "?"
+    };
+    print!("{} >> ", cwd);
+}
+
+/// Read a line from the console, echoing it, or `None` at Ctrl-D on an
+/// empty line or when stdin has nothing more to read.
+fn read_line() -> Option<String> {
+    let mut line = String::new();
+    loop {
+        let Some(c) = getchar() else {
+            println!("");
+            return None;
+        };
+        match c {
+            LF | CR => {
+                println!("");
+                return Some(line);
+            }
+            BS | DEL => {
+                if line.pop().is_some() {
+                    print!("{0} {0}", BS as char);
+                }
+            }
+            EOT if line.is_empty() => {
+                println!("");
+                return None;
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => {
+                print!("{}", c as char);
+                line.push(c as char);
+            }
+            _ => {}
+        }
+    }
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    println!("Rust user shell");
+    // Ctrl-C and Ctrl-Z are for the jobs, which get the console in turn
+    sys_setpgid(0, 0);
+    tcsetpgrp(STDIN, sys_getpid() as usize);
+    set_console_signals(SIG_IGN);
+    let mut jobs: Vec<Job> = Vec::new();
+    loop {
+        reap_jobs(&mut jobs);
+        print_prompt();
+        let Some(line) = read_line() else {
+            return 0;
+        };
+        let (pipeline, background) = match parse(&line) {
+            Ok(parsed) => parsed,
+            Err(err) => {
+                println!("syntax error: {}", err);
+                continue;
+            }
+        };
+        let Some(first) = pipeline.first() else {
+            continue;
+        };
+        // builtins, which only make sense in the shell itself
+        match first.args[0].as_str() {
+            "exit" if pipeline.len() == 1 => return 0,
+            "cd" if pipeline.len() == 1 => {
+                let dir = first.args.get(1).map_or("/", |dir| dir.as_str());
+                if sys_chdir(&format!("{}\0", dir)) != 0 {
+                    println!("cd: {}: no such directory", dir);
+                }
+                continue;
+            }
+            builtin @ ("fg" | "bg") if pipeline.len() == 1 => {
+                let Some(job) = continue_job(&mut jobs, first.args.get(1)) else {
+                    println!("{}: no such job", builtin);
+                    continue;
+                };
+                if builtin == "bg" {
+                    println!("[{}] continued", job.pgid);
+                    jobs.push(job);
+                } else if let Some(job) = run_foreground(job) {
+                    jobs.push(job);
+                }
+                continue;
+            }
+            _ => {}
+        }
+        let job = spawn(&pipeline);
+        if background {
+            println!("[{}]", job.pgid);
+            jobs.push(job);
+        } else if let Some(job) = run_foreground(job) {
+            jobs.push(job);
+        }
+    }
+}
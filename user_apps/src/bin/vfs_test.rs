@@ -0,0 +1,59 @@
+#![no_std]
+#![no_main]
+
+#[macro_use]
+extern crate user_lib;
+
+use user_lib::syscall::{
+    sys_chdir, sys_close, sys_getcwd, sys_mount, sys_open, sys_read, sys_umount2, sys_write,
+    O_RDONLY, O_WRONLY,
+};
+
+fn cwd(buf: &mut [u8]) -> &str {
+    let len = sys_getcwd(buf);
+    assert!(len > 0);
+    core::str::from_utf8(&buf[..len as usize - 1]).unwrap()
+}
+
+#[no_mangle]
+fn main(_argc: usize, _argv: &[&str]) -> i32 {
+    let mut buf = [0xffu8; 64];
+    assert_eq!(cwd(&mut buf), "/");
+    assert_eq!(sys_getcwd(&mut buf[..1]), -1);
+
+    // init mounts devfs at /dev; a second mount there fails
+    assert_eq!(sys_mount("none\0", "/dev\0", "devfs\0"), -1);
+    assert_eq!(sys_mount("none\0", "/dev\0", "nofs\0"), -1);
+    assert_eq!(sys_chdir("/dev\0"), 0);
+    assert_eq!(cwd(&mut buf), "/dev");
+    let fd = sys_open("zero\0", O_RDONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_read(fd as usize, &mut buf[..8]), 8);
+    assert!(buf[..8].iter().all(|&b| b == 0));
+    sys_close(fd as usize);
+    let fd = sys_open("./null\0", O_WRONLY);
+    assert!(fd > 0);
+    assert_eq!(sys_write(fd as usize, b"gone"), 4);
+    assert_eq!(sys_read(fd as usize, &mut buf), -1);
+    sys_close(fd as usize);
+    // files are not directories
+    assert_eq!(sys_chdir("null\0"), -1);
+
+    // `..` leaves the mount, and stops at the root
+    assert_eq!(sys_chdir("../../..\0"), 0);
+    assert_eq!(cwd(&mut buf), "/");
+    let fd = sys_open("dev/../dev/./null\0", O_RDONLY);
+    assert!(fd > 0);
+    sys_close(fd as usize);
+
+    // a second devfs can come and go
+    assert_eq!(sys_mount("none\0", "/dev/zero\0", "devfs\0"), -1);
+    assert_eq!(sys_mount("none\0", "/devices\0", "devfs\0"), 0);
+    assert!(sys_open("/devices/null\0", O_RDONLY) > 0);
+    assert_eq!(sys_umount2("/\0", 0), -1);
+    assert_eq!(sys_umount2("/devices\0", 0), 0);
+    assert_eq!(sys_umount2("/devices\0", 0), -1);
+    assert_eq!(sys_open("/devices/null\0", O_RDONLY), -1);
+    println!("vfs_test passed!");
+    0
+}
@@ -1,6 +1,6 @@
 use core::fmt::{self, Write};
 
-use crate::syscall::sys_write;
+use crate::syscall::{sys_read, sys_write};
 
 struct Stdout;
 
@@ -30,3 +30,16 @@ macro_rules! println {
         $crate::console::print(format_args!(concat!($fmt, "\n") $(, $($arg)+)?));
     }
 }
+
+const STDIN: usize = 0;
+
+/// Block until a byte arrives on stdin, or return `None` at end of input or
+/// if the read fails, e.g. because a signal interrupted it.
+pub fn getchar() -> Option<u8> {
+    let mut c = [0u8; 1];
+    if sys_read(STDIN, &mut c) == 1 {
+        Some(c[0])
+    } else {
+        None
+    }
+}
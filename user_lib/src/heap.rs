@@ -0,0 +1,50 @@
+//! Heap allocator grown on demand through `sys_sbrk`
+
+use core::{
+    alloc::{GlobalAlloc, Layout},
+    ptr::{null_mut, NonNull},
+};
+
+use buddy_system_allocator::LockedHeap;
+
+use crate::syscall::sys_sbrk;
+
+const PAGE_SIZE: usize = 0x1000;
+/// Grow the heap by at least this much at a time.
+const HEAP_GROW_SIZE: usize = 16 * PAGE_SIZE;
+
+#[global_allocator]
+static HEAP: SbrkHeap = SbrkHeap(LockedHeap::empty());
+
+struct SbrkHeap(LockedHeap<32>);
+
+unsafe impl GlobalAlloc for SbrkHeap {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let mut heap = self.0.lock();
+        if let Ok(ptr) = heap.alloc(layout) {
+            return ptr.as_ptr();
+        }
+        // leave room for aligning the block inside the new memory
+        let Some(size) = layout
+            .size()
+            .checked_add(layout.align())
+            .and_then(|size| size.max(HEAP_GROW_SIZE).checked_next_multiple_of(PAGE_SIZE))
+        else {
+            return null_mut();
+        };
+        // the break moves by at most `i32::MAX` bytes at a time
+        let Ok(increment) = i32::try_from(size) else {
+            return null_mut();
+        };
+        let start = sys_sbrk(increment);
+        if start < 0 {
+            return null_mut();
+        }
+        heap.add_to_heap(start as usize, start as usize + size);
+        heap.alloc(layout).map_or(null_mut(), |ptr| ptr.as_ptr())
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.0.lock().dealloc(NonNull::new_unchecked(ptr), layout)
+    }
+}
@@ -1,9 +1,19 @@
 #![no_std]
 #![no_main]
 
+extern crate alloc;
+
 pub mod console;
+mod heap;
+pub mod sync;
 pub mod syscall;
 
+/// Arguments beyond this are dropped by `_start`.
+pub const MAX_ARGS: usize = 16;
+
+/// `envp` handed over by the kernel, a NULL-terminated array of C strings
+static mut ENVP: usize = 0;
+
 #[panic_handler]
 fn panic_handler(panic_info: &core::panic::PanicInfo) -> ! {
     println!("[user_lib] {}", panic_info);
@@ -21,15 +31,117 @@ fn clear_bss() {
     });
 }
 
+/// Borrow the NUL-terminated string at `ptr`.
+unsafe fn c_str(ptr: usize) -> &'static str {
+    let start = ptr as *const u8;
+    let mut len = 0;
+    while start.add(len).read_volatile() != 0 {
+        len += 1;
+    }
+    core::str::from_utf8(core::slice::from_raw_parts(start, len)).unwrap()
+}
+
 #[no_mangle]
 #[link_section = ".text.entry"]
-pub extern "C" fn _start() -> ! {
+pub extern "C" fn _start(argc: usize, argv: usize, envp: usize) -> ! {
     extern "Rust" {
         fn main(_argc: usize, _argv: &[&str]) -> i32;
     }
     clear_bss();
+    let argc = argc.min(MAX_ARGS);
+    let mut args = [""; MAX_ARGS];
+    for (i, arg) in args[..argc].iter_mut().enumerate() {
+        *arg = unsafe { c_str(*(argv as *const usize).add(i)) };
+    }
     unsafe {
-        syscall::sys_exit(main(0, &[]));
+        ENVP = envp;
+        syscall::sys_exit(main(argc, &args[..argc]));
     }
     unreachable!("user_lib: _start returned!")
 }
+
+/// Look up `name` in the environment passed to `sys_exec`.
+pub fn getenv(name: &str) -> Option<&'static str> {
+    let mut envp = unsafe { ENVP } as *const usize;
+    if envp.is_null() {
+        return None;
+    }
+    loop {
+        let env = unsafe { envp.read() };
+        if env == 0 {
+            return None;
+        }
+        let env = unsafe { c_str(env) };
+        if let Some((key, value)) = env.split_once('=') {
+            if key == name {
+                return Some(value);
+            }
+        }
+        envp = unsafe { envp.add(1) };
+    }
+}
+
+/// Catch signal `signum` with `handler`, which gets the signal number and
+/// returns to where the process was interrupted.
+pub fn signal(signum: usize, handler: extern "C" fn(usize)) -> isize {
+    let action = syscall::SignalAction {
+        handler: handler as usize,
+        mask: 0,
+        restorer: sigreturn as *const () as usize,
+    };
+    syscall::sys_sigaction(signum, Some(&action), None)
+}
+
+/// The `restorer` of every handler set through [`signal`]
+extern "C" fn sigreturn() -> ! {
+    syscall::sys_sigreturn();
+    unreachable!("sigreturn returned!")
+}
+
+/// Return the foreground process group of the terminal at `fd`.
+pub fn tcgetpgrp(fd: usize) -> isize {
+    let mut pgid: i32 = 0;
+    match syscall::sys_ioctl(fd, syscall::TIOCGPGRP, &mut pgid as *mut i32 as usize) {
+        0 => pgid as isize,
+        err => err,
+    }
+}
+
+/// Make `pgid` the foreground process group of the terminal at `fd`, which
+/// Ctrl-C and Ctrl-Z send SIGINT and SIGTSTP to.
+pub fn tcsetpgrp(fd: usize, pgid: usize) -> isize {
+    let pgid = pgid as i32;
+    syscall::sys_ioctl(fd, syscall::TIOCSPGRP, &pgid as *const i32 as usize)
+}
+
+/// Block until any child exits, returning its pid, or -1 if there are no
+/// children.
+pub fn wait(exit_code: &mut i32) -> isize {
+    waitpid(-1, exit_code)
+}
+
+/// Block until the child `pid` exits, returning `pid`, or -1 if it is not a
+/// child of the caller.
+pub fn waitpid(pid: isize, exit_code: &mut i32) -> isize {
+    loop {
+        match syscall::sys_waitpid(pid, exit_code as *mut i32, 0) {
+            -2 => {
+                syscall::sys_yield();
+            }
+            exit_pid => return exit_pid,
+        }
+    }
+}
+
+/// Block until the thread `tid` exits, returning `tid`, or -1 if it cannot be
+/// waited for.
+pub fn waittid(tid: usize, exit_code: &mut i32) -> isize {
+    loop {
+        match syscall::sys_waittid(tid, exit_code as *mut i32) {
+            -2 => {
+                syscall::sys_yield();
+            }
+            exit_tid => return exit_tid,
+        }
+    }
+}
@@ -0,0 +1,49 @@
+//! Locks built on futexes, which only enter the kernel under contention
+
+use core::sync::atomic::{AtomicU32, Ordering};
+
+use crate::syscall::{sys_futex, FUTEX_WAIT, FUTEX_WAKE};
+
+const UNLOCKED: u32 = 0;
+const LOCKED: u32 = 1;
+/// locked, and some thread may be sleeping on it
+const CONTENDED: u32 = 2;
+
+/// A mutual exclusion lock for the threads of a process
+pub struct FutexMutex {
+    state: AtomicU32,
+}
+
+impl FutexMutex {
+    pub const fn new() -> Self {
+        Self {
+            state: AtomicU32::new(UNLOCKED),
+        }
+    }
+
+    pub fn lock(&self) {
+        if self
+            .state
+            .compare_exchange(UNLOCKED, LOCKED, Ordering::Acquire, Ordering::Relaxed)
+            .is_ok()
+        {
+            return;
+        }
+        // whoever unlocks it must wake us up
+        while self.state.swap(CONTENDED, Ordering::Acquire) != UNLOCKED {
+            sys_futex(&self.state, FUTEX_WAIT, CONTENDED, None);
+        }
+    }
+
+    pub fn unlock(&self) {
+        if self.state.swap(UNLOCKED, Ordering::Release) == CONTENDED {
+            sys_futex(&self.state, FUTEX_WAKE, 1, None);
+        }
+    }
+}
+
+impl Default for FutexMutex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,11 +1,55 @@
-use core::arch::asm;
+use core::{arch::asm, sync::atomic::AtomicU32};
 
+const SYSCALL_GETCWD: usize = 17;
+const SYSCALL_DUP: usize = 23;
+const SYSCALL_IOCTL: usize = 29;
+const SYSCALL_MKDIRAT: usize = 34;
+const SYSCALL_UNLINKAT: usize = 35;
+const SYSCALL_LINKAT: usize = 37;
+const SYSCALL_UMOUNT2: usize = 39;
+const SYSCALL_MOUNT: usize = 40;
+const SYSCALL_CHDIR: usize = 49;
+const SYSCALL_OPEN: usize = 56;
+const SYSCALL_CLOSE: usize = 57;
+const SYSCALL_PIPE: usize = 59;
+const SYSCALL_GETDENTS64: usize = 61;
+const SYSCALL_READ: usize = 63;
 const SYSCALL_WRITE: usize = 64;
+const SYSCALL_FSTAT: usize = 80;
 const SYSCALL_EXIT: usize = 93;
+const SYSCALL_FUTEX: usize = 98;
+const SYSCALL_NANOSLEEP: usize = 101;
 const SYSCALL_YIELD: usize = 124;
+const SYSCALL_KILL: usize = 129;
+const SYSCALL_SIGACTION: usize = 134;
+const SYSCALL_SIGPROCMASK: usize = 135;
+const SYSCALL_SIGRETURN: usize = 139;
+const SYSCALL_SET_PRIORITY: usize = 140;
+const SYSCALL_TIMES: usize = 153;
+const SYSCALL_SETPGID: usize = 154;
+const SYSCALL_GETPGID: usize = 155;
+const SYSCALL_GETRUSAGE: usize = 165;
 const SYSCALL_GET_TIME: usize = 169;
+const SYSCALL_GETPID: usize = 172;
+const SYSCALL_GETTID: usize = 178;
+const SYSCALL_SBRK: usize = 214;
+const SYSCALL_MUNMAP: usize = 215;
 const SYSCALL_FORK: usize = 220;
 const SYSCALL_EXEC: usize = 221;
+const SYSCALL_MMAP: usize = 222;
+const SYSCALL_WAITPID: usize = 260;
+const SYSCALL_RENAMEAT2: usize = 276;
+const SYSCALL_THREAD_CREATE: usize = 1000;
+const SYSCALL_WAITTID: usize = 1002;
+const SYSCALL_MUTEX_CREATE: usize = 1010;
+const SYSCALL_MUTEX_LOCK: usize = 1011;
+const SYSCALL_MUTEX_UNLOCK: usize = 1012;
+const SYSCALL_SEMAPHORE_CREATE: usize = 1020;
+const SYSCALL_SEMAPHORE_UP: usize = 1021;
+const SYSCALL_SEMAPHORE_DOWN: usize = 1022;
+const SYSCALL_CONDVAR_CREATE: usize = 1030;
+const SYSCALL_CONDVAR_SIGNAL: usize = 1031;
+const SYSCALL_CONDVAR_WAIT: usize = 1032;
 
 fn syscall(id: usize, args: [usize; 3]) -> isize {
     let mut ret: isize;
@@ -21,6 +65,236 @@ fn syscall(id: usize, args: [usize; 3]) -> isize {
     ret
 }
 
+fn syscall6(id: usize, args: [usize; 6]) -> isize {
+    let mut ret: isize;
+    unsafe {
+        asm!(
+            "ecall",
+            inlateout("x10") args[0] => ret,
+            in("x11") args[1],
+            in("x12") args[2],
+            in("x13") args[3],
+            in("x14") args[4],
+            in("x15") args[5],
+            in("x17") id
+        );
+    }
+    ret
+}
+
+/// Copy the cwd, NUL-terminated, into `buf`, returning the length copied or
+/// -1 if it does not fit.
+pub fn sys_getcwd(buf: &mut [u8]) -> isize {
+    syscall(SYSCALL_GETCWD, [buf.as_mut_ptr() as usize, buf.len(), 0])
+}
+
+pub fn sys_dup(fd: usize) -> isize {
+    syscall(SYSCALL_DUP, [fd, 0, 0])
+}
+
+/// `request` of [`sys_ioctl`]: store the foreground process group of the
+/// terminal in the `i32` at `arg`
+pub const TIOCGPGRP: usize = 0x540f;
+/// `request` of [`sys_ioctl`]: make the `i32` at `arg` the foreground process
+/// group of the terminal
+pub const TIOCSPGRP: usize = 0x5410;
+
+/// Control the device at `fd`. See also [`crate::tcsetpgrp`].
+pub fn sys_ioctl(fd: usize, request: usize, arg: usize) -> isize {
+    syscall(SYSCALL_IOCTL, [fd, request, arg])
+}
+
+/// `dirfd` meaning the cwd. It is the only one supported for relative paths.
+pub const AT_FDCWD: isize = -100;
+/// `sys_unlinkat` removes an empty directory rather than a file.
+pub const AT_REMOVEDIR: u32 = 0x200;
+
+/// `path` must end with `\0`.
+pub fn sys_mkdirat(dirfd: isize, path: &str, mode: u32) -> isize {
+    syscall(
+        SYSCALL_MKDIRAT,
+        [dirfd as usize, path.as_ptr() as usize, mode as usize],
+    )
+}
+
+/// `path` must end with `\0`.
+pub fn sys_unlinkat(dirfd: isize, path: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UNLINKAT,
+        [dirfd as usize, path.as_ptr() as usize, flags as usize],
+    )
+}
+
+/// Both paths must end with `\0`.
+pub fn sys_linkat(
+    old_dirfd: isize,
+    old_path: &str,
+    new_dirfd: isize,
+    new_path: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_LINKAT,
+        [
+            old_dirfd as usize,
+            old_path.as_ptr() as usize,
+            new_dirfd as usize,
+            new_path.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+/// Both paths must end with `\0`.
+pub fn sys_renameat2(
+    old_dirfd: isize,
+    old_path: &str,
+    new_dirfd: isize,
+    new_path: &str,
+    flags: u32,
+) -> isize {
+    syscall6(
+        SYSCALL_RENAMEAT2,
+        [
+            old_dirfd as usize,
+            old_path.as_ptr() as usize,
+            new_dirfd as usize,
+            new_path.as_ptr() as usize,
+            flags as usize,
+            0,
+        ],
+    )
+}
+
+pub const DT_DIR: u8 = 4;
+pub const DT_REG: u8 = 8;
+
+/// Fill `buf` with `struct linux_dirent64` entries of the directory at `fd`,
+/// returning the length filled, 0 at the end, or -1. See [`Dirents`].
+pub fn sys_getdents64(fd: usize, buf: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_GETDENTS64,
+        [fd, buf.as_mut_ptr() as usize, buf.len()],
+    )
+}
+
+/// The entries in a buffer filled by [`sys_getdents64`], as (inode number,
+/// `DT_*` type, name)
+pub struct Dirents<'a>(&'a [u8]);
+
+impl<'a> Dirents<'a> {
+    pub fn new(buf: &'a [u8]) -> Self {
+        Self(buf)
+    }
+}
+
+impl<'a> Iterator for Dirents<'a> {
+    type Item = (u64, u8, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.0.is_empty() {
+            return None;
+        }
+        let ino = u64::from_ne_bytes(self.0[..8].try_into().unwrap());
+        let reclen = u16::from_ne_bytes(self.0[16..18].try_into().unwrap()) as usize;
+        let d_type = self.0[18];
+        let name = &self.0[19..reclen];
+        let name = &name[..name.iter().position(|&b| b == 0).unwrap()];
+        self.0 = &self.0[reclen..];
+        Some((ino, d_type, core::str::from_utf8(name).unwrap()))
+    }
+}
+
+pub const S_IFMT: u32 = 0o170000;
+pub const S_IFIFO: u32 = 0o010000;
+pub const S_IFCHR: u32 = 0o020000;
+pub const S_IFDIR: u32 = 0o040000;
+pub const S_IFREG: u32 = 0o100000;
+
+/// `struct stat` as Linux lays it out on riscv64
+#[repr(C)]
+#[derive(Default)]
+pub struct Stat {
+    pub dev: u64,
+    pub ino: u64,
+    /// the file type, one of `S_IF*`, and the permission bits
+    pub mode: u32,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub rdev: u64,
+    pad1: u64,
+    pub size: i64,
+    pub blksize: i32,
+    pad2: i32,
+    pub blocks: i64,
+    pub atime_sec: i64,
+    pub atime_nsec: i64,
+    pub mtime_sec: i64,
+    pub mtime_nsec: i64,
+    pub ctime_sec: i64,
+    pub ctime_nsec: i64,
+    unused: [u32; 2],
+}
+
+pub fn sys_fstat(fd: usize, stat: &mut Stat) -> isize {
+    syscall(SYSCALL_FSTAT, [fd, stat as *mut Stat as usize, 0])
+}
+
+/// All strings must end with `\0`.
+pub fn sys_mount(source: &str, target: &str, fs_type: &str) -> isize {
+    syscall(
+        SYSCALL_MOUNT,
+        [
+            source.as_ptr() as usize,
+            target.as_ptr() as usize,
+            fs_type.as_ptr() as usize,
+        ],
+    )
+}
+
+/// `target` must end with `\0`.
+pub fn sys_umount2(target: &str, flags: u32) -> isize {
+    syscall(
+        SYSCALL_UMOUNT2,
+        [target.as_ptr() as usize, flags as usize, 0],
+    )
+}
+
+/// `path` must end with `\0`.
+pub fn sys_chdir(path: &str) -> isize {
+    syscall(SYSCALL_CHDIR, [path.as_ptr() as usize, 0, 0])
+}
+
+pub const O_RDONLY: u32 = 0;
+pub const O_WRONLY: u32 = 1 << 0;
+pub const O_RDWR: u32 = 1 << 1;
+pub const O_CREATE: u32 = 1 << 9;
+pub const O_TRUNC: u32 = 1 << 10;
+pub const O_APPEND: u32 = 1 << 11;
+
+/// `path` must end with `\0`. Returns the new fd, or -1.
+pub fn sys_open(path: &str, flags: u32) -> isize {
+    syscall(SYSCALL_OPEN, [path.as_ptr() as usize, flags as usize, 0])
+}
+
+pub fn sys_close(fd: usize) -> isize {
+    syscall(SYSCALL_CLOSE, [fd, 0, 0])
+}
+
+/// Store the read end fd in `pipe[0]` and the write end fd in `pipe[1]`.
+pub fn sys_pipe(pipe: &mut [usize; 2]) -> isize {
+    syscall(SYSCALL_PIPE, [pipe.as_mut_ptr() as usize, 0, 0])
+}
+
+pub fn sys_read(fd: usize, buffer: &mut [u8]) -> isize {
+    syscall(
+        SYSCALL_READ,
+        [fd, buffer.as_mut_ptr() as usize, buffer.len()],
+    )
+}
+
 pub fn sys_write(fd: usize, buffer: &[u8]) -> isize {
     syscall(SYSCALL_WRITE, [fd, buffer.as_ptr() as usize, buffer.len()])
 }
@@ -37,10 +311,346 @@ pub fn sys_get_time() -> isize {
     syscall(SYSCALL_GET_TIME, [0, 0, 0])
 }
 
+/// A span of time, like Linux's `struct timespec`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeSpec {
+    pub sec: usize,
+    pub nsec: usize,
+}
+
+/// Sleep for `req`, off the CPU. Returns 0, or -1 if `req` is invalid or a
+/// signal cut the sleep short, in which case the time left is stored in
+/// `rem`.
+pub fn sys_nanosleep(req: &TimeSpec, rem: Option<&mut TimeSpec>) -> isize {
+    syscall(
+        SYSCALL_NANOSLEEP,
+        [
+            req as *const TimeSpec as usize,
+            rem.map_or(0, |rem| rem as *mut TimeSpec as usize),
+            0,
+        ],
+    )
+}
+
+/// Sleep for `ms` milliseconds, off the CPU.
+pub fn sys_sleep_ms(ms: usize) -> isize {
+    let req = TimeSpec {
+        sec: ms / 1000,
+        nsec: ms % 1000 * 1_000_000,
+    };
+    sys_nanosleep(&req, None)
+}
+
+/// `op` of [`sys_futex`]: sleep while the word holds `val`
+pub const FUTEX_WAIT: usize = 0;
+/// `op` of [`sys_futex`]: wake up at most `val` sleepers
+pub const FUTEX_WAKE: usize = 1;
+
+/// Sleep on the futex word at `uaddr` while it holds `val`, or wake up to
+/// `val` sleepers on it, as `op` says.
+///
+/// `FUTEX_WAIT` returns 0 once woken, and -1 at once if the word does not
+/// hold `val`, once `timeout`, if any, has passed, or if a signal interrupts
+/// the wait. `FUTEX_WAKE` returns how many it woke.
+pub fn sys_futex(uaddr: &AtomicU32, op: usize, val: u32, timeout: Option<&TimeSpec>) -> isize {
+    syscall6(
+        SYSCALL_FUTEX,
+        [
+            uaddr.as_ptr() as usize,
+            op,
+            val as usize,
+            timeout.map_or(0, |timeout| timeout as *const TimeSpec as usize),
+            0,
+            0,
+        ],
+    )
+}
+
+/// Set the priority of the calling process, at least 2, which gets CPU time
+/// in proportion to it. Returns `prio`, or -1 if it is too low.
+pub fn sys_set_priority(prio: isize) -> isize {
+    syscall(SYSCALL_SET_PRIORITY, [prio as usize, 0, 0])
+}
+
+/// CPU times in timer ticks, like Linux's `struct tms`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Tms {
+    pub utime: usize,
+    pub stime: usize,
+    /// of the children reaped so far, with theirs
+    pub cutime: usize,
+    pub cstime: usize,
+}
+
+/// Store the CPU times of the calling process in `tms`. Returns the current
+/// time, as [`sys_get_time`] does, or -1.
+pub fn sys_times(tms: &mut Tms) -> isize {
+    syscall(SYSCALL_TIMES, [tms as *mut Tms as usize, 0, 0])
+}
+
+/// A span of time, like Linux's `struct timeval`
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TimeVal {
+    pub sec: usize,
+    pub usec: usize,
+}
+
+/// Linux's `struct rusage`. The kernel keeps only the times and the context
+/// switch counts, and leaves the rest zero.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct RUsage {
+    pub utime: TimeVal,
+    pub stime: TimeVal,
+    pub maxrss: usize,
+    pub ixrss: usize,
+    pub idrss: usize,
+    pub isrss: usize,
+    pub minflt: usize,
+    pub majflt: usize,
+    pub nswap: usize,
+    pub inblock: usize,
+    pub oublock: usize,
+    pub msgsnd: usize,
+    pub msgrcv: usize,
+    pub nsignals: usize,
+    /// voluntary context switches, by yielding or blocking
+    pub nvcsw: usize,
+    /// involuntary context switches, at the end of a time slice
+    pub nivcsw: usize,
+}
+
+/// `who` of [`sys_getrusage`]: the calling process
+pub const RUSAGE_SELF: isize = 0;
+/// `who` of [`sys_getrusage`]: its children reaped so far, with theirs
+pub const RUSAGE_CHILDREN: isize = -1;
+
+/// Store the resource usage of `who` in `usage`. Returns 0, or -1 if `who`
+/// is not `RUSAGE_SELF` or `RUSAGE_CHILDREN`.
+pub fn sys_getrusage(who: isize, usage: &mut RUsage) -> isize {
+    syscall(
+        SYSCALL_GETRUSAGE,
+        [who as usize, usage as *mut RUsage as usize, 0],
+    )
+}
+
+pub fn sys_getpid() -> isize {
+    syscall(SYSCALL_GETPID, [0, 0, 0])
+}
+
+pub fn sys_gettid() -> isize {
+    syscall(SYSCALL_GETTID, [0, 0, 0])
+}
+
+/// Move the process `pid` (0 for the caller), the caller or one of its
+/// children, into the process group `pgid`, or with `pgid` 0 into a new group
+/// of its own.
+pub fn sys_setpgid(pid: usize, pgid: usize) -> isize {
+    syscall(SYSCALL_SETPGID, [pid, pgid, 0])
+}
+
+/// Return the process group of the process `pid`, 0 for the caller.
+pub fn sys_getpgid(pid: usize) -> isize {
+    syscall(SYSCALL_GETPGID, [pid, 0, 0])
+}
+
 pub fn sys_fork() -> isize {
     syscall(SYSCALL_FORK, [0, 0, 0])
 }
 
-pub fn sys_exec(path: *const u8) -> isize {
-    syscall(SYSCALL_EXEC, [path as usize, 0, 0])
+/// `path` must end with `\0`, and `argv`/`envp` must end with a null pointer.
+pub fn sys_exec(path: &str, argv: &[*const u8], envp: &[*const u8]) -> isize {
+    syscall(
+        SYSCALL_EXEC,
+        [
+            path.as_ptr() as usize,
+            argv.as_ptr() as usize,
+            envp.as_ptr() as usize,
+        ],
+    )
+}
+
+/// `options` of [`sys_waitpid`]: also report children that have stopped
+pub const WUNTRACED: u32 = 2;
+
+/// Reap the child `pid`, or any child with -1, returning its pid, -1 if there
+/// is none, or -2 if it is still running. With `WUNTRACED`, a child that has
+/// stopped is reported too, see [`stop_signal`].
+pub fn sys_waitpid(pid: isize, exit_code: *mut i32, options: u32) -> isize {
+    syscall(
+        SYSCALL_WAITPID,
+        [pid as usize, exit_code as usize, options as usize],
+    )
+}
+
+/// Return the signal that stopped a child, if `status` from [`sys_waitpid`]
+/// reports a stop rather than an exit code.
+pub fn stop_signal(status: i32) -> Option<usize> {
+    let signum = (status >> 8) as usize;
+    (status & 0xff == 0x7f && (1..32).contains(&signum)).then_some(signum)
+}
+
+/// Start a thread in the calling process that runs `entry(arg)`, returning
+/// its tid. The thread ends by calling [`sys_exit`], which ends the whole
+/// process if it is the main thread.
+pub fn sys_thread_create(entry: fn(usize) -> !, arg: usize) -> isize {
+    syscall(SYSCALL_THREAD_CREATE, [entry as *const () as usize, arg, 0])
+}
+
+/// Collect the thread `tid` of the calling process once it has exited,
+/// returning `tid`, or -2 if it is still running and -1 if there is no such
+/// thread other than the caller and the main thread.
+pub fn sys_waittid(tid: usize, exit_code: *mut i32) -> isize {
+    syscall(SYSCALL_WAITTID, [tid, exit_code as usize, 0])
+}
+
+/// Create a mutex for the threads of the calling process, returning its id.
+/// Waiters for a `blocking` one sleep until it is theirs, and otherwise
+/// yield until it is free.
+pub fn sys_mutex_create(blocking: bool) -> isize {
+    syscall(SYSCALL_MUTEX_CREATE, [blocking as usize, 0, 0])
+}
+
+/// Lock the mutex `mutex_id`, failing with -1 if a signal interrupts the
+/// wait for it.
+pub fn sys_mutex_lock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_LOCK, [mutex_id, 0, 0])
+}
+
+/// Unlock the mutex `mutex_id`, failing with -1 if it is not locked.
+pub fn sys_mutex_unlock(mutex_id: usize) -> isize {
+    syscall(SYSCALL_MUTEX_UNLOCK, [mutex_id, 0, 0])
+}
+
+/// Create a semaphore with `count` resources, returning its id.
+pub fn sys_semaphore_create(count: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_CREATE, [count, 0, 0])
+}
+
+pub fn sys_semaphore_up(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_UP, [sem_id, 0, 0])
+}
+
+/// Take a resource of the semaphore `sem_id`, failing with -1 if a signal
+/// interrupts the wait for one.
+pub fn sys_semaphore_down(sem_id: usize) -> isize {
+    syscall(SYSCALL_SEMAPHORE_DOWN, [sem_id, 0, 0])
+}
+
+/// Create a condition variable, returning its id.
+pub fn sys_condvar_create() -> isize {
+    syscall(SYSCALL_CONDVAR_CREATE, [0, 0, 0])
+}
+
+pub fn sys_condvar_signal(condvar_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_SIGNAL, [condvar_id, 0, 0])
+}
+
+/// Wait on the condition variable `condvar_id` with the mutex `mutex_id`
+/// locked, which is unlocked while waiting and locked again before this
+/// returns. Returns -1 if a signal interrupts the wait.
+pub fn sys_condvar_wait(condvar_id: usize, mutex_id: usize) -> isize {
+    syscall(SYSCALL_CONDVAR_WAIT, [condvar_id, mutex_id, 0])
+}
+
+pub const PROT_READ: usize = 1 << 0;
+pub const PROT_WRITE: usize = 1 << 1;
+pub const PROT_EXEC: usize = 1 << 2;
+
+/// Map zeroed memory at the page-aligned `start`, returning 0 or -1.
+pub fn sys_mmap(start: usize, len: usize, prot: usize) -> isize {
+    syscall(SYSCALL_MMAP, [start, len, prot])
+}
+
+pub fn sys_munmap(start: usize, len: usize) -> isize {
+    syscall(SYSCALL_MUNMAP, [start, len, 0])
+}
+
+/// Move the program break by `increment` bytes, returning the old break or -1.
+pub fn sys_sbrk(increment: i32) -> isize {
+    syscall(SYSCALL_SBRK, [increment as usize, 0, 0])
+}
+
+pub const SIGHUP: usize = 1;
+pub const SIGINT: usize = 2;
+pub const SIGQUIT: usize = 3;
+pub const SIGILL: usize = 4;
+pub const SIGABRT: usize = 6;
+pub const SIGKILL: usize = 9;
+pub const SIGUSR1: usize = 10;
+pub const SIGSEGV: usize = 11;
+pub const SIGUSR2: usize = 12;
+pub const SIGPIPE: usize = 13;
+pub const SIGALRM: usize = 14;
+pub const SIGTERM: usize = 15;
+pub const SIGCHLD: usize = 17;
+pub const SIGCONT: usize = 18;
+pub const SIGSTOP: usize = 19;
+pub const SIGTSTP: usize = 20;
+
+/// `handler` for the default action
+pub const SIG_DFL: usize = 0;
+/// `handler` to ignore the signal
+pub const SIG_IGN: usize = 1;
+
+/// How to treat a signal, see [`sys_sigaction`]
+#[repr(C)]
+#[derive(Clone, Copy, Default)]
+pub struct SignalAction {
+    /// the handler's address, which gets the signal number, or `SIG_DFL` or
+    /// `SIG_IGN`
+    pub handler: usize,
+    /// signals blocked while the handler runs, signal `n` as bit `n`
+    pub mask: u32,
+    /// where the handler returns to, which must call [`sys_sigreturn`]
+    pub restorer: usize,
+}
+
+/// Send signal `signum` to the process `pid`, to the process group `-pid`,
+/// or with `pid` 0 to the caller's process group.
+pub fn sys_kill(pid: isize, signum: usize) -> isize {
+    syscall(SYSCALL_KILL, [pid as usize, signum, 0])
+}
+
+/// Set the action for `signum` unless `action` is `None`, storing the
+/// previous one in `old_action`. See also [`crate::signal`].
+pub fn sys_sigaction(
+    signum: usize,
+    action: Option<&SignalAction>,
+    old_action: Option<&mut SignalAction>,
+) -> isize {
+    syscall(
+        SYSCALL_SIGACTION,
+        [
+            signum,
+            action.map_or(0, |action| action as *const SignalAction as usize),
+            old_action.map_or(0, |action| action as *mut SignalAction as usize),
+        ],
+    )
+}
+
+pub const SIG_BLOCK: usize = 0;
+pub const SIG_UNBLOCK: usize = 1;
+pub const SIG_SETMASK: usize = 2;
+
+/// Change the signal mask by `set` as `how` says unless `set` is `None`,
+/// storing the previous mask in `old_set`.
+pub fn sys_sigprocmask(how: usize, set: Option<&u32>, old_set: Option<&mut u32>) -> isize {
+    syscall(
+        SYSCALL_SIGPROCMASK,
+        [
+            how,
+            set.map_or(0, |set| set as *const u32 as usize),
+            old_set.map_or(0, |set| set as *mut u32 as usize),
+        ],
+    )
+}
+
+/// Return from a signal handler. Only the `restorer` of a [`SignalAction`]
+/// should call this.
+pub fn sys_sigreturn() -> isize {
+    syscall(SYSCALL_SIGRETURN, [0, 0, 0])
 }